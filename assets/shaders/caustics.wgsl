@@ -0,0 +1,45 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import noisy_bevy::prelude
+
+struct CausticsMaterial {
+    color: vec4<f32>,
+    intensity: f32,
+    scale: f32,
+    speed: f32,
+    octaves: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: CausticsMaterial;
+
+struct FragmentInput {
+    #import bevy_pbr::mesh_vertex_output
+};
+
+// Bright ridges where the noise crosses zero, which is what light focused by waves looks like
+fn caustic_ridges(p: vec3<f32>) -> f32 {
+    return pow(1.0 - abs(simplex_noise_3d(p)), 6.0);
+}
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    let time = globals.time * material.speed;
+    var p = vec3<f32>(in.world_position.xz * material.scale, time);
+    var caustics = 0.0;
+    var amplitude = 1.0;
+
+    for (var octave = 0u; octave < material.octaves; octave = octave + 1u) {
+        // Two drifting layers multiplied together break up the pattern like real caustics
+        let a = caustic_ridges(p);
+        let b = caustic_ridges(p * vec3<f32>(1.3, 1.3, 0.8) + vec3<f32>(17.0, 5.0, 0.0));
+        caustics = caustics + a * b * amplitude;
+        p = p * 2.0;
+        amplitude = amplitude * 0.5;
+    }
+
+    // Only surfaces facing up catch light from the surface
+    let facing = clamp(in.world_normal.y, 0.0, 1.0);
+
+    return vec4<f32>(material.color.rgb * caustics * facing * material.intensity, 1.0);
+}
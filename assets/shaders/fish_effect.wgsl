@@ -0,0 +1,86 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::pbr_types
+#import bevy_pbr::utils
+#import bevy_pbr::clustered_forward
+#import bevy_pbr::lighting
+#import bevy_pbr::shadows
+#import bevy_pbr::fog
+#import bevy_pbr::pbr_functions
+#import noisy_bevy::prelude
+
+struct FishEffectMaterial {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    outline_color: vec4<f32>,
+    roughness: f32,
+    flash: f32,
+    dissolve: f32,
+    outline: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: FishEffectMaterial;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @builtin(position) frag_coord: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+};
+
+// How wide the glowing rim along the dissolve is
+const DISSOLVE_EDGE: f32 = 0.08;
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    // Bits of the fish drop out in noisy patches as the dissolve goes from 0 to 1
+    let noise = simplex_noise_3d(in.world_position.xyz * 4.0) * 0.5 + 0.5;
+    let dissolve = material.dissolve * (1.0 + DISSOLVE_EDGE);
+    if (noise < dissolve) {
+        discard;
+    }
+
+    var base_color = material.base_color;
+#ifdef VERTEX_UVS
+    base_color = base_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+#endif
+#ifdef VERTEX_COLORS
+    base_color = base_color * in.color;
+#endif
+
+    var pbr_input: PbrInput = pbr_input_new();
+    pbr_input.material.base_color = base_color;
+    pbr_input.material.emissive = material.emissive;
+    pbr_input.material.perceptual_roughness = material.roughness;
+    pbr_input.frag_coord = in.frag_coord;
+    pbr_input.world_position = in.world_position;
+    pbr_input.world_normal = prepare_world_normal(in.world_normal, false, in.is_front);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.N = normalize(pbr_input.world_normal);
+    pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+
+    var color = pbr(pbr_input);
+
+    if (fog.mode != FOG_MODE_OFF) {
+        color = apply_fog(color, in.world_position.xyz, view.world_position.xyz);
+    }
+
+    // A rim of light around the silhouette
+    let rim = pow(1.0 - max(dot(pbr_input.N, pbr_input.V), 0.0), 3.0);
+    let outline = material.outline_color.rgb * 4.0;
+    color = vec4<f32>(mix(color.rgb, outline, rim * material.outline), color.a);
+
+    // Hot white, so it still reads as a flash once bloom and tonemapping are done with it
+    color = vec4<f32>(mix(color.rgb, vec3<f32>(4.0), material.flash), color.a);
+
+    // The edge of the dissolve glows as it eats in
+    if (material.dissolve > 0.0 && noise < dissolve + DISSOLVE_EDGE) {
+        color = vec4<f32>(vec3<f32>(4.0, 2.0, 0.6), color.a);
+    }
+
+    return color;
+}
@@ -0,0 +1,72 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+
+// Bindings have to come before the functions that use them
+#import bevy_pbr::mesh_functions
+
+// Bevy's mesh vertex shader with the instance's transform on top of the mesh's. The fragment
+// shader is the material's own.
+struct Vertex {
+#ifdef VERTEX_POSITIONS
+    @location(0) position: vec3<f32>,
+#endif
+#ifdef VERTEX_NORMALS
+    @location(1) normal: vec3<f32>,
+#endif
+#ifdef VERTEX_UVS
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(3) tangent: vec4<f32>,
+#endif
+#ifdef VERTEX_COLORS
+    @location(4) color: vec4<f32>,
+#endif
+
+    @location(8) i_transform_0: vec4<f32>,
+    @location(9) i_transform_1: vec4<f32>,
+    @location(10) i_transform_2: vec4<f32>,
+    @location(11) i_transform_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let instance = mat4x4<f32>(
+        vertex.i_transform_0,
+        vertex.i_transform_1,
+        vertex.i_transform_2,
+        vertex.i_transform_3,
+    );
+    let model = mesh.model * instance;
+
+    var out: VertexOutput;
+
+#ifdef VERTEX_NORMALS
+    // Props are only ever scaled uniformly so the model matrix is fine for normals
+    out.world_normal = normalize((model * vec4<f32>(vertex.normal, 0.0)).xyz);
+#endif
+
+#ifdef VERTEX_POSITIONS
+    out.world_position = mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
+    out.clip_position = mesh_position_world_to_clip(out.world_position);
+#endif
+
+#ifdef VERTEX_UVS
+    out.uv = vertex.uv;
+#endif
+
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_tangent_local_to_world(model, vertex.tangent);
+#endif
+
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+
+    return out;
+}
@@ -0,0 +1,46 @@
+#import bevy_pbr::prepass_bindings
+#import bevy_pbr::mesh_functions
+
+// Bevy's prepass vertex shader with the instance's transform on top of the mesh's. Only used
+// for shadows, so there are no normals.
+struct Vertex {
+    @location(0) position: vec3<f32>,
+#ifdef VERTEX_UVS
+    @location(1) uv: vec2<f32>,
+#endif
+
+    @location(8) i_transform_0: vec4<f32>,
+    @location(9) i_transform_1: vec4<f32>,
+    @location(10) i_transform_2: vec4<f32>,
+    @location(11) i_transform_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+#ifdef VERTEX_UVS
+    @location(0) uv: vec2<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let instance = mat4x4<f32>(
+        vertex.i_transform_0,
+        vertex.i_transform_1,
+        vertex.i_transform_2,
+        vertex.i_transform_3,
+    );
+    let model = mesh.model * instance;
+
+    var out: VertexOutput;
+    out.clip_position = mesh_position_local_to_clip(model, vec4<f32>(vertex.position, 1.0));
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position.z = min(out.clip_position.z, 1.0);
+#endif
+
+#ifdef VERTEX_UVS
+    out.uv = vertex.uv;
+#endif
+
+    return out;
+}
@@ -0,0 +1,31 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import noisy_bevy::prelude
+
+struct LightShaftMaterial {
+    color: vec4<f32>,
+    intensity: f32,
+    seed: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: LightShaftMaterial;
+
+struct FragmentInput {
+    #import bevy_pbr::mesh_vertex_output
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    let pi = 3.14159265;
+
+    // Soft at the sides, strongest at the surface and fading out with depth
+    let across = sin(in.uv.x * pi);
+    let down = pow(1.0 - in.uv.y, 1.5);
+
+    // Slow shimmer as the waves overhead move
+    let shimmer = simplex_noise_2d(vec2<f32>(in.uv.x * 2.0 + material.seed, globals.time * 0.3 + material.seed));
+    let strength = across * down * (0.6 + 0.4 * shimmer) * material.intensity;
+
+    return vec4<f32>(material.color.rgb * strength, 1.0);
+}
@@ -0,0 +1,83 @@
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::mesh_functions
+
+#ifdef SKINNED
+#import bevy_pbr::skinning
+#endif
+
+struct SwayMaterial {
+    color: vec4<f32>,
+    strength: f32,
+    frequency: f32,
+    height: f32,
+    current: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> material: SwayMaterial;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef SKINNED
+    @location(5) joint_indices: vec4<u32>,
+    @location(6) joint_weights: vec4<f32>,
+#endif
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_normal: vec3<f32>,
+    @location(1) bend: f32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+#ifdef SKINNED
+    // The skeleton stays in its rest pose, the sway does the moving
+    let model = skin_model(vertex.joint_indices, vertex.joint_weights);
+#else
+    let model = mesh.model;
+#endif
+
+    var world_position = mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
+
+    // The base stays planted and the tip moves the most
+    let height = clamp(vertex.position.y / material.height, 0.0, 1.0);
+    let bend = height * height;
+
+    // Strands further along are a little behind, so a gust rolls across the seabed
+    let root = model[3].xyz;
+    let phase = dot(root.xz, vec2<f32>(0.35, 0.2));
+    let time = globals.time * material.frequency * 6.2831853;
+    let sway = vec2<f32>(sin(time + phase), sin(time * 0.7 + phase * 1.3) * 0.5);
+
+    let scale = length(model[1].xyz);
+    let offset = (sway * material.strength + material.current) * bend * scale;
+    world_position = world_position + vec4<f32>(offset.x, 0.0, offset.y, 0.0);
+
+    var out: VertexOutput;
+    out.clip_position = mesh_position_world_to_clip(world_position);
+    out.world_normal = normalize((model * vec4<f32>(vertex.normal, 0.0)).xyz);
+    out.bend = bend;
+    return out;
+}
+
+// Lambert lighting from the ambient light and the sun. Light colors already include their
+// intensity.
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var light = lights.ambient_color.rgb;
+
+    for (var i = 0u; i < lights.n_directional_lights; i = i + 1u) {
+        let sun = lights.directional_lights[i];
+        let diffuse = max(dot(in.world_normal, sun.direction_to_light), 0.0);
+        light = light + sun.color.rgb * diffuse / 3.14159265;
+    }
+
+    // Tips are younger and a bit brighter
+    let color = material.color.rgb * (0.85 + in.bend * 0.3);
+
+    return vec4<f32>(color * light, material.color.a);
+}
@@ -0,0 +1,749 @@
+use bevy::prelude::*;
+
+use crate::{
+    collision::HitGuard,
+    fishy_assets::FishType,
+    fixed_step::StepSet,
+    hazard::{Blinded, Hazard, Stunned},
+    hud::{Meter, MeterFill},
+    input::{Player, StepInput},
+    Fish, GameState, SimulationSet,
+};
+
+// Dash, boost and species specials, all paid for out of the stamina meter
+pub struct AbilitiesPlugin;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stamina>()
+            .add_event::<AbilityUsed>()
+            .add_system(setup_ability_assets.in_schedule(OnEnter(GameState::Playing)))
+            // Dashing and boosting change how fast the player swims, so they happen in steps
+            .add_systems(
+                (
+                    tick_cooldowns,
+                    use_dash,
+                    use_boost,
+                    use_special,
+                    regenerate_stamina,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(StepSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    blind_hazards_in_ink,
+                    animate_ability_effects,
+                    update_stamina_meter,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const DASH_COST: f32 = 25.0;
+const DASH_IMPULSE: f32 = 3.0;
+const BOOST_COST_PER_SECOND: f32 = 30.0;
+/// Once the boost has run dry it takes this much stamina to start it again, so it doesn't
+/// sputter on and off as the meter trickles back
+const BOOST_RESTART_STAMINA: f32 = 20.0;
+const BOOST_MULTIPLIER: f32 = 1.75;
+const SPECIAL_COST: f32 = 40.0;
+const INK_CLOUD_RADIUS: f32 = 3.0;
+const ELECTRIC_PULSE_RADIUS: f32 = 4.0;
+
+#[derive(Resource, Debug)]
+pub struct Stamina {
+    pub current: f32,
+
+    pub max: f32,
+
+    pub regen_per_second: f32,
+}
+
+impl Default for Stamina {
+    fn default() -> Stamina {
+        Stamina {
+            current: 100.0,
+            max: 100.0,
+            regen_per_second: 12.0,
+        }
+    }
+}
+
+impl Stamina {
+    /// Spends `amount` if there's enough of it. Returns whether it was spent.
+    pub fn spend(&mut self, amount: f32) -> bool {
+        if self.current < amount {
+            return false;
+        }
+
+        self.current -= amount;
+        true
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cooldown {
+    pub duration: f32,
+
+    pub remaining: f32,
+}
+
+impl Cooldown {
+    pub fn new(duration: f32) -> Cooldown {
+        Cooldown {
+            duration,
+            remaining: 0.0,
+        }
+    }
+
+    pub fn ready(&self) -> bool {
+        self.remaining <= 0.0
+    }
+
+    pub fn trigger(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    pub fn tick(&mut self, delta_seconds: f32) {
+        self.remaining = (self.remaining - delta_seconds).max(0.0);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Special {
+    /// Squids and octopuses leave a cloud of ink that blinds predators swimming through it
+    InkCloud,
+    /// Turtles duck into their shell to block the next hit
+    ShellGuard,
+    /// Eels stun everything around them
+    ElectricPulse,
+}
+
+impl Special {
+    pub fn for_fish(fish_type: FishType) -> Option<Special> {
+        match fish_type {
+            FishType::Squid | FishType::Octopus => Some(Special::InkCloud),
+            FishType::Turtle => Some(Special::ShellGuard),
+            FishType::Eel => Some(Special::ElectricPulse),
+            _ => None,
+        }
+    }
+
+    fn cooldown(&self) -> f32 {
+        match self {
+            Special::InkCloud => 6.0,
+            Special::ShellGuard => 10.0,
+            Special::ElectricPulse => 8.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Ability {
+    Dash,
+    Boost,
+    Special(Special),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AbilityUsed {
+    pub player: Entity,
+
+    pub ability: Ability,
+
+    pub position: Vec3,
+}
+
+#[derive(Component, Debug)]
+pub struct Abilities {
+    pub dash: Cooldown,
+
+    pub special: Cooldown,
+
+    pub boosting: bool,
+}
+
+impl Default for Abilities {
+    fn default() -> Abilities {
+        Abilities {
+            dash: Cooldown::new(1.0),
+            special: Cooldown::new(0.0),
+            boosting: false,
+        }
+    }
+}
+
+/// An area of ink that blinds any hazard inside it
+#[derive(Component, Debug)]
+pub struct InkCloud {
+    pub radius: f32,
+}
+
+/// A short lived visual that grows and fades out, like an ink cloud or an electric pulse
+#[derive(Component)]
+pub struct AbilityEffect {
+    pub timer: Timer,
+
+    pub start_scale: f32,
+
+    pub end_scale: f32,
+
+    pub start_alpha: f32,
+}
+
+#[derive(Resource)]
+pub struct AbilityAssets {
+    pub sphere_mesh: Handle<Mesh>,
+}
+
+fn setup_ability_assets(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(AbilityAssets {
+        sphere_mesh: meshes.add(
+            shape::UVSphere {
+                radius: 1.0,
+                ..default()
+            }
+            .into(),
+        ),
+    });
+}
+
+fn tick_cooldowns(mut query: Query<&mut Abilities>, fixed_time: Res<FixedTime>) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+
+    for mut abilities in query.iter_mut() {
+        abilities.dash.tick(delta_seconds);
+        abilities.special.tick(delta_seconds);
+    }
+}
+
+/// A press that can't be paid for yet is dropped rather than saved up for later
+fn use_dash(
+    mut query: Query<(
+        Entity,
+        &Transform,
+        &mut StepInput,
+        &mut Player,
+        &mut Abilities,
+    )>,
+    mut stamina: ResMut<Stamina>,
+    mut ability_used_events: EventWriter<AbilityUsed>,
+) {
+    for (entity, transform, mut step_input, mut player, mut abilities) in query.iter_mut() {
+        let pressed = std::mem::take(&mut step_input.dash);
+
+        if !pressed || !abilities.dash.ready() {
+            continue;
+        }
+
+        if !stamina.spend(DASH_COST) {
+            continue;
+        }
+
+        abilities.dash.trigger();
+        player.impulse(DASH_IMPULSE);
+        ability_used_events.send(AbilityUsed {
+            player: entity,
+            ability: Ability::Dash,
+            position: transform.translation,
+        });
+    }
+}
+
+/// Boosting raises the player's acceleration and top speed for as long as it's held and
+/// there's stamina left
+fn use_boost(
+    mut query: Query<(Entity, &Transform, &StepInput, &mut Player, &mut Abilities)>,
+    mut stamina: ResMut<Stamina>,
+    mut ability_used_events: EventWriter<AbilityUsed>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+
+    for (entity, transform, step_input, mut player, mut abilities) in query.iter_mut() {
+        let can_boost = abilities.boosting || stamina.current >= BOOST_RESTART_STAMINA;
+        let wants_boost =
+            step_input.boost && can_boost && stamina.spend(BOOST_COST_PER_SECOND * delta_seconds);
+
+        if wants_boost && !abilities.boosting {
+            player.speed_multiplier *= BOOST_MULTIPLIER;
+            ability_used_events.send(AbilityUsed {
+                player: entity,
+                ability: Ability::Boost,
+                position: transform.translation,
+            });
+        } else if !wants_boost && abilities.boosting {
+            player.speed_multiplier /= BOOST_MULTIPLIER;
+        }
+
+        abilities.boosting = wants_boost;
+    }
+}
+
+fn use_special(
+    mut commands: Commands,
+    mut player_query: Query<(
+        Entity,
+        &Fish,
+        &Transform,
+        &mut StepInput,
+        &mut Abilities,
+        &mut HitGuard,
+    )>,
+    hazard_query: Query<(Entity, &Transform), With<Hazard>>,
+    mut stamina: ResMut<Stamina>,
+    mut ability_used_events: EventWriter<AbilityUsed>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    ability_assets: Res<AbilityAssets>,
+) {
+    for (entity, fish, transform, mut step_input, mut abilities, mut guard) in
+        player_query.iter_mut()
+    {
+        let pressed = std::mem::take(&mut step_input.special);
+
+        if !pressed || !abilities.special.ready() {
+            continue;
+        }
+
+        let Some(special) = Special::for_fish(fish.fish_type) else {
+            continue;
+        };
+
+        if !stamina.spend(SPECIAL_COST) {
+            continue;
+        }
+
+        let position = transform.translation;
+
+        match special {
+            Special::InkCloud => {
+                commands.spawn((
+                    PbrBundle {
+                        mesh: ability_assets.sphere_mesh.clone(),
+                        material: materials.add(StandardMaterial {
+                            base_color: Color::rgba(0.05, 0.02, 0.1, 0.85),
+                            alpha_mode: AlphaMode::Blend,
+                            unlit: true,
+                            ..default()
+                        }),
+                        transform: Transform::from_translation(position),
+                        ..default()
+                    },
+                    InkCloud {
+                        radius: INK_CLOUD_RADIUS,
+                    },
+                    AbilityEffect {
+                        timer: Timer::from_seconds(4.0, TimerMode::Once),
+                        start_scale: INK_CLOUD_RADIUS * 0.5,
+                        end_scale: INK_CLOUD_RADIUS,
+                        start_alpha: 0.85,
+                    },
+                ));
+            }
+            Special::ShellGuard => {
+                guard.charges = guard.charges.max(1);
+            }
+            Special::ElectricPulse => {
+                for (hazard, hazard_transform) in hazard_query.iter() {
+                    if hazard_transform.translation.distance(position) < ELECTRIC_PULSE_RADIUS {
+                        commands.entity(hazard).insert(Stunned {
+                            timer: Timer::from_seconds(1.5, TimerMode::Once),
+                        });
+                    }
+                }
+
+                commands.spawn((
+                    PbrBundle {
+                        mesh: ability_assets.sphere_mesh.clone(),
+                        material: materials.add(StandardMaterial {
+                            base_color: Color::rgba(0.6, 0.9, 1.0, 0.6),
+                            emissive: Color::rgb(0.6, 0.9, 1.0),
+                            alpha_mode: AlphaMode::Blend,
+                            unlit: true,
+                            ..default()
+                        }),
+                        transform: Transform::from_translation(position),
+                        ..default()
+                    },
+                    AbilityEffect {
+                        timer: Timer::from_seconds(0.4, TimerMode::Once),
+                        start_scale: 0.5,
+                        end_scale: ELECTRIC_PULSE_RADIUS,
+                        start_alpha: 0.6,
+                    },
+                ));
+            }
+        }
+
+        abilities.special.duration = special.cooldown();
+        abilities.special.trigger();
+        ability_used_events.send(AbilityUsed {
+            player: entity,
+            ability: Ability::Special(special),
+            position,
+        });
+    }
+}
+
+fn regenerate_stamina(
+    query: Query<&Abilities, With<Player>>,
+    mut stamina: ResMut<Stamina>,
+    fixed_time: Res<FixedTime>,
+) {
+    // No regenerating while the boost is draining it
+    if query.iter().any(|abilities| abilities.boosting) {
+        return;
+    }
+
+    let delta_seconds = fixed_time.period.as_secs_f32();
+    stamina.current = (stamina.current + stamina.regen_per_second * delta_seconds).min(stamina.max);
+}
+
+fn blind_hazards_in_ink(
+    mut commands: Commands,
+    cloud_query: Query<(&Transform, &InkCloud)>,
+    hazard_query: Query<(Entity, &Transform), With<Hazard>>,
+) {
+    for (cloud_transform, cloud) in cloud_query.iter() {
+        for (hazard, hazard_transform) in hazard_query.iter() {
+            let distance = hazard_transform
+                .translation
+                .truncate()
+                .distance(cloud_transform.translation.truncate());
+
+            if distance < cloud.radius {
+                commands.entity(hazard).insert(Blinded {
+                    timer: Timer::from_seconds(2.0, TimerMode::Once),
+                });
+            }
+        }
+    }
+}
+
+fn animate_ability_effects(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &mut AbilityEffect,
+        &Handle<StandardMaterial>,
+    )>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut effect, material) in query.iter_mut() {
+        if effect.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let progress = effect.timer.percent();
+        let scale = effect.start_scale + (effect.end_scale - effect.start_scale) * progress;
+        transform.scale = Vec3::splat(scale);
+
+        if let Some(material) = materials.get_mut(material) {
+            material
+                .base_color
+                .set_a(effect.start_alpha * (1.0 - progress));
+        }
+    }
+}
+
+fn update_stamina_meter(stamina: Res<Stamina>, mut query: Query<&mut MeterFill>) {
+    if !stamina.is_changed() {
+        return;
+    }
+
+    for mut fill in query.iter_mut() {
+        if fill.meter == Meter::Stamina {
+            fill.fraction = stamina.fraction();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    /// Steps a tenth of a second long, so the numbers are easy to follow
+    const STEP: f32 = 0.1;
+
+    #[test]
+    fn cooldowns_are_ready_until_triggered() {
+        let mut cooldown = Cooldown::new(1.0);
+        assert!(cooldown.ready());
+
+        cooldown.trigger();
+        assert!(!cooldown.ready());
+
+        cooldown.tick(0.6);
+        assert!(!cooldown.ready());
+        assert!((cooldown.remaining - 0.4).abs() < TOLERANCE);
+
+        cooldown.tick(0.6);
+        assert!(cooldown.ready());
+        assert_eq!(cooldown.remaining, 0.0);
+    }
+
+    #[test]
+    fn stamina_is_only_spent_if_there_is_enough() {
+        let mut stamina = Stamina {
+            current: 30.0,
+            ..default()
+        };
+
+        assert!(stamina.spend(DASH_COST));
+        assert!((stamina.current - 5.0).abs() < TOLERANCE);
+        assert!(!stamina.spend(DASH_COST));
+        assert!((stamina.current - 5.0).abs() < TOLERANCE);
+    }
+
+    fn ability_app(stamina: f32) -> (App, Entity) {
+        let mut app = App::new();
+        app.insert_resource(FixedTime::new_from_secs(STEP))
+            .insert_resource(Stamina {
+                current: stamina,
+                ..default()
+            })
+            .add_event::<AbilityUsed>()
+            .add_systems((tick_cooldowns, use_dash, use_boost, regenerate_stamina).chain());
+
+        let player = app
+            .world
+            .spawn((
+                Transform::default(),
+                StepInput::default(),
+                Player::default(),
+                Abilities::default(),
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    fn set_input(app: &mut App, player: Entity, step_input: StepInput) {
+        *app.world.get_mut::<StepInput>(player).unwrap() = step_input;
+    }
+
+    fn stamina(app: &App) -> f32 {
+        app.world.resource::<Stamina>().current
+    }
+
+    fn boosting(app: &App, player: Entity) -> bool {
+        app.world.get::<Abilities>(player).unwrap().boosting
+    }
+
+    #[test]
+    fn dashing_costs_stamina_and_waits_for_the_cooldown() {
+        let (mut app, player) = ability_app(100.0);
+        let dash = StepInput {
+            dash: true,
+            ..default()
+        };
+
+        set_input(&mut app, player, dash);
+        app.update();
+        // Stamina regenerates for the step too
+        let regen = Stamina::default().regen_per_second * STEP;
+        assert!((stamina(&app) - (100.0 - DASH_COST + regen)).abs() < TOLERANCE);
+
+        // Still cooling down, so the press is dropped
+        set_input(&mut app, player, dash);
+        app.update();
+        assert!((stamina(&app) - (100.0 - DASH_COST + regen * 2.0)).abs() < TOLERANCE);
+        assert!(!app.world.get::<StepInput>(player).unwrap().dash);
+    }
+
+    #[test]
+    fn boosting_drains_stamina_and_stopping_refills_it() {
+        let (mut app, player) = ability_app(100.0);
+        let boost = StepInput {
+            boost: true,
+            ..default()
+        };
+
+        set_input(&mut app, player, boost);
+        app.update();
+        assert!(boosting(&app, player));
+        assert!((stamina(&app) - (100.0 - BOOST_COST_PER_SECOND * STEP)).abs() < TOLERANCE);
+        let player_speed = app.world.get::<Player>(player).unwrap().speed_multiplier;
+        assert!((player_speed - BOOST_MULTIPLIER).abs() < TOLERANCE);
+
+        set_input(&mut app, player, StepInput::default());
+        app.update();
+        assert!(!boosting(&app, player));
+        let regen = Stamina::default().regen_per_second * STEP;
+        assert!((stamina(&app) - (100.0 - BOOST_COST_PER_SECOND * STEP + regen)).abs() < TOLERANCE);
+        assert_eq!(
+            app.world.get::<Player>(player).unwrap().speed_multiplier,
+            1.0
+        );
+    }
+
+    #[test]
+    fn stamina_refills_up_to_the_max() {
+        let (mut app, _) = ability_app(99.9);
+
+        app.update();
+
+        assert_eq!(stamina(&app), Stamina::default().max);
+    }
+
+    #[test]
+    fn a_dry_boost_waits_for_the_restart_stamina() {
+        let (mut app, player) = ability_app(BOOST_RESTART_STAMINA);
+        let boost = StepInput {
+            boost: true,
+            ..default()
+        };
+
+        // Exactly enough to start, then hold it until it runs dry
+        set_input(&mut app, player, boost);
+        app.update();
+        assert!(boosting(&app, player));
+        for _ in 0..100 {
+            set_input(&mut app, player, boost);
+            app.update();
+
+            if !boosting(&app, player) {
+                break;
+            }
+        }
+        assert!(!boosting(&app, player));
+        assert!(stamina(&app) < BOOST_RESTART_STAMINA);
+
+        // Holding it down while the meter trickles back doesn't restart it
+        let mut restarted_at = None;
+        for _ in 0..100 {
+            let before = stamina(&app);
+            set_input(&mut app, player, boost);
+            app.update();
+
+            if boosting(&app, player) {
+                restarted_at = Some(before);
+                break;
+            }
+        }
+
+        let restarted_at = restarted_at.expect("the boost should restart once there's enough");
+        assert!(restarted_at >= BOOST_RESTART_STAMINA);
+        assert!(restarted_at < BOOST_RESTART_STAMINA + Stamina::default().regen_per_second * STEP);
+    }
+
+    fn special_app(fish_type: FishType) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .init_resource::<Stamina>()
+            .insert_resource(AbilityAssets {
+                sphere_mesh: Handle::default(),
+            })
+            .add_event::<AbilityUsed>()
+            .add_system(use_special);
+
+        let player = app
+            .world
+            .spawn((
+                Fish { fish_type },
+                Transform::default(),
+                StepInput {
+                    special: true,
+                    ..default()
+                },
+                Abilities::default(),
+                HitGuard::default(),
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    #[test]
+    fn every_species_with_a_special_can_use_it() {
+        for (fish_type, special) in [
+            (FishType::Squid, Special::InkCloud),
+            (FishType::Turtle, Special::ShellGuard),
+            (FishType::Eel, Special::ElectricPulse),
+        ] {
+            let (mut app, player) = special_app(fish_type);
+
+            app.update();
+
+            let abilities = app.world.get::<Abilities>(player).unwrap();
+            assert_eq!(abilities.special.duration, special.cooldown());
+            assert!(!abilities.special.ready());
+            assert!((stamina(&app) - (100.0 - SPECIAL_COST)).abs() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn ink_clouds_are_left_behind() {
+        let (mut app, _) = special_app(FishType::Octopus);
+
+        app.update();
+
+        let mut clouds = app.world.query::<&InkCloud>();
+        assert_eq!(clouds.iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn shell_guards_block_one_hit() {
+        let (mut app, player) = special_app(FishType::Turtle);
+
+        app.update();
+
+        let mut guard = *app.world.get::<HitGuard>(player).unwrap();
+        assert_eq!(guard.charges, 1);
+        assert!(guard.absorb());
+        assert!(!guard.absorb());
+    }
+
+    #[test]
+    fn electric_pulses_only_stun_nearby_hazards() {
+        let (mut app, _) = special_app(FishType::Eel);
+        let near = app
+            .world
+            .spawn((
+                Hazard::default(),
+                Transform::from_xyz(ELECTRIC_PULSE_RADIUS - 0.5, 0.0, 0.0),
+            ))
+            .id();
+        let far = app
+            .world
+            .spawn((
+                Hazard::default(),
+                Transform::from_xyz(ELECTRIC_PULSE_RADIUS + 0.5, 0.0, 0.0),
+            ))
+            .id();
+
+        app.update();
+
+        assert!(app.world.get::<Stunned>(near).is_some());
+        assert!(app.world.get::<Stunned>(far).is_none());
+    }
+
+    #[test]
+    fn specials_wait_for_enough_stamina() {
+        let (mut app, player) = special_app(FishType::Eel);
+        app.world.resource_mut::<Stamina>().current = SPECIAL_COST - 1.0;
+
+        app.update();
+
+        assert!(app.world.get::<Abilities>(player).unwrap().special.ready());
+        assert!(!app.world.get::<StepInput>(player).unwrap().special);
+    }
+}
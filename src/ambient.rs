@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    current::Drift,
+    fishy_assets::{FishAnimationCollection, FishCollection, FishType},
+    hazard::swim_rotation,
+    Bounds, Fish, FishBundle, GameState, InitialAnimation, SimulationSet,
+};
+
+// Harmless little fish swimming around behind the play plane to make the water feel alive
+pub struct AmbientPlugin;
+
+impl Plugin for AmbientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                // Waits for the first frame of play so that the bounds are known
+                spawn_ambient_fish.run_if(not(any_with_component::<AmbientFish>())),
+                swim_ambient_fish,
+                wrap_ambient_fish,
+            )
+                .chain()
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        );
+    }
+}
+
+const AMBIENT_FISH_COUNT: usize = 10;
+const AMBIENT_SPECIES: [FishType; 3] =
+    [FishType::ClownFish, FishType::DoryFish, FishType::BrownFish];
+
+#[derive(Component, Debug)]
+pub struct AmbientFish {
+    /// Swimming speed along x, negative for leftwards
+    pub speed: f32,
+}
+
+fn spawn_ambient_fish(
+    mut commands: Commands,
+    bounds: Res<Bounds>,
+    fish_collection: Res<FishCollection>,
+    animation_collection: Res<FishAnimationCollection>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..AMBIENT_FISH_COUNT {
+        let fish_type = *AMBIENT_SPECIES.choose(&mut rng).unwrap();
+        let animations = fish_type.animations_from(&animation_collection);
+        let moving_right = rng.gen_bool(0.5);
+        let speed = rng.gen_range(0.5..1.5);
+        let x = rng.gen_range(bounds.min.x..bounds.max.x);
+        let y = rng.gen_range(bounds.min.y..bounds.max.y);
+        let z = rng.gen_range(-6.0..-3.0);
+
+        commands.spawn((
+            InitialAnimation {
+                animation: animations.moving.unwrap_or(animations.idle),
+                repeat: true,
+            },
+            FishBundle {
+                fish: Fish { fish_type },
+                scene: SceneBundle {
+                    scene: fish_type.model_from(&fish_collection),
+                    transform: Transform::from_xyz(x, y, z)
+                        .with_rotation(swim_rotation(fish_type, moving_right))
+                        .with_scale(Vec3::splat(rng.gen_range(0.8..1.4))),
+                    ..default()
+                },
+            },
+            AmbientFish {
+                speed: if moving_right { speed } else { -speed },
+            },
+            Drift::default(),
+        ));
+    }
+}
+
+fn swim_ambient_fish(mut query: Query<(&mut Transform, &AmbientFish)>, time: Res<Time>) {
+    for (mut transform, ambient_fish) in query.iter_mut() {
+        transform.translation.x += ambient_fish.speed * time.delta_seconds();
+    }
+}
+
+/// Ambient fish loop around rather than despawning, and the currents can't push them out of view
+fn wrap_ambient_fish(mut query: Query<&mut Transform, With<AmbientFish>>, bounds: Res<Bounds>) {
+    const MARGIN: f32 = 2.0;
+
+    for mut transform in query.iter_mut() {
+        if transform.translation.x > bounds.max.x + MARGIN {
+            transform.translation.x = bounds.min.x - MARGIN;
+        } else if transform.translation.x < bounds.min.x - MARGIN {
+            transform.translation.x = bounds.max.x + MARGIN;
+        }
+
+        transform.translation.y = transform.translation.y.clamp(bounds.min.y, bounds.max.y);
+    }
+}
@@ -0,0 +1,147 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use strum::IntoEnumIterator;
+
+use crate::{
+    bend::Bendable,
+    biome::{BackgroundLayer, BackgroundLayerKind, CurrentBiome},
+    camera::Parallax,
+    fishy_assets::{RockCollection, RockType, SeaweedCollection, SeaweedType, TextureCollection},
+    GameState, SimulationSet, SEAWEED_HEIGHT,
+};
+
+// Layers of scenery at different depths that scroll at their own rates as the camera moves
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            spawn_background_layers
+                .run_if(resource_changed::<CurrentBiome>())
+                .run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        );
+    }
+}
+
+/// Copies of each tile either side of the middle one
+const TILE_COPIES: i32 = 1;
+
+#[derive(Component, Debug)]
+pub struct BackgroundLayerRoot;
+
+/// Rebuilds the background whenever the biome changes, including when the game starts
+fn spawn_background_layers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    layer_query: Query<Entity, With<BackgroundLayerRoot>>,
+    current_biome: Res<CurrentBiome>,
+    texture_collection: Res<TextureCollection>,
+    rock_collection: Res<RockCollection>,
+    seaweed_collection: Res<SeaweedCollection>,
+) {
+    for entity in layer_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (index, layer) in current_biome.biome.background_layers().iter().enumerate() {
+        let origin = Vec3::new(0.0, 0.0, layer.depth);
+
+        commands
+            .spawn((
+                SpatialBundle::from_transform(Transform::from_translation(origin)),
+                BackgroundLayerRoot,
+                Parallax {
+                    factor: Vec2::new(layer.parallax, layer.parallax * 0.5),
+                    origin,
+                    tile_width: Some(layer.tile_width),
+                },
+            ))
+            .with_children(|parent| {
+                // Every copy of a tile gets the same seed so the copies line up seamlessly
+                let seed = index as u64;
+
+                for copy in -TILE_COPIES..=TILE_COPIES {
+                    let offset = copy as f32 * layer.tile_width;
+                    let mut rng = StdRng::seed_from_u64(seed);
+
+                    match layer.kind {
+                        BackgroundLayerKind::Gradient { top, bottom } => {
+                            parent.spawn(PbrBundle {
+                                mesh: meshes.add(gradient_quad(layer, top, bottom)),
+                                material: materials.add(StandardMaterial {
+                                    base_color_texture: Some(texture_collection.background.clone()),
+                                    unlit: true,
+                                    ..default()
+                                }),
+                                transform: Transform::from_xyz(offset, layer.floor, 0.0),
+                                ..default()
+                            });
+                        }
+                        BackgroundLayerKind::Silhouettes | BackgroundLayerKind::Rocks => {
+                            let rock_types = RockType::iter().collect::<Vec<_>>();
+
+                            for transform in scatter(layer, offset, &mut rng) {
+                                let rock_type = rock_types.choose(&mut rng).unwrap();
+
+                                parent.spawn(SceneBundle {
+                                    scene: rock_type.model_from(&rock_collection),
+                                    transform,
+                                    ..default()
+                                });
+                            }
+                        }
+                        BackgroundLayerKind::Kelp => {
+                            let seaweed_types = SeaweedType::iter().collect::<Vec<_>>();
+
+                            for transform in scatter(layer, offset, &mut rng) {
+                                let seaweed_type = seaweed_types.choose(&mut rng).unwrap();
+
+                                parent.spawn((
+                                    SceneBundle {
+                                        scene: seaweed_type.model_from(&seaweed_collection),
+                                        transform,
+                                        ..default()
+                                    },
+                                    Bendable::new(
+                                        transform.rotation,
+                                        SEAWEED_HEIGHT * transform.scale.y,
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+            });
+    }
+}
+
+/// A tile-sized quad that fades from `top` to `bottom`
+fn gradient_quad(layer: &BackgroundLayer, top: Color, bottom: Color) -> Mesh {
+    let mut mesh = Mesh::from(shape::Quad::new(Vec2::new(layer.tile_width, 60.0)));
+    let (top, bottom) = (top.as_linear_rgba_f32(), bottom.as_linear_rgba_f32());
+
+    // Quad vertices go bottom left, top left, top right, bottom right
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![bottom, top, top, bottom]);
+    mesh
+}
+
+/// Spreads a layer's props over a tile, in the tile's local space
+fn scatter(layer: &BackgroundLayer, offset: f32, rng: &mut StdRng) -> Vec<Transform> {
+    let half_width = layer.tile_width / 2.0;
+    let (min_scale, max_scale) = layer.scale;
+
+    (0..layer.density)
+        .map(|_| {
+            let x = offset + rng.gen_range(-half_width..half_width);
+            let z = rng.gen_range(-2.0..2.0);
+
+            Transform::from_xyz(x, layer.floor, z)
+                .with_rotation(Quat::from_rotation_y(rng.gen_range(0.0..TAU)))
+                .with_scale(Vec3::splat(rng.gen_range(min_scale..max_scale)))
+        })
+        .collect()
+}
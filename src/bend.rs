@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{ambient::AmbientFish, hazard::Hazard, input::Player, GameState, SimulationSet};
+
+// Seaweed and other soft props that bend out of the way of anything swimming past and spring
+// back afterwards
+pub struct BendPlugin;
+
+impl Plugin for BendPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BendGrid>().add_systems(
+            (
+                add_disturbances,
+                update_disturbances,
+                fill_bend_grid,
+                push_bendables,
+                spring_bendables,
+            )
+                .chain()
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        );
+    }
+}
+
+/// Size of a grid cell on the seabed, in world units
+const CELL_SIZE: f32 = 4.0;
+
+/// The furthest anything can bend, in radians
+const MAX_BEND: f32 = 0.7;
+
+/// How quickly something swimming past at one world unit per second starts a bend
+const PUSH_STRENGTH: f32 = 1.5;
+
+/// Bent by anything with a `Disturbance` that swims close enough
+#[derive(Component, Debug)]
+pub struct Bendable {
+    /// How high the prop reaches above its root, in world units
+    pub height: f32,
+
+    /// How hard it springs back
+    pub stiffness: f32,
+
+    /// How quickly the springing dies down
+    pub damping: f32,
+
+    /// The tilt towards x and towards z, in radians
+    pub bend: Vec2,
+
+    pub angular_velocity: Vec2,
+
+    rest: Quat,
+}
+
+impl Bendable {
+    pub fn new(rest: Quat, height: f32) -> Bendable {
+        Bendable {
+            height,
+            stiffness: 12.0,
+            damping: 3.0,
+            bend: Vec2::ZERO,
+            angular_velocity: Vec2::ZERO,
+            rest,
+        }
+    }
+
+    fn is_resting(&self) -> bool {
+        self.bend.length_squared() < 1e-6 && self.angular_velocity.length_squared() < 1e-6
+    }
+}
+
+/// Something moving that pushes bendables away
+#[derive(Component, Debug)]
+pub struct Disturbance {
+    pub radius: f32,
+
+    /// In world units per second, on the play plane
+    pub velocity: Vec2,
+}
+
+impl Disturbance {
+    pub fn new(radius: f32) -> Disturbance {
+        Disturbance {
+            radius,
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+/// Bendables bucketed by where they stand on the seabed, so only the ones near a disturbance
+/// are checked
+#[derive(Resource, Default)]
+struct BendGrid {
+    cells: HashMap<IVec2, Vec<Entity>>,
+}
+
+fn cell(position: Vec3) -> IVec2 {
+    (Vec2::new(position.x, position.z) / CELL_SIZE)
+        .floor()
+        .as_ivec2()
+}
+
+/// The tilt a disturbance gives a bendable standing at `root`. Bends away from the disturbance
+/// and along with its movement, the closer and faster the harder.
+pub fn bend_push(disturbance: Vec3, velocity: Vec2, radius: f32, root: Vec3, height: f32) -> Vec2 {
+    // Only the part of the stalk level with the disturbance gets pushed
+    let nearest_y = disturbance.y.clamp(root.y, root.y + height);
+    let nearest = Vec3::new(root.x, nearest_y, root.z);
+    let offset = nearest - disturbance;
+    let distance = offset.length();
+
+    if distance >= radius {
+        return Vec2::ZERO;
+    }
+
+    let falloff = 1.0 - distance / radius;
+    let away = Vec2::new(offset.x, offset.z).normalize_or_zero();
+    // The play plane's x is the seabed's x, its y has nowhere to push a stalk
+    let along = Vec2::new(velocity.x, 0.0);
+
+    (away * velocity.length() + along) * falloff * PUSH_STRENGTH
+}
+
+fn add_disturbances(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    hazard_query: Query<Entity, Added<Hazard>>,
+    ambient_fish_query: Query<Entity, Added<AmbientFish>>,
+) {
+    for entity in player_query.iter() {
+        commands.entity(entity).insert(Disturbance::new(2.0));
+    }
+
+    for entity in hazard_query.iter() {
+        commands.entity(entity).insert(Disturbance::new(2.5));
+    }
+
+    for entity in ambient_fish_query.iter() {
+        commands.entity(entity).insert(Disturbance::new(1.5));
+    }
+}
+
+fn update_disturbances(
+    mut player_query: Query<(&Player, &mut Disturbance), (Without<Hazard>, Without<AmbientFish>)>,
+    mut hazard_query: Query<(&Hazard, &mut Disturbance), Without<AmbientFish>>,
+    mut ambient_fish_query: Query<(&AmbientFish, &mut Disturbance)>,
+) {
+    for (player, mut disturbance) in player_query.iter_mut() {
+        disturbance.velocity = player.velocity();
+    }
+
+    for (hazard, mut disturbance) in hazard_query.iter_mut() {
+        disturbance.velocity = hazard.velocity;
+    }
+
+    for (ambient_fish, mut disturbance) in ambient_fish_query.iter_mut() {
+        disturbance.velocity = Vec2::new(ambient_fish.speed, 0.0);
+    }
+}
+
+fn fill_bend_grid(
+    mut grid: ResMut<BendGrid>,
+    query: Query<(Entity, &GlobalTransform), With<Bendable>>,
+) {
+    for entities in grid.cells.values_mut() {
+        entities.clear();
+    }
+
+    for (entity, transform) in query.iter() {
+        grid.cells
+            .entry(cell(transform.translation()))
+            .or_default()
+            .push(entity);
+    }
+}
+
+fn push_bendables(
+    grid: Res<BendGrid>,
+    disturbance_query: Query<(&GlobalTransform, &Disturbance)>,
+    mut bendable_query: Query<(&GlobalTransform, &mut Bendable)>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (disturbance_transform, disturbance) in disturbance_query.iter() {
+        if disturbance.velocity == Vec2::ZERO {
+            continue;
+        }
+
+        let position = disturbance_transform.translation();
+        let reach = Vec3::new(disturbance.radius, 0.0, disturbance.radius);
+        let (min, max) = (cell(position - reach), cell(position + reach));
+
+        for x in min.x..=max.x {
+            for z in min.y..=max.y {
+                let Some(entities) = grid.cells.get(&IVec2::new(x, z)) else {
+                    continue;
+                };
+
+                for entity in entities.iter() {
+                    let Ok((transform, mut bendable)) = bendable_query.get_mut(*entity) else {
+                        continue;
+                    };
+
+                    let push = bend_push(
+                        position,
+                        disturbance.velocity,
+                        disturbance.radius,
+                        transform.translation(),
+                        bendable.height,
+                    );
+
+                    if push != Vec2::ZERO {
+                        bendable.angular_velocity += push * delta_seconds;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn spring_bendables(mut query: Query<(&mut Bendable, &mut Transform)>, time: Res<Time>) {
+    let delta_seconds = time.delta_seconds();
+
+    for (mut bendable, mut transform) in query.iter_mut() {
+        if bendable.is_resting() {
+            if bendable.bend != Vec2::ZERO {
+                bendable.bend = Vec2::ZERO;
+                bendable.angular_velocity = Vec2::ZERO;
+                transform.rotation = bendable.rest;
+            }
+            continue;
+        }
+
+        let acceleration =
+            -bendable.bend * bendable.stiffness - bendable.angular_velocity * bendable.damping;
+        bendable.angular_velocity += acceleration * delta_seconds;
+
+        let bend = bendable.bend + bendable.angular_velocity * delta_seconds;
+        bendable.bend = bend.clamp_length_max(MAX_BEND);
+
+        // Leaning towards +x turns about -z, leaning towards +z turns about +x
+        transform.rotation = Quat::from_rotation_z(-bendable.bend.x)
+            * Quat::from_rotation_x(bendable.bend.y)
+            * bendable.rest;
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use strum_macros::EnumIter;
+
+/// The kind of ocean the level takes place in. Most spawning and environment tables are keyed
+/// by this.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, EnumIter)]
+pub enum Biome {
+    #[default]
+    Reef,
+    KelpForest,
+    Abyss,
+}
+
+#[derive(Resource, Default)]
+pub struct CurrentBiome {
+    pub biome: Biome,
+}
+
+impl Biome {
+    /// How dark the water is even in the middle of the day, from 0 to 1. Bioluminescent things
+    /// glow at least this much.
+    pub fn darkness(&self) -> f32 {
+        match self {
+            Biome::Reef => 0.0,
+            Biome::KelpForest => 0.2,
+            Biome::Abyss => 1.0,
+        }
+    }
+}
+
+/// A rectangle of authored current, e.g. a jet stream across the middle of the screen. Corners
+/// are fractions of the `Bounds` so that zones keep their place when the window changes size.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CurrentZone {
+    pub min: Vec2,
+
+    pub max: Vec2,
+
+    pub flow: Vec2,
+}
+
+/// How the water moves in a biome
+#[derive(Debug, Clone, PartialEq)]
+pub struct Currents {
+    /// A steady drift across the whole level
+    pub base: Vec2,
+
+    /// How hard the noise swirls on top of the base drift push
+    pub turbulence: f32,
+
+    /// Size of a swirl in world units
+    pub noise_scale: f32,
+
+    /// How quickly the swirls change over time
+    pub evolution_speed: f32,
+
+    pub zones: Vec<CurrentZone>,
+}
+
+impl Biome {
+    pub fn currents(&self) -> Currents {
+        match self {
+            Biome::Reef => Currents {
+                base: Vec2::new(0.2, 0.0),
+                turbulence: 0.3,
+                noise_scale: 12.0,
+                evolution_speed: 0.05,
+                zones: vec![],
+            },
+            // Kelp forests have a strong tidal surge near the surface
+            Biome::KelpForest => Currents {
+                base: Vec2::new(0.1, 0.0),
+                turbulence: 0.5,
+                noise_scale: 8.0,
+                evolution_speed: 0.1,
+                zones: vec![CurrentZone {
+                    min: Vec2::new(0.0, 0.75),
+                    max: Vec2::new(1.0, 0.95),
+                    flow: Vec2::new(1.2, 0.0),
+                }],
+            },
+            // The abyss is mostly still apart from a cold upwelling in the middle
+            Biome::Abyss => Currents {
+                base: Vec2::ZERO,
+                turbulence: 0.15,
+                noise_scale: 16.0,
+                evolution_speed: 0.02,
+                zones: vec![CurrentZone {
+                    min: Vec2::new(0.4, 0.0),
+                    max: Vec2::new(0.6, 1.0),
+                    flow: Vec2::new(0.0, 0.8),
+                }],
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BackgroundLayerKind {
+    /// The backdrop texture tinted from `top` to `bottom`
+    Gradient {
+        top: Color,
+        bottom: Color,
+    },
+    /// Huge rocks far off in the murk
+    Silhouettes,
+    Rocks,
+    /// Kelp in front of the play plane
+    Kelp,
+}
+
+/// One depth layer of the parallax background. Layers are tiled along x so they never run out
+/// however far the camera moves.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BackgroundLayer {
+    pub kind: BackgroundLayerKind,
+
+    /// Where the layer sits along z, negative is behind the play plane
+    pub depth: f32,
+
+    /// How much the layer moves with the camera, see `Parallax`
+    pub parallax: f32,
+
+    /// How wide a single tile of the layer is
+    pub tile_width: f32,
+
+    /// How many props are scattered over each tile
+    pub density: usize,
+
+    /// The height props are planted at
+    pub floor: f32,
+
+    pub scale: (f32, f32),
+}
+
+impl Biome {
+    pub fn background_layers(&self) -> Vec<BackgroundLayer> {
+        let (top, bottom, rock_density, kelp_density) = match self {
+            Biome::Reef => (
+                Color::hex("7fd1ff").unwrap(),
+                Color::hex("1b4f72").unwrap(),
+                6,
+                3,
+            ),
+            Biome::KelpForest => (
+                Color::hex("8fd694").unwrap(),
+                Color::hex("1d3b2a").unwrap(),
+                4,
+                8,
+            ),
+            Biome::Abyss => (
+                Color::hex("1a2a4f").unwrap(),
+                Color::hex("02030a").unwrap(),
+                3,
+                0,
+            ),
+        };
+
+        let mut layers = vec![
+            BackgroundLayer {
+                kind: BackgroundLayerKind::Gradient { top, bottom },
+                depth: -100.0,
+                parallax: 0.9,
+                tile_width: 60.0,
+                density: 1,
+                floor: -5.0,
+                scale: (1.0, 1.0),
+            },
+            BackgroundLayer {
+                kind: BackgroundLayerKind::Silhouettes,
+                depth: -60.0,
+                parallax: 0.7,
+                tile_width: 80.0,
+                density: 4,
+                floor: -22.0,
+                scale: (8.0, 14.0),
+            },
+            BackgroundLayer {
+                kind: BackgroundLayerKind::Rocks,
+                depth: -25.0,
+                parallax: 0.4,
+                tile_width: 60.0,
+                density: rock_density,
+                floor: -17.0,
+                scale: (2.0, 4.0),
+            },
+        ];
+
+        if kelp_density > 0 {
+            layers.push(BackgroundLayer {
+                kind: BackgroundLayerKind::Kelp,
+                depth: 6.0,
+                // Moves against the camera so it looks closer than the play plane
+                parallax: -0.3,
+                tile_width: 50.0,
+                density: kelp_density,
+                floor: -17.0,
+                scale: (2.0, 3.5),
+            });
+        }
+
+        layers
+    }
+}
@@ -0,0 +1,1126 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collision::{distance_to_segment, Hitbox, PlayerHitEvent},
+    fishy_assets::{FishAnimationCollection, FishCollection, FishType, FontCollection},
+    fixed_step::{Interpolated, StepSet},
+    hazard::{swim_rotation, HazardSpawnTimer, HazardTimeScale},
+    hud::{spawn_meter, Meter, MeterFill},
+    in_mode,
+    input::Player,
+    rng::GameRng,
+    stats::Score,
+    Bounds, Fish, FishBundle, GameMode, GameState, InitialAnimation, SimulationSet,
+};
+
+// Scripted boss fights that interrupt the regular hazards once the player has survived long
+// enough
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossDirector>()
+            .add_system(setup_boss_assets.in_schedule(OnEnter(GameState::Playing)))
+            // Bosses move themselves and pull the player around, so they run in steps
+            .add_systems(
+                (
+                    tick_boss_director,
+                    update_bosses,
+                    sweep_whales,
+                    extend_tentacles,
+                    despawn_stale_boss_parts,
+                    resolve_boss_strikes,
+                    finish_defeated_bosses,
+                )
+                    .chain()
+                    .distributive_run_if(in_mode(GameMode::Classic))
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(StepSet::Movement)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    spawn_boss_health_bars,
+                    update_boss_health_bars,
+                    despawn_boss_health_bars,
+                )
+                    .chain()
+                    .distributive_run_if(in_mode(GameMode::Classic))
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const ENTER_SPEED: f32 = 3.0;
+const LEAVE_SPEED: f32 = 4.0;
+const DEFEAT_POINTS: u32 = 250;
+
+const SUCTION_RADIUS: f32 = 8.0;
+const WHALE_BODY_RADIUS: f32 = 1.5;
+
+const TENTACLE_RADIUS: f32 = 0.4;
+const TENTACLE_MESH_LENGTH: f32 = 2.0 + TENTACLE_RADIUS * 2.0;
+/// How far past its target a tentacle reaches
+const TENTACLE_OVERSHOOT: f32 = 3.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BossKind {
+    Whale,
+    Octopus,
+}
+
+/// Bosses show up in this order, then loop
+const BOSS_ORDER: [BossKind; 2] = [BossKind::Whale, BossKind::Octopus];
+
+impl BossKind {
+    pub fn fish_type(&self) -> FishType {
+        match self {
+            BossKind::Whale => FishType::Whale,
+            BossKind::Octopus => FishType::Octopus,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BossKind::Whale => "The Great Whale",
+            BossKind::Octopus => "The Giant Octopus",
+        }
+    }
+
+    /// Every attack the player gets through without being hit takes a point off
+    pub fn max_health(&self) -> u32 {
+        match self {
+            BossKind::Whale => 4,
+            BossKind::Octopus => 6,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        match self {
+            BossKind::Whale => 1.2,
+            BossKind::Octopus => 4.0,
+        }
+    }
+
+    pub fn half_length(&self) -> f32 {
+        self.fish_type().body_length() * self.scale() / 2.0
+    }
+}
+
+/// Counts how long the player has survived and brings in a boss once it's been long enough.
+/// Regular hazards are held back while a boss is on screen.
+#[derive(Resource)]
+pub struct BossDirector {
+    /// Seconds the player has to survive before the next boss shows up
+    pub survival_threshold: f32,
+
+    pub survived: f32,
+
+    pub encounters: usize,
+
+    pub active: Option<Entity>,
+}
+
+impl Default for BossDirector {
+    fn default() -> BossDirector {
+        BossDirector {
+            survival_threshold: 60.0,
+            survived: 0.0,
+            encounters: 0,
+            active: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BossAttack {
+    /// The whale charges across the screen along `lane`, sucking the player towards its mouth
+    Sweep { lane: f32, moving_right: bool },
+    /// Tentacles lash in from the edges of the screen, each from an origin towards a reach
+    TentacleStrikes { strikes: Vec<(Vec2, Vec2)> },
+}
+
+#[derive(Debug, Clone)]
+pub enum BossState {
+    /// Moving into position
+    Entering {
+        target: Vec3,
+    },
+    /// Resting between attacks
+    Idle {
+        timer: Timer,
+    },
+    /// Showing where the next attack will land
+    Telegraphing {
+        attack: BossAttack,
+        timer: Timer,
+    },
+    Attacking {
+        attack: BossAttack,
+        timer: Timer,
+    },
+    /// Defeated and on its way out
+    Leaving,
+}
+
+#[derive(Component, Debug)]
+pub struct Boss {
+    pub kind: BossKind,
+
+    pub health: u32,
+
+    pub state: BossState,
+
+    /// Whether the current attack has hit the player, in which case it does the boss no harm
+    pub attack_landed: bool,
+}
+
+impl Boss {
+    pub fn new(kind: BossKind, target: Vec3) -> Boss {
+        Boss {
+            kind,
+            health: kind.max_health(),
+            state: BossState::Entering { target },
+            attack_landed: false,
+        }
+    }
+
+    pub fn health_fraction(&self) -> f32 {
+        self.health as f32 / self.kind.max_health() as f32
+    }
+
+    /// Bosses get faster and meaner once they're down to half health
+    pub fn phase(&self) -> u32 {
+        if self.health * 2 > self.kind.max_health() {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// A part of a boss that hurts to touch, shaped like a capsule between `start` and `end`
+#[derive(Component, Debug)]
+pub struct BossStrike {
+    pub boss: Entity,
+
+    pub start: Vec2,
+
+    pub end: Vec2,
+
+    pub radius: f32,
+
+    /// Each strike only hits once per attack
+    pub hit_player: bool,
+}
+
+impl BossStrike {
+    fn at(boss: Entity, position: Vec2, radius: f32) -> BossStrike {
+        BossStrike {
+            boss,
+            start: position,
+            end: position,
+            radius,
+            hit_player: false,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Tentacle {
+    pub boss: Entity,
+
+    pub origin: Vec2,
+
+    pub reach: Vec2,
+}
+
+/// Marks the warning shown while an attack is being telegraphed
+#[derive(Component, Debug)]
+pub struct BossWarning {
+    pub boss: Entity,
+}
+
+#[derive(Component, Debug)]
+pub struct BossHealthBar {
+    pub boss: Entity,
+}
+
+#[derive(Resource)]
+pub struct BossAssets {
+    pub warning_mesh: Handle<Mesh>,
+
+    pub warning_material: Handle<StandardMaterial>,
+
+    pub tentacle_mesh: Handle<Mesh>,
+
+    pub tentacle_material: Handle<StandardMaterial>,
+}
+
+fn setup_boss_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(BossAssets {
+        warning_mesh: meshes.add(shape::Quad::new(Vec2::ONE).into()),
+        warning_material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.9, 0.1, 0.1, 0.3),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        tentacle_mesh: meshes.add(
+            shape::Capsule {
+                radius: TENTACLE_RADIUS,
+                depth: TENTACLE_MESH_LENGTH - TENTACLE_RADIUS * 2.0,
+                ..default()
+            }
+            .into(),
+        ),
+        tentacle_material: materials.add(StandardMaterial {
+            base_color: Color::hex("7b2d8b").unwrap(),
+            perceptual_roughness: 0.6,
+            ..default()
+        }),
+    });
+}
+
+fn rest_timer(phase: u32) -> Timer {
+    let seconds = if phase == 1 { 1.5 } else { 1.0 };
+
+    Timer::from_seconds(seconds, TimerMode::Once)
+}
+
+fn telegraph_timer(kind: BossKind, phase: u32) -> Timer {
+    let seconds = match (kind, phase) {
+        (BossKind::Whale, 1) => 1.6,
+        (BossKind::Whale, _) => 1.0,
+        (BossKind::Octopus, 1) => 1.2,
+        (BossKind::Octopus, _) => 0.8,
+    };
+
+    Timer::from_seconds(seconds, TimerMode::Once)
+}
+
+fn sweep_speed(phase: u32) -> f32 {
+    if phase == 1 {
+        8.0
+    } else {
+        12.0
+    }
+}
+
+fn suction_strength(phase: u32) -> f32 {
+    if phase == 1 {
+        1.2
+    } else {
+        2.0
+    }
+}
+
+/// Where a sweep starts and ends along x so that the whale is fully off screen at both ends
+fn sweep_span(bounds: &Bounds, half_length: f32, moving_right: bool) -> (f32, f32) {
+    let left = bounds.min.x - half_length - 1.0;
+    let right = bounds.max.x + half_length + 1.0;
+
+    if moving_right {
+        (left, right)
+    } else {
+        (right, left)
+    }
+}
+
+/// How far out a tentacle is over the course of a strike: a quick lash, a pause, then a slower
+/// retreat
+pub fn tentacle_extension(t: f32) -> f32 {
+    const LASH: f32 = 0.3;
+    const HOLD: f32 = 0.55;
+
+    if t < LASH {
+        t / LASH
+    } else if t < HOLD {
+        1.0
+    } else {
+        1.0 - (t - HOLD) / (1.0 - HOLD)
+    }
+}
+
+fn plan_attack(
+    kind: BossKind,
+    phase: u32,
+    player_position: Vec2,
+    bounds: &Bounds,
+    rng: &mut impl Rng,
+) -> BossAttack {
+    match kind {
+        BossKind::Whale => {
+            // The first phase gives the player a bit of slack
+            let jitter = if phase == 1 { 1.5 } else { 0.0 };
+            let lane = (player_position.y + rng.gen_range(-jitter..=jitter))
+                .clamp(bounds.min.y + 1.0, bounds.max.y - 1.0);
+
+            BossAttack::Sweep {
+                lane,
+                moving_right: rng.gen_bool(0.5),
+            }
+        }
+        BossKind::Octopus => {
+            let strikes = (0..phase + 1)
+                .map(|_| {
+                    let origin = match rng.gen_range(0..3) {
+                        0 => Vec2::new(bounds.min.x, rng.gen_range(bounds.min.y..bounds.max.y)),
+                        1 => Vec2::new(bounds.max.x, rng.gen_range(bounds.min.y..bounds.max.y)),
+                        _ => Vec2::new(rng.gen_range(bounds.min.x..bounds.max.x), bounds.max.y),
+                    };
+                    let target = player_position
+                        + Vec2::new(rng.gen_range(-1.5..1.5), rng.gen_range(-1.5..1.5));
+                    let direction = (target - origin).normalize_or_zero();
+                    let reach = target + direction * TENTACLE_OVERSHOOT;
+
+                    (origin, reach)
+                })
+                .collect();
+
+            BossAttack::TentacleStrikes { strikes }
+        }
+    }
+}
+
+fn attack_timer(kind: BossKind, phase: u32, bounds: &Bounds) -> Timer {
+    let seconds = match kind {
+        BossKind::Whale => {
+            let distance = bounds.max.x - bounds.min.x + (kind.half_length() + 1.0) * 2.0;
+
+            distance / sweep_speed(phase)
+        }
+        BossKind::Octopus => 1.2,
+    };
+
+    Timer::from_seconds(seconds, TimerMode::Once)
+}
+
+fn spawn_warnings(
+    commands: &mut Commands,
+    boss_assets: &BossAssets,
+    boss: Entity,
+    attack: &BossAttack,
+    bounds: &Bounds,
+) {
+    let mut spawn_warning = |center: Vec2, size: Vec2, angle: f32| {
+        commands.spawn((
+            PbrBundle {
+                mesh: boss_assets.warning_mesh.clone(),
+                material: boss_assets.warning_material.clone(),
+                transform: Transform::from_translation(center.extend(-0.5))
+                    .with_rotation(Quat::from_rotation_z(angle))
+                    .with_scale(size.extend(1.0)),
+                ..default()
+            },
+            BossWarning { boss },
+        ));
+    };
+
+    match attack {
+        BossAttack::Sweep { lane, .. } => {
+            let width = bounds.max.x - bounds.min.x;
+
+            spawn_warning(
+                Vec2::new((bounds.min.x + bounds.max.x) / 2.0, *lane),
+                Vec2::new(width, WHALE_BODY_RADIUS * 2.0),
+                0.0,
+            );
+        }
+        BossAttack::TentacleStrikes { strikes } => {
+            for (origin, reach) in strikes {
+                let span = *reach - *origin;
+
+                spawn_warning(
+                    (*origin + *reach) / 2.0,
+                    Vec2::new(span.length(), TENTACLE_RADIUS * 2.0),
+                    span.y.atan2(span.x),
+                );
+            }
+        }
+    }
+}
+
+fn spawn_tentacles(
+    commands: &mut Commands,
+    boss_assets: &BossAssets,
+    boss: Entity,
+    strikes: &[(Vec2, Vec2)],
+) {
+    for (origin, reach) in strikes {
+        let direction = (*reach - *origin).normalize_or_zero();
+        let transform = Transform::from_translation(origin.extend(0.0))
+            .with_rotation(Quat::from_rotation_arc(Vec3::Y, direction.extend(0.0)))
+            .with_scale(Vec3::new(1.0, 0.0, 1.0));
+
+        commands.spawn((
+            PbrBundle {
+                mesh: boss_assets.tentacle_mesh.clone(),
+                material: boss_assets.tentacle_material.clone(),
+                transform,
+                ..default()
+            },
+            Interpolated::from(transform),
+            Tentacle {
+                boss,
+                origin: *origin,
+                reach: *reach,
+            },
+            BossStrike::at(boss, *origin, TENTACLE_RADIUS),
+        ));
+    }
+}
+
+fn tick_boss_director(
+    mut commands: Commands,
+    mut boss_director: ResMut<BossDirector>,
+    mut hazard_spawn_timer: ResMut<HazardSpawnTimer>,
+    bounds: Res<Bounds>,
+    fish_collection: Res<FishCollection>,
+    animation_collection: Res<FishAnimationCollection>,
+    fixed_time: Res<FixedTime>,
+) {
+    if boss_director.active.is_some() {
+        return;
+    }
+
+    boss_director.survived += fixed_time.period.as_secs_f32();
+
+    if boss_director.survived < boss_director.survival_threshold {
+        return;
+    }
+
+    let kind = BOSS_ORDER[boss_director.encounters % BOSS_ORDER.len()];
+    let fish_type = kind.fish_type();
+    let animations = fish_type.animations_from(&animation_collection);
+    let (spawn, target, rotation, animation) = match kind {
+        // The whale waits off screen until its first sweep
+        BossKind::Whale => {
+            let position = Vec3::new(bounds.max.x + kind.half_length() + 1.0, 0.0, 0.0);
+
+            (
+                position,
+                position,
+                swim_rotation(fish_type, false),
+                animations.moving.unwrap_or(animations.idle),
+            )
+        }
+        // The octopus rises up from below and sits on the bottom edge of the screen
+        BossKind::Octopus => (
+            Vec3::new(0.0, bounds.min.y - kind.half_length() * 2.0, -1.0),
+            Vec3::new(0.0, bounds.min.y - kind.half_length() * 0.5, -1.0),
+            Quat::IDENTITY,
+            animations.idle,
+        ),
+    };
+
+    let transform = Transform::from_translation(spawn)
+        .with_rotation(rotation)
+        .with_scale(Vec3::splat(kind.scale()));
+    let mut boss = commands.spawn((
+        InitialAnimation {
+            animation,
+            repeat: true,
+        },
+        FishBundle {
+            fish: Fish { fish_type },
+            scene: SceneBundle {
+                scene: fish_type.model_from(&fish_collection),
+                transform,
+                ..default()
+            },
+        },
+        Interpolated::from(transform),
+        Boss::new(kind, target),
+    ));
+    let entity = boss.id();
+    let radius = match kind {
+        BossKind::Whale => WHALE_BODY_RADIUS,
+        BossKind::Octopus => kind.half_length() * 0.6,
+    };
+    boss.insert(BossStrike::at(entity, spawn.truncate(), radius));
+
+    boss_director.active = Some(entity);
+    boss_director.survived = 0.0;
+    boss_director.encounters += 1;
+    hazard_spawn_timer.timer.pause();
+}
+
+/// Steps each boss through its state machine. Boss timers run on hazard time so slow-motion
+/// slows them down too.
+#[allow(clippy::too_many_arguments)]
+fn update_bosses(
+    mut commands: Commands,
+    mut boss_query: Query<(Entity, &mut Boss, &mut Transform, &mut BossStrike), Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+    bounds: Res<Bounds>,
+    boss_assets: Res<BossAssets>,
+    hazard_time_scale: Res<HazardTimeScale>,
+    game_rng: Res<GameRng>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta = fixed_time.period.mul_f32(hazard_time_scale.scale);
+    let mut rng = game_rng.stream("bosses");
+    let player_position = player_query
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+
+    for (entity, mut boss, mut transform, mut strike) in boss_query.iter_mut() {
+        let kind = boss.kind;
+        let phase = boss.phase();
+        let finishing_attack = matches!(boss.state, BossState::Attacking { .. });
+
+        let next_state = match &mut boss.state {
+            BossState::Entering { target } => {
+                let step = (*target - transform.translation)
+                    .clamp_length_max(ENTER_SPEED * delta.as_secs_f32());
+                transform.translation += step;
+
+                (transform.translation.distance(*target) < 0.01).then(|| BossState::Idle {
+                    timer: rest_timer(phase),
+                })
+            }
+            BossState::Idle { timer } => {
+                timer
+                    .tick(delta)
+                    .finished()
+                    .then(|| BossState::Telegraphing {
+                        attack: plan_attack(kind, phase, player_position, &bounds, &mut rng),
+                        timer: telegraph_timer(kind, phase),
+                    })
+            }
+            BossState::Telegraphing { attack, timer } => {
+                timer.tick(delta).finished().then(|| BossState::Attacking {
+                    attack: attack.clone(),
+                    timer: attack_timer(kind, phase, &bounds),
+                })
+            }
+            BossState::Attacking { timer, .. } => {
+                timer.tick(delta).finished().then(|| BossState::Idle {
+                    timer: rest_timer(phase),
+                })
+            }
+            BossState::Leaving => None,
+        };
+
+        if kind == BossKind::Octopus {
+            strike.start = transform.translation.truncate();
+            strike.end = strike.start;
+        }
+
+        let Some(mut next_state) = next_state else {
+            continue;
+        };
+
+        match &next_state {
+            BossState::Telegraphing { attack, .. } => {
+                spawn_warnings(&mut commands, &boss_assets, entity, attack, &bounds);
+            }
+            BossState::Attacking { attack, .. } => {
+                boss.attack_landed = false;
+                strike.hit_player = false;
+
+                if let BossAttack::TentacleStrikes { strikes } = attack {
+                    spawn_tentacles(&mut commands, &boss_assets, entity, strikes);
+                }
+            }
+            _ => {}
+        }
+
+        if finishing_attack {
+            if !boss.attack_landed {
+                boss.health = boss.health.saturating_sub(1);
+            }
+
+            if boss.health == 0 {
+                next_state = BossState::Leaving;
+            }
+        }
+
+        boss.state = next_state;
+    }
+}
+
+fn sweep_whales(
+    mut boss_query: Query<(&Boss, &mut Transform, &mut BossStrike), Without<Player>>,
+    mut player_query: Query<&mut Transform, With<Player>>,
+    bounds: Res<Bounds>,
+    hazard_time_scale: Res<HazardTimeScale>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32() * hazard_time_scale.scale;
+
+    for (boss, mut transform, mut strike) in boss_query.iter_mut() {
+        let BossState::Attacking {
+            attack: BossAttack::Sweep { lane, moving_right },
+            timer,
+        } = &boss.state
+        else {
+            continue;
+        };
+
+        let half_length = boss.kind.half_length();
+        let (start_x, end_x) = sweep_span(&bounds, half_length, *moving_right);
+        let x = start_x + (end_x - start_x) * timer.percent();
+
+        transform.translation = Vec3::new(x, *lane, 0.0);
+        transform.rotation = swim_rotation(FishType::Whale, *moving_right);
+
+        let forward = if *moving_right { Vec2::X } else { Vec2::NEG_X };
+        let center = transform.translation.truncate();
+        strike.start = center - forward * half_length;
+        strike.end = center + forward * half_length;
+
+        // Only what's in front of the mouth gets sucked in
+        let mouth = strike.end;
+        for mut player_transform in player_query.iter_mut() {
+            let offset = mouth - player_transform.translation.truncate();
+
+            if offset.dot(forward) >= 0.0 || offset.length() > SUCTION_RADIUS {
+                continue;
+            }
+
+            let pull = offset.normalize_or_zero() * suction_strength(boss.phase()) * delta_seconds;
+            player_transform.translation += pull.extend(0.0);
+        }
+    }
+}
+
+fn extend_tentacles(
+    mut tentacle_query: Query<(&Tentacle, &mut Transform, &mut BossStrike)>,
+    boss_query: Query<&Boss>,
+) {
+    for (tentacle, mut transform, mut strike) in tentacle_query.iter_mut() {
+        let Ok(boss) = boss_query.get(tentacle.boss) else {
+            continue;
+        };
+        let BossState::Attacking { timer, .. } = &boss.state else {
+            continue;
+        };
+
+        let extension = tentacle_extension(timer.percent());
+        let end = tentacle.origin + (tentacle.reach - tentacle.origin) * extension;
+        let length = tentacle.origin.distance(end);
+
+        strike.end = end;
+        transform.translation = ((tentacle.origin + end) / 2.0).extend(0.0);
+        transform.scale.y = length / TENTACLE_MESH_LENGTH;
+    }
+}
+
+/// Warnings only last while an attack is telegraphed and tentacles only while it's happening
+fn despawn_stale_boss_parts(
+    mut commands: Commands,
+    warning_query: Query<(Entity, &BossWarning)>,
+    tentacle_query: Query<(Entity, &Tentacle)>,
+    boss_query: Query<&Boss>,
+) {
+    for (entity, warning) in warning_query.iter() {
+        let telegraphing = boss_query.get(warning.boss).map_or(false, |boss| {
+            matches!(boss.state, BossState::Telegraphing { .. })
+        });
+
+        if !telegraphing {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (entity, tentacle) in tentacle_query.iter() {
+        let attacking = boss_query.get(tentacle.boss).map_or(false, |boss| {
+            matches!(boss.state, BossState::Attacking { .. })
+        });
+
+        if !attacking {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Strikes hit the player the same way hazards do, so they cost health unless guarded against.
+/// Any hit at all during an attack saves the boss from losing health for it.
+fn resolve_boss_strikes(
+    mut strike_query: Query<(Entity, &mut BossStrike)>,
+    mut boss_query: Query<&mut Boss>,
+    player_query: Query<(Entity, &Transform, &Hitbox), With<Player>>,
+    mut player_hit_events: EventWriter<PlayerHitEvent>,
+) {
+    for (entity, mut strike) in strike_query.iter_mut() {
+        if strike.hit_player {
+            continue;
+        }
+
+        for (player, transform, hitbox) in player_query.iter() {
+            let distance =
+                distance_to_segment(transform.translation.truncate(), strike.start, strike.end);
+
+            if distance >= strike.radius + hitbox.radius {
+                continue;
+            }
+
+            strike.hit_player = true;
+            player_hit_events.send(PlayerHitEvent {
+                player,
+                hazard: entity,
+            });
+
+            if let Ok(mut boss) = boss_query.get_mut(strike.boss) {
+                boss.attack_landed = true;
+            }
+        }
+    }
+}
+
+/// Defeated bosses sink out of view, then the regular hazards pick back up
+fn finish_defeated_bosses(
+    mut commands: Commands,
+    mut boss_query: Query<(Entity, &Boss, &mut Transform)>,
+    mut boss_director: ResMut<BossDirector>,
+    mut hazard_spawn_timer: ResMut<HazardSpawnTimer>,
+    mut score: ResMut<Score>,
+    bounds: Res<Bounds>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+
+    for (entity, boss, mut transform) in boss_query.iter_mut() {
+        if !matches!(boss.state, BossState::Leaving) {
+            continue;
+        }
+
+        transform.translation.y -= LEAVE_SPEED * delta_seconds;
+        transform.rotation = transform
+            .rotation
+            .slerp(Quat::from_rotation_x(PI / 4.0), delta_seconds);
+
+        if transform.translation.y > bounds.min.y - boss.kind.half_length() * 2.0 {
+            continue;
+        }
+
+        commands.entity(entity).despawn_recursive();
+        score.points += DEFEAT_POINTS;
+        boss_director.active = None;
+        hazard_spawn_timer.timer.unpause();
+    }
+}
+
+fn spawn_boss_health_bars(
+    mut commands: Commands,
+    boss_query: Query<(Entity, &Boss), Added<Boss>>,
+    font_collection: Res<FontCollection>,
+) {
+    for (entity, boss) in boss_query.iter() {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(0.0),
+                            top: Val::Px(12.0),
+                            ..default()
+                        },
+                        size: Size::width(Val::Percent(100.0)),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        gap: Size::height(Val::Px(4.0)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                BossHealthBar { boss: entity },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    boss.kind.name(),
+                    TextStyle {
+                        font: font_collection.bold.clone(),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                ));
+
+                spawn_meter(parent, &font_collection, Meter::BossHealth);
+            });
+    }
+}
+
+fn update_boss_health_bars(boss_query: Query<&Boss>, mut fill_query: Query<&mut MeterFill>) {
+    // Only one boss is ever out at a time
+    let Some(boss) = boss_query.iter().next() else {
+        return;
+    };
+
+    for mut fill in fill_query.iter_mut() {
+        if fill.meter == Meter::BossHealth && fill.fraction != boss.health_fraction() {
+            fill.fraction = boss.health_fraction();
+        }
+    }
+}
+
+fn despawn_boss_health_bars(
+    mut commands: Commands,
+    bar_query: Query<(Entity, &BossHealthBar)>,
+    boss_query: Query<&Boss>,
+) {
+    for (entity, bar) in bar_query.iter() {
+        if boss_query.get(bar.boss).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        collision::{Health, HitGuard, PlayerDamagedEvent},
+        hazard::resolve_classic_hits,
+    };
+
+    const TOLERANCE: f32 = 1e-4;
+
+    /// Steps a tenth of a second long, so the timers finish on whole steps
+    const STEP: f32 = 0.1;
+
+    fn bounds() -> Bounds {
+        Bounds {
+            min: Vec2::new(-8.0, -6.0),
+            max: Vec2::new(8.0, 6.0),
+        }
+    }
+
+    fn boss_app(kind: BossKind) -> (App, Entity) {
+        let mut app = App::new();
+        app.insert_resource(FixedTime::new_from_secs(STEP))
+            .insert_resource(bounds())
+            .insert_resource(GameRng::from_seed(7))
+            .init_resource::<HazardTimeScale>()
+            .insert_resource(BossAssets {
+                warning_mesh: Handle::default(),
+                warning_material: Handle::default(),
+                tentacle_mesh: Handle::default(),
+                tentacle_material: Handle::default(),
+            })
+            .add_system(update_bosses);
+
+        // Close enough to its target to be in place after a single step
+        let target = Vec3::new(0.0, -4.0, -1.0);
+        let boss = app.world.spawn_empty().id();
+        app.world.entity_mut(boss).insert((
+            Transform::from_translation(target + Vec3::Y * 0.1),
+            Boss::new(kind, target),
+            BossStrike::at(boss, target.truncate(), 1.0),
+        ));
+
+        (app, boss)
+    }
+
+    fn boss(app: &App, entity: Entity) -> &Boss {
+        app.world.get::<Boss>(entity).unwrap()
+    }
+
+    /// Steps until the boss's state matches, returning how many steps it took
+    fn step_until(app: &mut App, entity: Entity, matches: fn(&BossState) -> bool) -> u32 {
+        for steps in 1..=200 {
+            app.update();
+
+            if matches(&boss(app, entity).state) {
+                return steps;
+            }
+        }
+
+        panic!("stuck in {:?}", boss(app, entity).state);
+    }
+
+    fn count<T: Component>(app: &mut App) -> usize {
+        app.world.query::<&T>().iter(&app.world).count()
+    }
+
+    fn seconds(steps: u32) -> f32 {
+        steps as f32 * STEP
+    }
+
+    #[test]
+    fn bosses_enter_rest_telegraph_and_attack() {
+        let (mut app, entity) = boss_app(BossKind::Octopus);
+        assert!(matches!(
+            boss(&app, entity).state,
+            BossState::Entering { .. }
+        ));
+
+        assert_eq!(
+            step_until(&mut app, entity, |state| matches!(
+                state,
+                BossState::Idle { .. }
+            )),
+            1
+        );
+
+        let rest = step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Telegraphing { .. })
+        });
+        assert!((seconds(rest) - rest_timer(1).duration().as_secs_f32()).abs() < TOLERANCE);
+        // The first phase strikes with two tentacles
+        assert_eq!(count::<BossWarning>(&mut app), 2);
+
+        let telegraph = step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Attacking { .. })
+        });
+        let expected = telegraph_timer(BossKind::Octopus, 1)
+            .duration()
+            .as_secs_f32();
+        assert!((seconds(telegraph) - expected).abs() < TOLERANCE);
+        assert_eq!(count::<Tentacle>(&mut app), 2);
+
+        let attack = step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Idle { .. })
+        });
+        let expected = attack_timer(BossKind::Octopus, 1, &bounds())
+            .duration()
+            .as_secs_f32();
+        assert!((seconds(attack) - expected).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn dodged_attacks_cost_the_boss_health() {
+        let (mut app, entity) = boss_app(BossKind::Octopus);
+        let max_health = BossKind::Octopus.max_health();
+
+        step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Attacking { .. })
+        });
+        step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Idle { .. })
+        });
+        assert_eq!(boss(&app, entity).health, max_health - 1);
+
+        // One that lands does no harm to it
+        step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Attacking { .. })
+        });
+        app.world.get_mut::<Boss>(entity).unwrap().attack_landed = true;
+        step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Idle { .. })
+        });
+        assert_eq!(boss(&app, entity).health, max_health - 1);
+    }
+
+    #[test]
+    fn bosses_leave_once_out_of_health() {
+        let (mut app, entity) = boss_app(BossKind::Whale);
+        app.world.get_mut::<Boss>(entity).unwrap().health = 1;
+
+        step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Attacking { .. })
+        });
+        step_until(&mut app, entity, |state| {
+            matches!(state, BossState::Leaving)
+        });
+
+        assert_eq!(boss(&app, entity).health, 0);
+        for _ in 0..10 {
+            app.update();
+            assert!(matches!(boss(&app, entity).state, BossState::Leaving));
+        }
+    }
+
+    #[test]
+    fn phase_two_starts_at_half_health() {
+        for kind in BOSS_ORDER {
+            let mut boss = Boss::new(kind, Vec3::ZERO);
+            let half = kind.max_health() / 2;
+
+            for health in (half + 1)..=kind.max_health() {
+                boss.health = health;
+                assert_eq!(boss.phase(), 1, "{kind:?} at {health}");
+            }
+            for health in 0..=half {
+                boss.health = health;
+                assert_eq!(boss.phase(), 2, "{kind:?} at {health}");
+            }
+        }
+    }
+
+    #[test]
+    fn phase_two_is_faster_and_meaner() {
+        let seconds = |timer: Timer| timer.duration().as_secs_f32();
+
+        assert!(seconds(rest_timer(2)) < seconds(rest_timer(1)));
+        for kind in BOSS_ORDER {
+            assert!(seconds(telegraph_timer(kind, 2)) < seconds(telegraph_timer(kind, 1)));
+        }
+        assert!(sweep_speed(2) > sweep_speed(1));
+        assert!(suction_strength(2) > suction_strength(1));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let strikes = |phase, rng: &mut StdRng| match plan_attack(
+            BossKind::Octopus,
+            phase,
+            Vec2::ZERO,
+            &bounds(),
+            rng,
+        ) {
+            BossAttack::TentacleStrikes { strikes } => strikes.len(),
+            attack => panic!("the octopus planned {attack:?}"),
+        };
+        assert_eq!(strikes(1, &mut rng), 2);
+        assert_eq!(strikes(2, &mut rng), 3);
+    }
+
+    #[test]
+    fn tentacles_lash_out_hold_and_retreat() {
+        assert_eq!(tentacle_extension(0.0), 0.0);
+        assert!((tentacle_extension(0.15) - 0.5).abs() < TOLERANCE);
+        assert_eq!(tentacle_extension(0.3), 1.0);
+        assert_eq!(tentacle_extension(0.45), 1.0);
+        assert!(tentacle_extension(1.0).abs() < TOLERANCE);
+
+        // The retreat is slower than the lash
+        let lash = tentacle_extension(0.1) - tentacle_extension(0.0);
+        let retreat = tentacle_extension(0.6) - tentacle_extension(0.7);
+        assert!(lash > retreat);
+        assert!(retreat > 0.0);
+    }
+
+    #[test]
+    fn strikes_cost_the_player_health_once() {
+        let mut app = App::new();
+        app.add_state::<GameState>()
+            .add_event::<PlayerHitEvent>()
+            .add_event::<PlayerDamagedEvent>()
+            .add_systems((resolve_boss_strikes, resolve_classic_hits).chain());
+
+        let player = app
+            .world
+            .spawn((
+                Player::default(),
+                Transform::default(),
+                Hitbox::new(0.5),
+                Health::default(),
+                HitGuard::default(),
+            ))
+            .id();
+        let entity = app.world.spawn_empty().id();
+        app.world.entity_mut(entity).insert((
+            Boss::new(BossKind::Octopus, Vec3::ZERO),
+            BossStrike::at(entity, Vec2::new(1.0, 0.0), 1.0),
+        ));
+
+        app.update();
+        app.update();
+
+        let max = Health::default().max;
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, max - 1);
+        assert!(boss(&app, entity).attack_landed);
+    }
+}
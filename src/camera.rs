@@ -0,0 +1,374 @@
+use bevy::prelude::*;
+use noisy_bevy::simplex_noise_2d;
+
+use crate::{
+    boss::Boss, collision::PlayerDamagedEvent, fixed_step::Interpolated, input::Player, GameState,
+    SimulationSet,
+};
+
+// Moves the camera around: following the player, shaking on hits and zooming out for bosses.
+// Runs before the bounds are worked out so they always match what's on screen.
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                add_hit_trauma,
+                set_camera_zoom,
+                follow_player,
+                apply_camera_controller,
+                apply_parallax,
+            )
+                .chain()
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Camera),
+        );
+    }
+}
+
+const HIT_TRAUMA: f32 = 0.5;
+const TRAUMA_DECAY: f32 = 1.2;
+const MAX_SHAKE_OFFSET: f32 = 0.8;
+const MAX_SHAKE_ROLL: f32 = 0.03;
+const SHAKE_FREQUENCY: f32 = 12.0;
+const BOSS_ZOOM: f32 = 1.35;
+const ZOOM_SPEED: f32 = 1.5;
+
+#[derive(Component, Debug)]
+pub struct CameraController {
+    /// The point on the play plane the camera is centered on, before any shake
+    pub focus: Vec2,
+
+    /// Half the size of the box around the focus that the player can move in without the
+    /// camera following
+    pub dead_zone: Vec2,
+
+    /// How many seconds of the player's velocity the camera looks ahead by
+    pub look_ahead_time: f32,
+
+    pub look_ahead: Vec2,
+
+    /// How quickly the camera catches up, as a fraction of the distance per second
+    pub follow_speed: f32,
+
+    /// How far the focus can stray from where the camera started, so the level never runs out
+    pub limits: Rect,
+
+    /// Between 0 and 1, decays over time. Shake grows with its square so small knocks stay
+    /// subtle.
+    pub trauma: f32,
+
+    pub zoom: f32,
+
+    pub target_zoom: f32,
+
+    home: Transform,
+
+    base_orthographic_scale: f32,
+}
+
+impl CameraController {
+    pub fn new(home: Transform, projection: &Projection) -> CameraController {
+        let base_orthographic_scale = match projection {
+            Projection::Orthographic(orthographic) => orthographic.scale,
+            Projection::Perspective(_) => 1.0,
+        };
+
+        CameraController {
+            focus: Vec2::ZERO,
+            dead_zone: Vec2::new(3.0, 2.0),
+            look_ahead_time: 0.5,
+            look_ahead: Vec2::ZERO,
+            follow_speed: 3.0,
+            limits: Rect::new(-60.0, -4.0, 60.0, 6.0),
+            trauma: 0.0,
+            zoom: 1.0,
+            target_zoom: 1.0,
+            home,
+            base_orthographic_scale,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    /// How far the camera has moved from where it started, which parallax layers scroll by
+    pub fn offset(&self) -> Vec2 {
+        self.focus
+    }
+}
+
+/// Scrolls something with the camera. A factor of 1 moves with the camera so it looks infinitely
+/// far away, 0 stays put like the play plane, and anything in between sits somewhere behind it.
+#[derive(Component, Debug)]
+pub struct Parallax {
+    pub factor: Vec2,
+
+    pub origin: Vec3,
+
+    /// Tiled layers jump back by a whole tile whenever the camera gets more than half a tile
+    /// away, so a few copies of a tile are enough to cover any distance
+    pub tile_width: Option<f32>,
+}
+
+/// Where a parallax layer goes for the given camera offset
+pub fn parallax_translation(parallax: &Parallax, camera_offset: Vec2) -> Vec3 {
+    let mut translation = parallax.origin + (camera_offset * parallax.factor).extend(0.0);
+
+    if let Some(tile_width) = parallax.tile_width {
+        let behind = camera_offset.x - (translation.x - parallax.origin.x);
+        translation.x += (behind / tile_width).round() * tile_width;
+    }
+
+    translation
+}
+
+/// The smallest distance between a point and a box, per axis. Zero while the point is inside.
+pub fn dead_zone_offset(center: Vec2, half_size: Vec2, point: Vec2) -> Vec2 {
+    let offset = point - center;
+
+    offset.signum() * (offset.abs() - half_size).max(Vec2::ZERO)
+}
+
+/// Only hits that hurt shake the camera, not blocked ones or meals in the feeding frenzy
+fn add_hit_trauma(
+    mut player_damaged_events: EventReader<PlayerDamagedEvent>,
+    mut query: Query<&mut CameraController>,
+) {
+    for _ in player_damaged_events.iter() {
+        for mut controller in query.iter_mut() {
+            controller.add_trauma(HIT_TRAUMA);
+        }
+    }
+}
+
+/// Bosses need a bit more room
+fn set_camera_zoom(mut query: Query<&mut CameraController>, boss_query: Query<(), With<Boss>>) {
+    let target_zoom = if boss_query.is_empty() {
+        1.0
+    } else {
+        BOSS_ZOOM
+    };
+
+    for mut controller in query.iter_mut() {
+        if controller.target_zoom != target_zoom {
+            controller.target_zoom = target_zoom;
+        }
+    }
+}
+
+/// Follows where the player is drawn rather than where it's simulated, or the camera would
+/// judder against it whenever frames and steps don't line up
+fn follow_player(
+    mut query: Query<&mut CameraController>,
+    player_query: Query<(&Transform, &Interpolated, &Player)>,
+    fixed_time: Res<FixedTime>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, interpolated, player)) = player_query.get_single() else {
+        return;
+    };
+
+    let delta_seconds = time.delta_seconds();
+    let player_position = interpolated
+        .drawn_translation(player_transform, &fixed_time)
+        .truncate();
+
+    for mut controller in query.iter_mut() {
+        let t = (controller.follow_speed * delta_seconds).min(1.0);
+        let look_ahead = player.velocity() * controller.look_ahead_time;
+        controller.look_ahead = controller.look_ahead.lerp(look_ahead, t);
+
+        // Only the part of the target outside of the dead zone pulls the camera along
+        let target = player_position + controller.look_ahead;
+        let offset = dead_zone_offset(controller.focus, controller.dead_zone, target);
+        let focus = controller.focus + offset * t;
+
+        controller.focus = focus.clamp(controller.limits.min, controller.limits.max);
+    }
+}
+
+fn apply_camera_controller(
+    mut query: Query<(&mut CameraController, &mut Transform, &mut Projection)>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let elapsed = time.elapsed_seconds();
+
+    for (mut controller, mut transform, mut projection) in query.iter_mut() {
+        let t = (ZOOM_SPEED * delta_seconds).min(1.0);
+        controller.zoom += (controller.target_zoom - controller.zoom) * t;
+        controller.trauma = (controller.trauma - TRAUMA_DECAY * delta_seconds).max(0.0);
+
+        let shake = controller.trauma.powi(2);
+        let sample = elapsed * SHAKE_FREQUENCY;
+        let shake_offset = Vec2::new(
+            simplex_noise_2d(Vec2::new(sample, 0.0)),
+            simplex_noise_2d(Vec2::new(sample, 10.0)),
+        ) * MAX_SHAKE_OFFSET
+            * shake;
+        let shake_roll = simplex_noise_2d(Vec2::new(sample, 20.0)) * MAX_SHAKE_ROLL * shake;
+
+        let home = controller.home;
+        transform.translation = home.translation + (controller.focus + shake_offset).extend(0.0);
+        transform.rotation = home.rotation * Quat::from_rotation_z(shake_roll);
+
+        match &mut *projection {
+            Projection::Orthographic(orthographic) => {
+                orthographic.scale = controller.base_orthographic_scale * controller.zoom;
+            }
+            // Perspective cameras back away instead, keeping the play plane at the same angle
+            Projection::Perspective(_) => {
+                transform.translation += home.back() * home.translation.z * (controller.zoom - 1.0);
+            }
+        }
+    }
+}
+
+fn apply_parallax(
+    camera_query: Query<&CameraController>,
+    mut query: Query<(&Parallax, &mut Transform), Without<CameraController>>,
+) {
+    let Ok(controller) = camera_query.get_single() else {
+        return;
+    };
+
+    for (parallax, mut transform) in query.iter_mut() {
+        transform.translation = parallax_translation(parallax, controller.offset());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use bevy::render::camera::ScalingMode;
+
+    use super::*;
+    use crate::{visible_play_area, Bounds};
+
+    const TOLERANCE: f32 = 1e-3;
+
+    const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+    fn parallax(factor: Vec2, tile_width: Option<f32>) -> Parallax {
+        Parallax {
+            factor,
+            origin: Vec3::new(1.0, 2.0, -10.0),
+            tile_width,
+        }
+    }
+
+    #[test]
+    fn parallax_layers_scroll_by_their_factor() {
+        let offset = Vec2::new(12.0, -4.0);
+
+        let still = parallax(Vec2::ZERO, None);
+        assert_eq!(parallax_translation(&still, offset), still.origin);
+
+        let far = parallax(Vec2::ONE, None);
+        assert_eq!(
+            parallax_translation(&far, offset),
+            far.origin + offset.extend(0.0)
+        );
+
+        let between = parallax(Vec2::new(0.5, 0.25), None);
+        assert_eq!(
+            parallax_translation(&between, offset),
+            between.origin + Vec3::new(6.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn tiled_layers_stay_within_half_a_tile_of_the_camera() {
+        let tile_width = 10.0;
+        let tiled = parallax(Vec2::new(0.2, 0.5), Some(tile_width));
+        let untiled = parallax(tiled.factor, None);
+
+        for step in -40..=40 {
+            let offset = Vec2::new(step as f32 * 1.7, step as f32 * 0.3);
+            let translation = parallax_translation(&tiled, offset);
+            let scrolled = parallax_translation(&untiled, offset);
+
+            // Only ever moved along by whole tiles, and only along x
+            let jump = (translation.x - scrolled.x) / tile_width;
+            assert!(
+                (jump - jump.round()).abs() < TOLERANCE,
+                "{jump} tiles at {offset}"
+            );
+            assert_eq!(translation.y, scrolled.y);
+            assert_eq!(translation.z, scrolled.z);
+
+            let behind = offset.x - (translation.x - tiled.origin.x);
+            assert!(behind.abs() <= tile_width / 2.0 + TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn dead_zones_only_pull_by_what_sticks_out() {
+        let center = Vec2::new(1.0, 1.0);
+        let half_size = Vec2::new(3.0, 2.0);
+        let offset = |x, y| dead_zone_offset(center, half_size, Vec2::new(x, y));
+
+        assert_eq!(offset(1.0, 1.0), Vec2::ZERO);
+        assert_eq!(offset(4.0, 3.0), Vec2::ZERO);
+        assert_eq!(offset(-2.0, -1.0), Vec2::ZERO);
+
+        assert_eq!(offset(5.5, 1.0), Vec2::new(1.5, 0.0));
+        assert_eq!(offset(-3.0, 1.0), Vec2::new(-1.0, 0.0));
+        assert_eq!(offset(1.0, 4.0), Vec2::new(0.0, 1.0));
+        assert_eq!(offset(1.0, -3.0), Vec2::new(0.0, -2.0));
+        assert_eq!(offset(6.0, -2.0), Vec2::new(2.0, -1.0));
+    }
+
+    /// What the camera sees once it's settled at `zoom`, tilted like the game's camera
+    fn settled_view(projection: Projection, zoom: f32) -> Bounds {
+        let mut home = Transform::from_xyz(0.0, 0.0, 30.0);
+        home.rotate_x(-PI / 40.0);
+
+        let mut controller = CameraController::new(home, &projection);
+        controller.zoom = zoom;
+        controller.target_zoom = zoom;
+
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_system(apply_camera_controller);
+        let camera = app.world.spawn((controller, home, projection)).id();
+        app.update();
+
+        let transform = app.world.get::<Transform>(camera).unwrap();
+        let projection = app.world.get::<Projection>(camera).unwrap();
+
+        visible_play_area(transform, projection, VIEWPORT)
+            .expect("the play plane should still be in view")
+    }
+
+    fn assert_zoomed_out(projection: Projection) {
+        let normal = settled_view(projection.clone(), 1.0);
+        let boss = settled_view(projection, BOSS_ZOOM);
+
+        assert!(boss.min.cmple(normal.min).all() && boss.max.cmpge(normal.max).all());
+
+        let ratio = (boss.max - boss.min) / (normal.max - normal.min);
+        assert!(
+            ratio.abs_diff_eq(Vec2::splat(BOSS_ZOOM), TOLERANCE * 10.0),
+            "zoomed out by {ratio} instead of {BOSS_ZOOM}"
+        );
+    }
+
+    #[test]
+    fn boss_zoom_widens_the_orthographic_view() {
+        assert_zoomed_out(Projection::Orthographic(OrthographicProjection {
+            scale: 8.0,
+            scaling_mode: ScalingMode::FixedVertical(4.0),
+            ..default()
+        }));
+    }
+
+    #[test]
+    fn boss_zoom_widens_the_perspective_view() {
+        assert_zoomed_out(Projection::Perspective(PerspectiveProjection::default()));
+    }
+}
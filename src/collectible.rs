@@ -0,0 +1,650 @@
+use std::{collections::HashMap, f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+use rand::{seq::SliceRandom, Rng};
+use strum::IntoEnumIterator;
+
+use crate::{
+    biome::{Biome, CurrentBiome},
+    collision::Hitbox,
+    depth::collectible_height,
+    fishy_assets::{AudioCollection, ShellType, ShellsCollection},
+    input::Player,
+    rng::GameRng,
+    settings::AudioSettings,
+    stats::{Energy, Score},
+    Bounds, GameState, SimulationSet,
+};
+
+pub struct CollectiblePlugin;
+
+impl Plugin for CollectiblePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollectibleSettings>()
+            .init_resource::<CollectibleSpawnTimer>()
+            .add_event::<CollectiblePickedUp>()
+            .add_system(setup_collectible_assets.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (
+                    tick_collectible_spawn_timer,
+                    spawn_collectible,
+                    age_collectibles,
+                    bob,
+                    pick_up_collectibles,
+                    reward_pickups,
+                    play_pickup_sounds,
+                    expire_collectibles,
+                    animate_pickup_effects,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CollectibleKind {
+    Shell,
+    Pearl,
+    FoodFlake,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CollectibleValue {
+    Points(u32),
+    Energy(f32),
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectibleEntry {
+    pub kind: CollectibleKind,
+
+    /// Relative chance of this entry being picked
+    pub weight: f32,
+
+    pub value: CollectibleValue,
+}
+
+/// Everything needed to decide what, and how often, collectibles spawn in a biome
+#[derive(Debug, Clone)]
+pub struct CollectibleTable {
+    pub spawn_interval: f32,
+
+    pub entries: Vec<CollectibleEntry>,
+}
+
+impl CollectibleTable {
+    pub fn choose(&self, rng: &mut impl Rng) -> Option<&CollectibleEntry> {
+        self.entries.choose_weighted(rng, |entry| entry.weight).ok()
+    }
+}
+
+#[derive(Resource)]
+pub struct CollectibleSettings {
+    pub tables: HashMap<Biome, CollectibleTable>,
+
+    /// How long a collectible sticks around before it disappears
+    pub lifetime: f32,
+}
+
+impl Default for CollectibleSettings {
+    fn default() -> CollectibleSettings {
+        use CollectibleKind::*;
+        use CollectibleValue::Points;
+
+        let entry = |kind, weight, value| CollectibleEntry {
+            kind,
+            weight,
+            value,
+        };
+
+        let tables = Biome::iter()
+            .map(|biome| {
+                let table = match biome {
+                    Biome::Reef => CollectibleTable {
+                        spawn_interval: 2.0,
+                        entries: vec![
+                            entry(Shell, 4.0, Points(10)),
+                            entry(Pearl, 1.0, Points(50)),
+                            entry(FoodFlake, 5.0, CollectibleValue::Energy(10.0)),
+                        ],
+                    },
+                    Biome::KelpForest => CollectibleTable {
+                        spawn_interval: 2.5,
+                        entries: vec![
+                            entry(Shell, 2.0, Points(10)),
+                            entry(Pearl, 1.0, Points(50)),
+                            entry(FoodFlake, 8.0, CollectibleValue::Energy(15.0)),
+                        ],
+                    },
+                    Biome::Abyss => CollectibleTable {
+                        spawn_interval: 4.0,
+                        entries: vec![
+                            entry(Shell, 1.0, Points(20)),
+                            entry(Pearl, 2.0, Points(100)),
+                            entry(FoodFlake, 2.0, CollectibleValue::Energy(20.0)),
+                        ],
+                    },
+                };
+
+                (biome, table)
+            })
+            .collect();
+
+        CollectibleSettings {
+            tables,
+            lifetime: 12.0,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct CollectibleSpawnTimer {
+    pub timer: Timer,
+}
+
+impl Default for CollectibleSpawnTimer {
+    fn default() -> CollectibleSpawnTimer {
+        CollectibleSpawnTimer {
+            timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Meshes and materials for the collectibles that don't have a glTF model
+#[derive(Resource)]
+pub struct CollectibleAssets {
+    pub pearl_mesh: Handle<Mesh>,
+
+    pub pearl_material: Handle<StandardMaterial>,
+
+    pub food_flake_mesh: Handle<Mesh>,
+
+    pub food_flake_material: Handle<StandardMaterial>,
+}
+
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Collectible {
+    pub kind: CollectibleKind,
+
+    pub value: CollectibleValue,
+
+    pub age: f32,
+}
+
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Bobbing {
+    pub base_y: f32,
+
+    pub elapsed: f32,
+
+    pub amplitude: f32,
+
+    pub frequency: f32,
+
+    pub phase: f32,
+
+    pub spin_speed: f32,
+}
+
+/// Scales a picked up collectible up and away before removing it
+#[derive(Component)]
+pub struct PickupEffect {
+    pub timer: Timer,
+
+    pub start_scale: Vec3,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CollectiblePickedUp {
+    pub player: Entity,
+
+    pub kind: CollectibleKind,
+
+    pub value: CollectibleValue,
+
+    pub position: Vec3,
+}
+
+fn setup_collectible_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(CollectibleAssets {
+        pearl_mesh: meshes.add(
+            shape::UVSphere {
+                radius: 0.25,
+                ..default()
+            }
+            .into(),
+        ),
+        pearl_material: materials.add(StandardMaterial {
+            base_color: Color::hex("f4eee6").unwrap(),
+            perceptual_roughness: 0.15,
+            reflectance: 0.9,
+            ..default()
+        }),
+        food_flake_mesh: meshes.add(shape::Box::new(0.3, 0.05, 0.2).into()),
+        food_flake_material: materials.add(StandardMaterial {
+            base_color: Color::hex("e07a2f").unwrap(),
+            perceptual_roughness: 0.9,
+            ..default()
+        }),
+    });
+}
+
+pub fn tick_collectible_spawn_timer(
+    mut collectible_spawn_timer: ResMut<CollectibleSpawnTimer>,
+    collectible_settings: Res<CollectibleSettings>,
+    current_biome: Res<CurrentBiome>,
+    time: Res<Time>,
+) {
+    if let Some(table) = collectible_settings.tables.get(&current_biome.biome) {
+        let interval = Duration::from_secs_f32(table.spawn_interval);
+
+        if collectible_spawn_timer.timer.duration() != interval {
+            collectible_spawn_timer.timer.set_duration(interval);
+        }
+    }
+
+    collectible_spawn_timer.timer.tick(time.delta());
+}
+
+/// Picks a spot the player can actually reach. Shells rest near the seabed, food can be
+/// anywhere in the water and pearls favour the deep. There's nowhere to put one when the visible
+/// area is too small to keep it clear of the edges.
+fn reachable_position(kind: CollectibleKind, bounds: &Bounds, rng: &mut impl Rng) -> Option<Vec3> {
+    const EDGE_PADDING: f32 = 1.0;
+
+    let min = bounds.min + Vec2::splat(EDGE_PADDING);
+    let max = bounds.max - Vec2::splat(EDGE_PADDING);
+    if min.x >= max.x || min.y >= max.y {
+        return None;
+    }
+
+    let height = max.y - min.y;
+    let x = rng.gen_range(min.x..max.x);
+    let y = match kind {
+        CollectibleKind::Shell => rng.gen_range(min.y..min.y + height / 5.0),
+        CollectibleKind::Pearl => collectible_height(bounds, min.y, max.y, rng),
+        CollectibleKind::FoodFlake => rng.gen_range(min.y + height / 3.0..max.y),
+    };
+
+    Some(Vec3::new(x, y, 0.0))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_collectible(
+    mut commands: Commands,
+    bounds: Res<Bounds>,
+    collectible_spawn_timer: Res<CollectibleSpawnTimer>,
+    collectible_settings: Res<CollectibleSettings>,
+    current_biome: Res<CurrentBiome>,
+    collectible_assets: Res<CollectibleAssets>,
+    shells_collection: Res<ShellsCollection>,
+    game_rng: Res<GameRng>,
+) {
+    if !collectible_spawn_timer.timer.just_finished() {
+        return;
+    }
+
+    let Some(table) = collectible_settings.tables.get(&current_biome.biome) else {
+        return;
+    };
+
+    let mut rng = game_rng.stream("collectibles");
+    let Some(entry) = table.choose(&mut rng) else {
+        return;
+    };
+
+    let Some(position) = reachable_position(entry.kind, &bounds, &mut rng) else {
+        return;
+    };
+    let transform = Transform::from_translation(position);
+    let collectible = Collectible {
+        kind: entry.kind,
+        value: entry.value,
+        age: 0.0,
+    };
+    let bobbing = Bobbing {
+        base_y: position.y,
+        elapsed: 0.0,
+        amplitude: rng.gen_range(0.1..0.3),
+        frequency: rng.gen_range(0.5..1.0),
+        phase: rng.gen_range(0.0..TAU),
+        spin_speed: rng.gen_range(0.5..1.5),
+    };
+
+    match entry.kind {
+        CollectibleKind::Shell => {
+            let shell_types = ShellType::iter().collect::<Vec<_>>();
+            let shell_type = shell_types.choose(&mut rng).unwrap();
+
+            commands.spawn((
+                SceneBundle {
+                    scene: shell_type.model_from(&shells_collection),
+                    transform: transform.with_scale(Vec3::splat(0.5)),
+                    ..default()
+                },
+                collectible,
+                bobbing,
+                Hitbox::new(0.5),
+            ));
+        }
+        CollectibleKind::Pearl => {
+            commands.spawn((
+                PbrBundle {
+                    mesh: collectible_assets.pearl_mesh.clone(),
+                    material: collectible_assets.pearl_material.clone(),
+                    transform,
+                    ..default()
+                },
+                collectible,
+                bobbing,
+                Hitbox::new(0.35),
+            ));
+        }
+        CollectibleKind::FoodFlake => {
+            commands.spawn((
+                PbrBundle {
+                    mesh: collectible_assets.food_flake_mesh.clone(),
+                    material: collectible_assets.food_flake_material.clone(),
+                    transform,
+                    ..default()
+                },
+                collectible,
+                bobbing,
+                Hitbox::new(0.3),
+            ));
+        }
+    }
+}
+
+pub fn age_collectibles(mut query: Query<&mut Collectible>, time: Res<Time>) {
+    for mut collectible in query.iter_mut() {
+        collectible.age += time.delta_seconds();
+    }
+}
+
+/// Bobs anything floating in the water up and down while slowly spinning it
+pub fn bob(mut query: Query<(&mut Transform, &mut Bobbing)>, time: Res<Time>) {
+    let delta_seconds = time.delta_seconds();
+
+    for (mut transform, mut bobbing) in query.iter_mut() {
+        bobbing.elapsed += delta_seconds;
+
+        let wave = (bobbing.elapsed * bobbing.frequency * TAU + bobbing.phase).sin();
+        transform.translation.y = bobbing.base_y + wave * bobbing.amplitude;
+        transform.rotate_y(bobbing.spin_speed * delta_seconds);
+    }
+}
+
+pub fn pick_up_collectibles(
+    mut commands: Commands,
+    collectible_query: Query<(Entity, &Transform, &Hitbox, &Collectible)>,
+    player_query: Query<(Entity, &Transform, &Hitbox), With<Player>>,
+    mut pickup_events: EventWriter<CollectiblePickedUp>,
+) {
+    for (entity, transform, hitbox, collectible) in collectible_query.iter() {
+        let Some((player, _, _)) =
+            player_query
+                .iter()
+                .find(|(_, player_transform, player_hitbox)| {
+                    hitbox.overlaps(
+                        transform.translation,
+                        player_hitbox,
+                        player_transform.translation,
+                    )
+                })
+        else {
+            continue;
+        };
+
+        pickup_events.send(CollectiblePickedUp {
+            player,
+            kind: collectible.kind,
+            value: collectible.value,
+            position: transform.translation,
+        });
+
+        commands
+            .entity(entity)
+            .remove::<(Collectible, Bobbing, Hitbox)>()
+            .insert(PickupEffect {
+                timer: Timer::from_seconds(0.25, TimerMode::Once),
+                start_scale: transform.scale,
+            });
+    }
+}
+
+pub fn reward_pickups(
+    mut pickup_events: EventReader<CollectiblePickedUp>,
+    mut score: ResMut<Score>,
+    mut energy: ResMut<Energy>,
+) {
+    for pickup in pickup_events.iter() {
+        match pickup.value {
+            CollectibleValue::Points(points) => score.points += points,
+            CollectibleValue::Energy(amount) => energy.add(amount),
+        }
+    }
+}
+
+pub fn play_pickup_sounds(
+    mut pickup_events: EventReader<CollectiblePickedUp>,
+    audio: Res<Audio>,
+    audio_collection: Res<AudioCollection>,
+    audio_settings: Res<AudioSettings>,
+) {
+    for _ in pickup_events.iter() {
+        audio
+            .play(audio_collection.pickup.clone())
+            .with_volume(audio_settings.effects);
+    }
+}
+
+pub fn expire_collectibles(
+    mut commands: Commands,
+    query: Query<(Entity, &Collectible)>,
+    collectible_settings: Res<CollectibleSettings>,
+) {
+    for (entity, collectible) in query.iter() {
+        if collectible.age > collectible_settings.lifetime {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn animate_pickup_effects(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut PickupEffect)>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut effect) in query.iter_mut() {
+        if effect.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let progress = effect.timer.percent();
+        transform.scale = effect.start_scale * (1.0 + progress);
+        transform.translation.y += time.delta_seconds() * 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const DRAWS: usize = 10_000;
+
+    fn bounds(half_size: Vec2) -> Bounds {
+        Bounds {
+            min: -half_size,
+            max: half_size,
+        }
+    }
+
+    #[test]
+    fn each_biome_picks_entries_by_weight() {
+        let collectible_settings = CollectibleSettings::default();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for biome in Biome::iter() {
+            let table = &collectible_settings.tables[&biome];
+            let total_weight = table.entries.iter().map(|entry| entry.weight).sum::<f32>();
+
+            let mut counts = HashMap::new();
+            for _ in 0..DRAWS {
+                let entry = table.choose(&mut rng).unwrap();
+                *counts.entry(entry.kind).or_insert(0) += 1;
+            }
+
+            for entry in &table.entries {
+                let expected = entry.weight / total_weight;
+                let actual = counts.get(&entry.kind).copied().unwrap_or(0) as f32 / DRAWS as f32;
+
+                assert!(
+                    (actual - expected).abs() < 0.02,
+                    "{:?} in {biome:?} picked {actual} of the time instead of {expected}",
+                    entry.kind
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn an_empty_or_weightless_table_picks_nothing() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut table = CollectibleTable {
+            spawn_interval: 1.0,
+            entries: Vec::new(),
+        };
+        assert!(table.choose(&mut rng).is_none());
+
+        table.entries.push(CollectibleEntry {
+            kind: CollectibleKind::Pearl,
+            weight: 0.0,
+            value: CollectibleValue::Points(1),
+        });
+        assert!(table.choose(&mut rng).is_none());
+    }
+
+    #[test]
+    fn positions_stay_clear_of_the_edges() {
+        let bounds = bounds(Vec2::new(8.0, 6.0));
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for kind in [
+            CollectibleKind::Shell,
+            CollectibleKind::Pearl,
+            CollectibleKind::FoodFlake,
+        ] {
+            for _ in 0..100 {
+                let position = reachable_position(kind, &bounds, &mut rng).unwrap();
+
+                assert!(position.x >= -7.0 && position.x < 7.0);
+                assert!(position.y >= -5.0 && position.y < 5.0);
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_spawns_in_a_tiny_area() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for half_size in [Vec2::new(0.5, 6.0), Vec2::new(8.0, 1.0), Vec2::ZERO] {
+            assert_eq!(
+                reachable_position(CollectibleKind::Shell, &bounds(half_size), &mut rng),
+                None
+            );
+        }
+    }
+
+    fn pickup_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Score>()
+            .init_resource::<Energy>()
+            .add_event::<CollectiblePickedUp>()
+            .add_systems((pick_up_collectibles, reward_pickups).chain());
+
+        app
+    }
+
+    fn spawn_collectible_at(app: &mut App, x: f32, value: CollectibleValue) -> Entity {
+        app.world
+            .spawn((
+                Transform::from_xyz(x, 0.0, 0.0),
+                Hitbox::new(0.5),
+                Collectible {
+                    kind: CollectibleKind::Shell,
+                    value,
+                    age: 0.0,
+                },
+                Bobbing {
+                    base_y: 0.0,
+                    elapsed: 0.0,
+                    amplitude: 0.1,
+                    frequency: 1.0,
+                    phase: 0.0,
+                    spin_speed: 1.0,
+                },
+            ))
+            .id()
+    }
+
+    #[test]
+    fn only_overlapping_collectibles_are_picked_up() {
+        let mut app = pickup_app();
+        app.world
+            .spawn((Transform::default(), Hitbox::new(0.5), Player::default()));
+        let touching = spawn_collectible_at(&mut app, 0.9, CollectibleValue::Points(10));
+        let apart = spawn_collectible_at(&mut app, 1.1, CollectibleValue::Points(10));
+
+        app.update();
+
+        let touching = app.world.entity(touching);
+        assert!(!touching.contains::<Collectible>());
+        assert!(!touching.contains::<Hitbox>());
+        assert!(touching.contains::<PickupEffect>());
+
+        let apart = app.world.entity(apart);
+        assert!(apart.contains::<Collectible>());
+        assert!(!apart.contains::<PickupEffect>());
+
+        assert_eq!(app.world.resource::<Score>().points, 10);
+    }
+
+    #[test]
+    fn pickups_reward_points_or_energy() {
+        let mut app = pickup_app();
+        app.world
+            .spawn((Transform::default(), Hitbox::new(0.5), Player::default()));
+        spawn_collectible_at(&mut app, 0.0, CollectibleValue::Points(50));
+        spawn_collectible_at(&mut app, 0.2, CollectibleValue::Energy(10.0));
+        spawn_collectible_at(&mut app, -0.2, CollectibleValue::Energy(100.0));
+
+        app.update();
+
+        let energy = app.world.resource::<Energy>();
+        assert_eq!(app.world.resource::<Score>().points, 50);
+        // Energy doesn't overflow its maximum
+        assert_eq!(energy.current, energy.max);
+    }
+
+    #[test]
+    fn nothing_is_picked_up_without_a_player() {
+        let mut app = pickup_app();
+        let collectible = spawn_collectible_at(&mut app, 0.0, CollectibleValue::Points(10));
+
+        app.update();
+
+        assert!(app.world.entity(collectible).contains::<Collectible>());
+        assert_eq!(app.world.resource::<Score>().points, 0);
+    }
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+
+/// A simple circular hitbox on the xy play plane, in world units
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Hitbox {
+    pub radius: f32,
+}
+
+impl Hitbox {
+    pub fn new(radius: f32) -> Hitbox {
+        Hitbox { radius }
+    }
+
+    /// Whether two hitboxes at the given positions overlap. Depth (z) is ignored.
+    pub fn overlaps(&self, position: Vec3, other: &Hitbox, other_position: Vec3) -> bool {
+        let distance = position.truncate().distance(other_position.truncate());
+
+        distance < self.radius + other.radius
+    }
+}
+
+/// The distance from `point` to the closest point on the segment between `start` and `end`
+pub fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+
+    if length_squared <= f32::EPSILON {
+        return point.distance(start);
+    }
+
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+
+    point.distance(start + segment * t)
+}
+
+/// Scales an entity's transform and hitbox together
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct BodyScale {
+    pub base: f32,
+
+    /// Temporary changes in size multiply into this and divide back out when they end
+    pub multiplier: f32,
+
+    hitbox_per_scale: f32,
+}
+
+impl BodyScale {
+    pub fn new(base: f32, hitbox: Hitbox) -> BodyScale {
+        BodyScale {
+            base,
+            multiplier: 1.0,
+            hitbox_per_scale: hitbox.radius / base,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.base * self.multiplier
+    }
+}
+
+pub fn apply_body_scale(
+    mut query: Query<(&BodyScale, &mut Transform, &mut Hitbox), Changed<BodyScale>>,
+) {
+    for (body_scale, mut transform, mut hitbox) in query.iter_mut() {
+        let scale = body_scale.scale();
+
+        transform.scale = Vec3::splat(scale);
+        hitbox.radius = body_scale.hitbox_per_scale * scale;
+    }
+}
+
+/// Absorbs harmful hits before they reach the player
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq)]
+pub struct HitGuard {
+    /// Each charge blocks a single hit
+    pub charges: u32,
+
+    /// While any shields are up every hit is blocked
+    pub shields: u32,
+}
+
+impl HitGuard {
+    /// Blocks the hit if shielded, otherwise uses up a charge if there is one. Returns whether
+    /// the hit was blocked.
+    pub fn absorb(&mut self) -> bool {
+        if self.shields > 0 {
+            return true;
+        }
+
+        if self.charges == 0 {
+            return false;
+        }
+
+        self.charges -= 1;
+        true
+    }
+}
+
+/// How many more hits the player can take in the classic mode before the run is over
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Health {
+    pub current: u32,
+
+    pub max: u32,
+}
+
+impl Default for Health {
+    fn default() -> Health {
+        Health { current: 3, max: 3 }
+    }
+}
+
+impl Health {
+    /// Takes a hit. Returns whether that was the last of it.
+    pub fn hurt(&mut self) -> bool {
+        self.current = self.current.saturating_sub(1);
+
+        self.current == 0
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current as f32 / self.max as f32
+    }
+}
+
+/// Sent once when a hazard first touches the player
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlayerHitEvent {
+    pub player: Entity,
+
+    pub hazard: Entity,
+}
+
+/// Sent when a hit gets through to the player, as opposed to one that was blocked or, in the
+/// feeding frenzy, one that was the player taking a bite
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlayerDamagedEvent {
+    pub player: Entity,
+}
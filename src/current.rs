@@ -0,0 +1,392 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use noisy_bevy::simplex_noise_3d;
+use rand::Rng;
+
+use crate::{
+    biome::{CurrentBiome, Currents},
+    fixed_step::{Interpolated, SimulationTime, StepSet},
+    hazard::Stunned,
+    Bounds, GameState, SimulationSet,
+};
+
+// Ocean currents that push everything that drifts around the screen
+pub struct CurrentPlugin;
+
+impl Plugin for CurrentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentField>()
+            .init_resource::<CurrentDebug>()
+            .add_systems(
+                (
+                    // Waits for the first frame of play so that the bounds are known
+                    setup_current_visuals.run_if(not(any_with_component::<CurrentMote>())),
+                    sync_current_field,
+                    drift_scenery,
+                    wrap_current_motes,
+                    toggle_current_debug,
+                    draw_current_debug,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            )
+            .add_system(
+                apply_currents
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(StepSet::Movement)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+const MOTE_COUNT: usize = 80;
+const DEBUG_GRID_COLUMNS: usize = 16;
+const DEBUG_GRID_ROWS: usize = 12;
+/// World units of arrow per unit of flow
+const DEBUG_ARROW_SCALE: f32 = 2.0;
+
+/// The currents of the biome being played in
+#[derive(Resource)]
+pub struct CurrentField {
+    pub currents: Currents,
+}
+
+impl Default for CurrentField {
+    fn default() -> CurrentField {
+        CurrentField {
+            currents: CurrentBiome::default().biome.currents(),
+        }
+    }
+}
+
+impl CurrentField {
+    /// The flow of the water at a point on the play plane
+    pub fn flow_at(&self, bounds: &Bounds, position: Vec2, time: f32) -> Vec2 {
+        let currents = &self.currents;
+        let sample = (position / currents.noise_scale).extend(time * currents.evolution_speed);
+        // A second, offset sample so the strength doesn't line up with the direction
+        let angle = simplex_noise_3d(sample) * TAU;
+        let strength = simplex_noise_3d(sample + Vec3::splat(100.0)) * 0.5 + 0.5;
+        let mut flow = currents.base + Vec2::from_angle(angle) * currents.turbulence * strength;
+
+        let size = bounds.max - bounds.min;
+        if size.x > 0.0 && size.y > 0.0 {
+            let normalized = (position - bounds.min) / size;
+
+            for zone in currents.zones.iter() {
+                if normalized.cmpge(zone.min).all() && normalized.cmple(zone.max).all() {
+                    flow += zone.flow;
+                }
+            }
+        }
+
+        flow
+    }
+}
+
+/// Anything with this is carried along by the currents. `response` scales how much, so heavy
+/// fish can shrug them off.
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Drift {
+    pub response: f32,
+}
+
+impl Default for Drift {
+    fn default() -> Drift {
+        Drift { response: 1.0 }
+    }
+}
+
+/// A speck floating in the water that makes the currents visible
+#[derive(Component)]
+pub struct CurrentMote;
+
+/// Draws the flow at a point of a grid over the screen, given as a fraction of the `Bounds`
+#[derive(Component)]
+pub struct CurrentArrow {
+    pub sample: Vec2,
+}
+
+/// Toggled with F3
+#[derive(Resource, Default)]
+pub struct CurrentDebug {
+    pub enabled: bool,
+}
+
+fn setup_current_visuals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    bounds: Res<Bounds>,
+) {
+    let mote_mesh = meshes.add(
+        shape::UVSphere {
+            radius: 0.05,
+            sectors: 6,
+            stacks: 4,
+        }
+        .into(),
+    );
+    let mote_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 1.0, 1.0, 0.5),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..MOTE_COUNT {
+        let x = rng.gen_range(bounds.min.x..bounds.max.x);
+        let y = rng.gen_range(bounds.min.y..bounds.max.y);
+        let z = rng.gen_range(-2.0..2.0);
+
+        commands.spawn((
+            PbrBundle {
+                mesh: mote_mesh.clone(),
+                material: mote_material.clone(),
+                transform: Transform::from_xyz(x, y, z),
+                ..default()
+            },
+            CurrentMote,
+            // Motes are light so they show the currents off a bit more than they really are
+            Drift { response: 1.5 },
+        ));
+    }
+
+    let arrow_mesh = meshes.add(shape::Quad::new(Vec2::new(1.0, 0.08)).into());
+    let arrow_material = materials.add(StandardMaterial {
+        base_color: Color::YELLOW,
+        unlit: true,
+        ..default()
+    });
+
+    for column in 0..DEBUG_GRID_COLUMNS {
+        for row in 0..DEBUG_GRID_ROWS {
+            let sample = Vec2::new(
+                (column as f32 + 0.5) / DEBUG_GRID_COLUMNS as f32,
+                (row as f32 + 0.5) / DEBUG_GRID_ROWS as f32,
+            );
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: arrow_mesh.clone(),
+                    material: arrow_material.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                CurrentArrow { sample },
+            ));
+        }
+    }
+}
+
+fn sync_current_field(current_biome: Res<CurrentBiome>, mut current_field: ResMut<CurrentField>) {
+    if current_biome.is_changed() {
+        current_field.currents = current_biome.biome.currents();
+    }
+}
+
+/// Carries the player and hazards along. Stunned hazards stop dead, currents and all.
+#[allow(clippy::type_complexity)]
+fn apply_currents(
+    mut query: Query<(&mut Transform, &Drift), (With<Interpolated>, Without<Stunned>)>,
+    current_field: Res<CurrentField>,
+    bounds: Res<Bounds>,
+    simulation_time: Res<SimulationTime>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+
+    for (mut transform, drift) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        let flow = current_field.flow_at(&bounds, position, simulation_time.elapsed);
+
+        transform.translation += (flow * drift.response * delta_seconds).extend(0.0);
+    }
+}
+
+/// Motes and ambient fish are only there to look at, so they drift every frame instead
+fn drift_scenery(
+    mut query: Query<(&mut Transform, &Drift), Without<Interpolated>>,
+    current_field: Res<CurrentField>,
+    bounds: Res<Bounds>,
+    simulation_time: Res<SimulationTime>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (mut transform, drift) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        let flow = current_field.flow_at(&bounds, position, simulation_time.elapsed);
+
+        transform.translation += (flow * drift.response * delta_seconds).extend(0.0);
+    }
+}
+
+/// Motes that drift off one side come back in on the other
+fn wrap_current_motes(mut query: Query<&mut Transform, With<CurrentMote>>, bounds: Res<Bounds>) {
+    let size = bounds.max - bounds.min;
+
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return;
+    }
+
+    for mut transform in query.iter_mut() {
+        let position = transform.translation.truncate() - bounds.min;
+        let wrapped =
+            bounds.min + Vec2::new(position.x.rem_euclid(size.x), position.y.rem_euclid(size.y));
+
+        transform.translation.x = wrapped.x;
+        transform.translation.y = wrapped.y;
+    }
+}
+
+fn toggle_current_debug(keys: Res<Input<KeyCode>>, mut current_debug: ResMut<CurrentDebug>) {
+    if keys.just_pressed(KeyCode::F3) {
+        current_debug.enabled = !current_debug.enabled;
+    }
+}
+
+fn draw_current_debug(
+    mut query: Query<(&CurrentArrow, &mut Transform, &mut Visibility)>,
+    current_debug: Res<CurrentDebug>,
+    current_field: Res<CurrentField>,
+    bounds: Res<Bounds>,
+    simulation_time: Res<SimulationTime>,
+) {
+    if current_debug.is_changed() {
+        let visibility = if current_debug.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        for (_, _, mut arrow_visibility) in query.iter_mut() {
+            *arrow_visibility = visibility;
+        }
+    }
+
+    if !current_debug.enabled {
+        return;
+    }
+
+    for (arrow, mut transform, _) in query.iter_mut() {
+        let start = bounds.min + (bounds.max - bounds.min) * arrow.sample;
+        let flow = current_field.flow_at(&bounds, start, simulation_time.elapsed);
+        let length = flow.length() * DEBUG_ARROW_SCALE;
+
+        // The quad is centered so push it forward by half its length to start at the sample
+        transform.translation = (start + flow.normalize_or_zero() * length / 2.0).extend(1.0);
+        transform.rotation = Quat::from_rotation_z(flow.y.atan2(flow.x));
+        transform.scale = Vec3::new(length.max(0.01), 1.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biome::CurrentZone;
+
+    const TOLERANCE: f32 = 1e-5;
+
+    fn bounds() -> Bounds {
+        Bounds {
+            min: Vec2::new(-8.0, -6.0),
+            max: Vec2::new(8.0, 6.0),
+        }
+    }
+
+    /// Still water apart from a zone over the right half of the screen
+    fn zoned_field() -> CurrentField {
+        CurrentField {
+            currents: Currents {
+                base: Vec2::ZERO,
+                turbulence: 0.0,
+                noise_scale: 4.0,
+                evolution_speed: 0.1,
+                zones: vec![CurrentZone {
+                    min: Vec2::new(0.5, 0.0),
+                    max: Vec2::new(1.0, 1.0),
+                    flow: Vec2::new(2.0, 0.0),
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn zones_cover_the_same_part_of_any_bounds() {
+        let field = zoned_field();
+        let zone_flow = Vec2::new(2.0, 0.0);
+
+        let bounds = bounds();
+        assert_eq!(field.flow_at(&bounds, Vec2::new(4.0, 0.0), 0.0), zone_flow);
+        assert_eq!(
+            field.flow_at(&bounds, Vec2::new(-4.0, 0.0), 0.0),
+            Vec2::ZERO
+        );
+
+        // Twice as wide and moved over, so the same points fall on the other side of halfway
+        let moved = Bounds {
+            min: Vec2::new(0.0, -6.0),
+            max: Vec2::new(32.0, 6.0),
+        };
+        assert_eq!(field.flow_at(&moved, Vec2::new(4.0, 0.0), 0.0), Vec2::ZERO);
+        assert_eq!(field.flow_at(&moved, Vec2::new(20.0, 0.0), 0.0), zone_flow);
+
+        // Edges count as inside
+        assert_eq!(field.flow_at(&bounds, bounds.max, 0.0), zone_flow);
+    }
+
+    #[test]
+    fn zones_are_skipped_without_any_bounds() {
+        let field = zoned_field();
+
+        assert_eq!(
+            field.flow_at(&Bounds::default(), Vec2::new(4.0, 0.0), 0.0),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn the_flow_only_depends_on_where_and_when() {
+        let field = CurrentField::default();
+        let bounds = bounds();
+        let position = Vec2::new(3.0, -2.0);
+
+        let flow = field.flow_at(&bounds, position, 12.5);
+        assert_eq!(field.flow_at(&bounds, position, 12.5), flow);
+        assert_ne!(field.flow_at(&bounds, position, 40.0), flow);
+    }
+
+    /// Where something drifting from `start` ends up after a step at `elapsed` seconds
+    fn drift_once(start: Vec3, elapsed: f32) -> Vec3 {
+        let mut app = App::new();
+        app.insert_resource(FixedTime::new_from_secs(0.1))
+            .insert_resource(bounds())
+            .insert_resource(SimulationTime { elapsed })
+            .init_resource::<CurrentField>()
+            .add_system(apply_currents);
+
+        let transform = Transform::from_translation(start);
+        let entity = app
+            .world
+            .spawn((transform, Interpolated::from(transform), Drift::default()))
+            .id();
+        app.update();
+
+        app.world.get::<Transform>(entity).unwrap().translation
+    }
+
+    #[test]
+    fn drifting_follows_the_simulation_time() {
+        let start = Vec3::new(3.0, -2.0, 0.0);
+        let moved = drift_once(start, 12.5);
+
+        assert_eq!(drift_once(start, 12.5), moved);
+
+        let flow = CurrentField::default().flow_at(&bounds(), start.truncate(), 12.5);
+        assert!(moved.distance(start + (flow * 0.1).extend(0.0)) < TOLERANCE);
+    }
+}
@@ -0,0 +1,332 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{biome::CurrentBiome, camera::CameraController, GameState, SimulationSet};
+
+// Days and nights passing overhead. The light, fog and water color follow the time of day and
+// get darker the deeper the camera goes, and at night some corals and fish start to glow.
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .init_resource::<Glow>()
+            .add_systems(
+                (advance_time_of_day, apply_lighting, update_glow)
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+/// The furthest the sun or moon leans away from straight overhead, in radians
+const MAX_SUN_TILT: f32 = 1.1;
+
+/// How much brighter than its base color a glowing material gets at full strength
+pub const GLOW_INTENSITY: f32 = 4.0;
+
+/// The glow only changes in steps this fine. It fades a little every frame at dusk and dawn, and
+/// every glowing fish and coral has its own copy of the glow to rewrite each time it changes.
+const GLOW_STEPS: f32 = 32.0;
+
+/// The point in the day that is dark enough for the night hazards to come out
+const NIGHT_DAYLIGHT: f32 = 0.25;
+
+/// How far through the day it is, from 0 at midnight through 0.5 at noon and back around.
+/// F8 skips ahead a quarter of a day.
+#[derive(Resource, Debug)]
+pub struct TimeOfDay {
+    pub time: f32,
+
+    /// Seconds for a whole day and night
+    pub cycle_length: f32,
+
+    pub paused: bool,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> TimeOfDay {
+        TimeOfDay {
+            // Start in the morning so the first impression is bright
+            time: 0.35,
+            cycle_length: 240.0,
+            paused: false,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// How light it is, from 0 at night to 1 during the day
+    pub fn daylight(&self) -> f32 {
+        let elevation = ((self.time - 0.25) * 2.0 * PI).sin();
+
+        ((elevation + 0.2) / 0.6).clamp(0.0, 1.0)
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.daylight() < NIGHT_DAYLIGHT
+    }
+
+    /// How far the sun, or the moon at night, leans away from overhead. Both cross the sky from
+    /// one side to the other.
+    pub fn sun_tilt(&self) -> f32 {
+        let arc = (self.time * 2.0 + 0.5).fract();
+
+        (arc - 0.5) * 2.0 * MAX_SUN_TILT
+    }
+}
+
+/// How strongly bioluminescent things are glowing, from 0 by day to 1 at night or in the deep
+#[derive(Resource, Debug, Default)]
+pub struct Glow {
+    pub strength: f32,
+}
+
+/// The light that moves with the time of day
+#[derive(Component, Debug)]
+pub struct Sun;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lighting {
+    pub ambient_color: Color,
+
+    pub ambient_brightness: f32,
+
+    pub sun_color: Color,
+
+    pub sun_illuminance: f32,
+
+    pub fog_color: Color,
+
+    /// How quickly the fog thickens with distance
+    pub fog_extinction: f32,
+
+    pub clear_color: Color,
+}
+
+impl Lighting {
+    fn day() -> Lighting {
+        Lighting {
+            ambient_color: Color::WHITE,
+            ambient_brightness: 0.2,
+            sun_color: Color::WHITE,
+            sun_illuminance: 10000.0,
+            fog_color: Color::rgb(0.0, 0.5, 0.8),
+            fog_extinction: 0.005,
+            clear_color: Color::rgb(0.6, 0.8, 1.0),
+        }
+    }
+
+    fn twilight() -> Lighting {
+        Lighting {
+            ambient_color: Color::rgb(1.0, 0.8, 0.7),
+            ambient_brightness: 0.12,
+            sun_color: Color::rgb(1.0, 0.65, 0.45),
+            sun_illuminance: 4000.0,
+            fog_color: Color::rgb(0.25, 0.3, 0.55),
+            fog_extinction: 0.01,
+            clear_color: Color::rgb(0.6, 0.5, 0.8),
+        }
+    }
+
+    fn night() -> Lighting {
+        Lighting {
+            ambient_color: Color::rgb(0.4, 0.5, 1.0),
+            ambient_brightness: 0.05,
+            sun_color: Color::rgb(0.6, 0.7, 1.0),
+            sun_illuminance: 800.0,
+            fog_color: Color::rgb(0.0, 0.05, 0.15),
+            fog_extinction: 0.02,
+            clear_color: Color::rgb(0.02, 0.05, 0.2),
+        }
+    }
+
+    /// The lighting at a time of day, blended between dawn, day, dusk and night
+    pub fn at(time: f32) -> Lighting {
+        let keyframes = [
+            (0.0, Lighting::night()),
+            (0.2, Lighting::night()),
+            (0.28, Lighting::twilight()),
+            (0.38, Lighting::day()),
+            (0.62, Lighting::day()),
+            (0.72, Lighting::twilight()),
+            (0.8, Lighting::night()),
+            (1.0, Lighting::night()),
+        ];
+
+        let time = time.rem_euclid(1.0);
+        let next = keyframes
+            .iter()
+            .position(|(keyframe_time, _)| *keyframe_time >= time)
+            .unwrap_or(keyframes.len() - 1)
+            .max(1);
+        let (start_time, start) = keyframes[next - 1];
+        let (end_time, end) = keyframes[next];
+
+        start.lerp(&end, (time - start_time) / (end_time - start_time))
+    }
+
+    /// Darkens the lighting the deeper down the camera is, from 0 at the top to 1 at the bottom
+    pub fn at_depth(&self, depth: f32) -> Lighting {
+        let depth = depth.clamp(0.0, 1.0);
+        let darken = |color: Color| lerp_color(color, Color::BLACK, depth * 0.4);
+
+        Lighting {
+            ambient_brightness: self.ambient_brightness * (1.0 - depth * 0.5),
+            sun_illuminance: self.sun_illuminance * (1.0 - depth * 0.6),
+            fog_color: darken(self.fog_color),
+            fog_extinction: self.fog_extinction * (1.0 + depth * 2.0),
+            clear_color: darken(self.clear_color),
+            ..*self
+        }
+    }
+
+    fn lerp(&self, other: &Lighting, t: f32) -> Lighting {
+        Lighting {
+            ambient_color: lerp_color(self.ambient_color, other.ambient_color, t),
+            ambient_brightness: lerp(self.ambient_brightness, other.ambient_brightness, t),
+            sun_color: lerp_color(self.sun_color, other.sun_color, t),
+            sun_illuminance: lerp(self.sun_illuminance, other.sun_illuminance, t),
+            fog_color: lerp_color(self.fog_color, other.fog_color, t),
+            fog_extinction: lerp(self.fog_extinction, other.fog_extinction, t),
+            clear_color: lerp_color(self.clear_color, other.clear_color, t),
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let (a, b) = (Vec4::from(a.as_rgba_f32()), Vec4::from(b.as_rgba_f32()));
+
+    Color::from(a.lerp(b, t))
+}
+
+fn advance_time_of_day(
+    mut time_of_day: ResMut<TimeOfDay>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+) {
+    if keys.just_pressed(KeyCode::F8) {
+        time_of_day.time = (time_of_day.time + 0.25).fract();
+    }
+
+    if time_of_day.paused {
+        return;
+    }
+
+    time_of_day.time = (time_of_day.time + time.delta_seconds() / time_of_day.cycle_length).fract();
+}
+
+fn apply_lighting(
+    time_of_day: Res<TimeOfDay>,
+    camera_query: Query<&CameraController>,
+    mut fog_query: Query<&mut FogSettings>,
+    mut sun_query: Query<(&mut DirectionalLight, &mut Transform), With<Sun>>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    let depth = camera_query.get_single().map_or(0.0, |controller| {
+        let limits = controller.limits;
+
+        (limits.max.y - controller.focus.y) / (limits.max.y - limits.min.y)
+    });
+    let lighting = Lighting::at(time_of_day.time).at_depth(depth);
+
+    ambient_light.color = lighting.ambient_color;
+    ambient_light.brightness = lighting.ambient_brightness;
+    clear_color.0 = lighting.clear_color;
+
+    for mut fog in fog_query.iter_mut() {
+        fog.color = lighting.fog_color;
+        fog.falloff = FogFalloff::Atmospheric {
+            extinction: Vec3::splat(lighting.fog_extinction),
+            inscattering: Vec3::splat(lighting.fog_extinction * 0.1),
+        };
+    }
+
+    for (mut sun, mut transform) in sun_query.iter_mut() {
+        sun.color = lighting.sun_color;
+        sun.illuminance = lighting.sun_illuminance;
+        // Straight down, then leaning across the sky
+        transform.rotation =
+            Quat::from_rotation_z(time_of_day.sun_tilt()) * Quat::from_rotation_x(-PI / 2.0);
+    }
+}
+
+fn update_glow(
+    time_of_day: Res<TimeOfDay>,
+    current_biome: Res<CurrentBiome>,
+    mut glow: ResMut<Glow>,
+) {
+    // The deep is dark whatever the time of day
+    let strength = (1.0 - time_of_day.daylight()).max(current_biome.biome.darkness());
+    let strength = (strength * GLOW_STEPS).round() / GLOW_STEPS;
+
+    // Only touch it when it actually changes since everything that glows watches for changes
+    if glow.strength != strength {
+        glow.strength = strength;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct GlowChanges(usize);
+
+    fn count_glow_changes(glow: Res<Glow>, mut changes: ResMut<GlowChanges>) {
+        if glow.is_changed() {
+            changes.0 += 1;
+        }
+    }
+
+    fn glow_app(time: f32) -> App {
+        let mut app = App::new();
+        app.insert_resource(TimeOfDay { time, ..default() })
+            .init_resource::<CurrentBiome>()
+            .init_resource::<Glow>()
+            .init_resource::<GlowChanges>()
+            .add_systems((update_glow, count_glow_changes).chain());
+
+        app
+    }
+
+    #[test]
+    fn glow_is_quantized() {
+        for step in 0..=100 {
+            let mut app = glow_app(step as f32 / 100.0);
+            app.update();
+
+            let strength = app.world.resource::<Glow>().strength;
+            assert!((0.0..=1.0).contains(&strength));
+            assert_eq!((strength * GLOW_STEPS).round() / GLOW_STEPS, strength);
+        }
+    }
+
+    #[test]
+    fn the_glow_only_changes_a_step_at_a_time() {
+        // Dusk, a third of the way into the fade
+        let mut app = glow_app(0.75);
+        app.update();
+        let strength = app.world.resource::<Glow>().strength;
+        assert_eq!(app.world.resource::<GlowChanges>().0, 1);
+
+        // Far less than a step
+        app.world.resource_mut::<TimeOfDay>().time += 0.00001;
+        app.update();
+        assert_eq!(app.world.resource::<Glow>().strength, strength);
+        assert_eq!(app.world.resource::<GlowChanges>().0, 1);
+
+        // Night
+        app.world.resource_mut::<TimeOfDay>().time = 0.0;
+        app.update();
+        assert_eq!(app.world.resource::<Glow>().strength, 1.0);
+        assert_eq!(app.world.resource::<GlowChanges>().0, 2);
+    }
+}
@@ -0,0 +1,363 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    fishy_assets::FontCollection,
+    fixed_step::StepSet,
+    hud::{spawn_meter, HudRoot, Meter, MeterFill},
+    input::Player,
+    Bounds, Fish, GameState, SimulationSet,
+};
+
+// Splits the water into depth bands with their own rules, and keeps air-breathers coming back up
+// for air
+pub struct DepthPlugin;
+
+impl Plugin for DepthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (init_player_oxygen, spawn_oxygen_meter, update_oxygen_meter)
+                .chain()
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        )
+        // Running out ends the run, so it has to happen on the same step in a replay
+        .add_system(
+            breathe
+                .run_if(in_state(GameState::Playing))
+                .in_set(StepSet::Constraints)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// How much of the screen's height the surface band takes up, from the top
+const SURFACE_BAND: f32 = 0.15;
+
+/// How much of the screen's height the seabed band takes up, from the bottom
+const SEABED_BAND: f32 = 0.2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthZone {
+    Surface,
+    Mid,
+    Seabed,
+}
+
+const DEPTH_ZONES: [DepthZone; 3] = [DepthZone::Surface, DepthZone::Mid, DepthZone::Seabed];
+
+impl DepthZone {
+    /// Which band a height on the play plane falls in
+    pub fn at(bounds: &Bounds, y: f32) -> DepthZone {
+        let height = bounds.max.y - bounds.min.y;
+
+        if y >= bounds.max.y - height * SURFACE_BAND {
+            DepthZone::Surface
+        } else if y <= bounds.min.y + height * SEABED_BAND {
+            DepthZone::Seabed
+        } else {
+            DepthZone::Mid
+        }
+    }
+
+    /// The bottom and top of the band
+    pub fn band(&self, bounds: &Bounds) -> (f32, f32) {
+        let height = bounds.max.y - bounds.min.y;
+        let surface = bounds.max.y - height * SURFACE_BAND;
+        let seabed = bounds.min.y + height * SEABED_BAND;
+
+        match self {
+            DepthZone::Surface => (surface, bounds.max.y),
+            DepthZone::Mid => (seabed, surface),
+            DepthZone::Seabed => (bounds.min.y, seabed),
+        }
+    }
+
+    /// Oxygen gained per second in the band, negative when it's being used up. The pressure
+    /// down by the seabed makes holding your breath harder.
+    pub fn oxygen_rate(&self) -> f32 {
+        match self {
+            DepthZone::Surface => 25.0,
+            DepthZone::Mid => -2.5,
+            DepthZone::Seabed => -6.0,
+        }
+    }
+
+    /// How likely collectibles are to turn up in the band, relative to its height. The deep
+    /// is riskier but richer.
+    pub fn collectible_weight(&self) -> f32 {
+        match self {
+            DepthZone::Surface => 0.5,
+            DepthZone::Mid => 1.0,
+            DepthZone::Seabed => 3.0,
+        }
+    }
+}
+
+/// A random height for a collectible, favouring the bands with higher `collectible_weight`s
+pub fn collectible_height(bounds: &Bounds, min_y: f32, max_y: f32, rng: &mut impl Rng) -> f32 {
+    let bands = DEPTH_ZONES
+        .iter()
+        .filter_map(|zone| {
+            let (bottom, top) = zone.band(bounds);
+            let (bottom, top) = (bottom.max(min_y), top.min(max_y));
+
+            (top > bottom).then_some((zone.collectible_weight() * (top - bottom), bottom, top))
+        })
+        .collect::<Vec<_>>();
+
+    let Ok((_, bottom, top)) = bands.choose_weighted(rng, |(weight, _, _)| *weight) else {
+        return rng.gen_range(min_y..max_y);
+    };
+
+    rng.gen_range(*bottom..*top)
+}
+
+/// Only air-breathers have this
+#[derive(Component, Debug)]
+pub struct Oxygen {
+    pub current: f32,
+
+    pub max: f32,
+}
+
+impl Default for Oxygen {
+    fn default() -> Oxygen {
+        Oxygen {
+            current: 100.0,
+            max: 100.0,
+        }
+    }
+}
+
+impl Oxygen {
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+}
+
+fn init_player_oxygen(
+    mut commands: Commands,
+    query: Query<(Entity, &Fish), (With<Player>, Without<Oxygen>)>,
+) {
+    for (entity, fish) in query.iter() {
+        if fish.fish_type.breathes_air() {
+            commands.entity(entity).insert(Oxygen::default());
+        }
+    }
+}
+
+fn spawn_oxygen_meter(
+    mut commands: Commands,
+    oxygen_query: Query<(), Added<Oxygen>>,
+    root_query: Query<Entity, With<HudRoot>>,
+    font_collection: Res<FontCollection>,
+) {
+    if oxygen_query.is_empty() {
+        return;
+    }
+
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        spawn_meter(parent, &font_collection, Meter::Oxygen);
+    });
+}
+
+/// Refills oxygen at the surface and uses it up everywhere else. Running out is fatal.
+fn breathe(
+    mut query: Query<(&Transform, &mut Oxygen), With<Player>>,
+    bounds: Res<Bounds>,
+    fixed_time: Res<FixedTime>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+
+    for (transform, mut oxygen) in query.iter_mut() {
+        let zone = DepthZone::at(&bounds, transform.translation.y);
+
+        oxygen.current =
+            (oxygen.current + zone.oxygen_rate() * delta_seconds).clamp(0.0, oxygen.max);
+
+        if oxygen.current <= 0.0 {
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+fn update_oxygen_meter(
+    oxygen_query: Query<&Oxygen, (With<Player>, Changed<Oxygen>)>,
+    mut fill_query: Query<&mut MeterFill>,
+) {
+    for oxygen in oxygen_query.iter() {
+        for mut fill in fill_query.iter_mut() {
+            if fill.meter == Meter::Oxygen {
+                fill.fraction = oxygen.fraction();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn bounds() -> Bounds {
+        Bounds {
+            min: Vec2::new(-8.0, -6.0),
+            max: Vec2::new(8.0, 6.0),
+        }
+    }
+
+    #[test]
+    fn zones_split_the_height_into_bands() {
+        let bounds = bounds();
+        // 12 high, so the surface starts at 4.2 and the seabed ends at -3.6
+        assert_eq!(DepthZone::at(&bounds, 6.0), DepthZone::Surface);
+        assert_eq!(DepthZone::at(&bounds, 4.25), DepthZone::Surface);
+        assert_eq!(DepthZone::at(&bounds, 4.15), DepthZone::Mid);
+        assert_eq!(DepthZone::at(&bounds, 0.0), DepthZone::Mid);
+        assert_eq!(DepthZone::at(&bounds, -3.55), DepthZone::Mid);
+        assert_eq!(DepthZone::at(&bounds, -3.65), DepthZone::Seabed);
+        assert_eq!(DepthZone::at(&bounds, -6.0), DepthZone::Seabed);
+
+        // Anything past the edges belongs to the band at that edge
+        assert_eq!(DepthZone::at(&bounds, 10.0), DepthZone::Surface);
+        assert_eq!(DepthZone::at(&bounds, -10.0), DepthZone::Seabed);
+    }
+
+    #[test]
+    fn bands_cover_the_bounds_without_gaps() {
+        let bounds = bounds();
+        let (seabed_bottom, seabed_top) = DepthZone::Seabed.band(&bounds);
+        let (mid_bottom, mid_top) = DepthZone::Mid.band(&bounds);
+        let (surface_bottom, surface_top) = DepthZone::Surface.band(&bounds);
+
+        assert_eq!(seabed_bottom, bounds.min.y);
+        assert_eq!(seabed_top, mid_bottom);
+        assert_eq!(mid_top, surface_bottom);
+        assert_eq!(surface_top, bounds.max.y);
+        assert!((seabed_top - -3.6).abs() < TOLERANCE);
+        assert!((surface_bottom - 4.2).abs() < TOLERANCE);
+
+        for zone in DEPTH_ZONES {
+            let (bottom, top) = zone.band(&bounds);
+            assert_eq!(DepthZone::at(&bounds, (bottom + top) / 2.0), zone);
+        }
+    }
+
+    #[test]
+    fn collectibles_favour_the_richer_bands() {
+        let bounds = bounds();
+        let mut rng = StdRng::seed_from_u64(7);
+        let draws = 10_000;
+        let mut counts = [0; 3];
+
+        for _ in 0..draws {
+            let y = collectible_height(&bounds, bounds.min.y, bounds.max.y, &mut rng);
+            assert!((bounds.min.y..bounds.max.y).contains(&y));
+
+            let zone = DepthZone::at(&bounds, y);
+            counts[DEPTH_ZONES.iter().position(|other| *other == zone).unwrap()] += 1;
+        }
+
+        let weights = DEPTH_ZONES.map(|zone| {
+            let (bottom, top) = zone.band(&bounds);
+            zone.collectible_weight() * (top - bottom)
+        });
+        let total = weights.iter().sum::<f32>();
+
+        for (count, weight) in counts.iter().zip(weights) {
+            let share = *count as f32 / draws as f32;
+            assert!((share - weight / total).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn collectibles_stay_within_the_allowed_heights() {
+        let bounds = bounds();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        // Only part of the mid band and the seabed band are allowed
+        for _ in 0..1_000 {
+            let y = collectible_height(&bounds, -5.0, 0.0, &mut rng);
+            assert!((-5.0..0.0).contains(&y));
+        }
+    }
+
+    #[test]
+    fn collectibles_fall_back_to_any_height_when_every_band_is_clipped() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // Flat bounds put every band at the same height, so none of them are left
+        let flat = Bounds {
+            min: Vec2::new(-8.0, 0.0),
+            max: Vec2::new(8.0, 0.0),
+        };
+
+        for _ in 0..1_000 {
+            let y = collectible_height(&flat, -2.0, 2.0, &mut rng);
+            assert!((-2.0..2.0).contains(&y));
+        }
+    }
+
+    fn breathing_app(y: f32, oxygen: f32) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_state::<GameState>()
+            .insert_resource(FixedTime::new_from_secs(0.1))
+            .insert_resource(bounds())
+            .add_system(breathe);
+
+        let player = app
+            .world
+            .spawn((
+                Player::default(),
+                Transform::from_xyz(0.0, y, 0.0),
+                Oxygen {
+                    current: oxygen,
+                    ..default()
+                },
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    #[test]
+    fn breathing_goes_by_the_step_not_the_frame() {
+        let (mut app, player) = breathing_app(0.0, 50.0);
+
+        app.update();
+
+        let expected = 50.0 + DepthZone::Mid.oxygen_rate() * 0.1;
+        assert!((app.world.get::<Oxygen>(player).unwrap().current - expected).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn running_out_of_oxygen_ends_the_run() {
+        let (mut app, player) = breathing_app(-5.0, 0.5);
+
+        app.update();
+
+        assert_eq!(app.world.get::<Oxygen>(player).unwrap().current, 0.0);
+        assert_eq!(
+            app.world.resource::<NextState<GameState>>().0,
+            Some(GameState::GameOver)
+        );
+    }
+
+    #[test]
+    fn the_surface_refills_up_to_the_max() {
+        let (mut app, player) = breathing_app(5.0, 99.0);
+
+        app.update();
+
+        assert_eq!(app.world.get::<Oxygen>(player).unwrap().current, 100.0);
+        assert_eq!(app.world.resource::<NextState<GameState>>().0, None);
+    }
+}
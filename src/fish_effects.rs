@@ -0,0 +1,342 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    scene::SceneInstance,
+};
+
+use crate::{
+    collectible::Collectible,
+    collision::{HitGuard, PlayerHitEvent},
+    input::Player,
+    material_override::{MaterialOverride, OverriddenMaterials},
+    GameState, SimulationSet,
+};
+
+// Visual feedback on fish and pickups: a white flash when hit, blinking while invulnerable,
+// dissolving away on death and an outline on collectibles. Effects that need a shader swap the
+// scene's materials out while they run and put the originals back afterwards.
+pub struct FishEffectsPlugin;
+
+impl Plugin for FishEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MaterialPlugin::<FishEffectMaterial>::default())
+            .add_system(dissolve_player.in_schedule(OnEnter(GameState::GameOver)))
+            // Not limited to playing so that the player can dissolve on the game over screen
+            .add_systems(
+                (
+                    add_fish_effects,
+                    flash_on_hits,
+                    blink_while_shielded,
+                    tick_fish_effects,
+                    swap_in_effect_materials,
+                    update_effect_materials,
+                    restore_materials,
+                    blink,
+                )
+                    .chain()
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const FLASH_DURATION: f32 = 0.15;
+const HIT_BLINK_DURATION: f32 = 1.0;
+const BLINKS_PER_SECOND: f32 = 8.0;
+const DISSOLVE_DURATION: f32 = 0.8;
+
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct FishEffects {
+    /// Seconds left of the white flash
+    pub flash: f32,
+
+    /// Seconds left of blinking
+    pub blink: f32,
+
+    /// How far through dissolving, from 0 to 1
+    pub dissolve: Option<f32>,
+
+    /// Despawns the entity once it's fully dissolved
+    pub despawn_when_dissolved: bool,
+
+    pub outline: Option<Color>,
+}
+
+impl FishEffects {
+    /// Dissolves away and then despawns
+    pub fn dissolve_and_despawn() -> FishEffects {
+        FishEffects {
+            dissolve: Some(0.0),
+            despawn_when_dissolved: true,
+            ..default()
+        }
+    }
+
+    /// Blinking doesn't need a shader, everything else does
+    fn needs_material(&self) -> bool {
+        self.flash > 0.0 || self.dissolve.is_some() || self.outline.is_some()
+    }
+}
+
+#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
+#[uuid = "8d2f6a3c-19e4-4b7a-a5c0-3e7d1f9b2c64"]
+pub struct FishEffectMaterial {
+    #[uniform(0)]
+    pub base_color: Color,
+
+    #[uniform(0)]
+    pub emissive: Color,
+
+    #[uniform(0)]
+    pub outline_color: Color,
+
+    #[uniform(0)]
+    pub roughness: f32,
+
+    /// How white the fish is, from 0 to 1
+    #[uniform(0)]
+    pub flash: f32,
+
+    /// How much has dissolved, from 0 to 1
+    #[uniform(0)]
+    pub dissolve: f32,
+
+    /// How strong the outline is, from 0 to 1
+    #[uniform(0)]
+    pub outline: f32,
+
+    #[texture(1)]
+    #[sampler(2)]
+    pub base_color_texture: Option<Handle<Image>>,
+}
+
+impl FishEffectMaterial {
+    /// Starts off looking just like the material it replaces
+    fn from_standard(material: &StandardMaterial) -> FishEffectMaterial {
+        FishEffectMaterial {
+            base_color: material.base_color,
+            emissive: material.emissive,
+            outline_color: Color::NONE,
+            roughness: material.perceptual_roughness,
+            flash: 0.0,
+            dissolve: 0.0,
+            outline: 0.0,
+            base_color_texture: material.base_color_texture.clone(),
+        }
+    }
+}
+
+impl Material for FishEffectMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/fish_effect.wgsl".into()
+    }
+}
+
+/// The materials swapped out while effects run, to be put back afterwards
+#[derive(Component, Debug)]
+pub struct SwappedMaterials {
+    originals: Vec<(Entity, Handle<StandardMaterial>)>,
+
+    materials: Vec<Handle<FishEffectMaterial>>,
+}
+
+fn add_fish_effects(
+    mut commands: Commands,
+    player_query: Query<Entity, (Added<Player>, Without<FishEffects>)>,
+    collectible_query: Query<Entity, (Added<Collectible>, Without<FishEffects>)>,
+) {
+    for entity in player_query.iter() {
+        commands.entity(entity).insert(FishEffects::default());
+    }
+
+    for entity in collectible_query.iter() {
+        commands.entity(entity).insert(FishEffects {
+            outline: Some(Color::rgb(1.0, 0.9, 0.5)),
+            ..default()
+        });
+    }
+}
+
+fn flash_on_hits(
+    mut player_hit_events: EventReader<PlayerHitEvent>,
+    mut query: Query<&mut FishEffects>,
+) {
+    for PlayerHitEvent { player, .. } in player_hit_events.iter() {
+        if let Ok(mut effects) = query.get_mut(*player) {
+            effects.flash = FLASH_DURATION;
+            effects.blink = HIT_BLINK_DURATION;
+        }
+    }
+}
+
+fn blink_while_shielded(mut query: Query<(&HitGuard, &mut FishEffects)>) {
+    for (guard, mut effects) in query.iter_mut() {
+        if guard.shields > 0 && effects.blink <= 0.0 {
+            effects.blink = 1.0 / BLINKS_PER_SECOND;
+        }
+    }
+}
+
+fn dissolve_player(mut query: Query<&mut FishEffects, With<Player>>) {
+    for mut effects in query.iter_mut() {
+        effects.dissolve = Some(0.0);
+    }
+}
+
+fn tick_fish_effects(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut FishEffects)>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (entity, mut effects) in query.iter_mut() {
+        if effects.flash > 0.0 {
+            effects.flash = (effects.flash - delta_seconds).max(0.0);
+        }
+
+        if effects.blink > 0.0 {
+            effects.blink = (effects.blink - delta_seconds).max(0.0);
+        }
+
+        if let Some(dissolve) = effects.dissolve {
+            if dissolve >= 1.0 {
+                if effects.despawn_when_dissolved {
+                    commands.entity(entity).despawn_recursive();
+                }
+                continue;
+            }
+
+            effects.dissolve = Some((dissolve + delta_seconds / DISSOLVE_DURATION).min(1.0));
+        }
+    }
+}
+
+/// Swaps every material in the scene for an effect material, once the scene has spawned and any
+/// overrides have been applied to it
+#[allow(clippy::type_complexity)]
+fn swap_in_effect_materials(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &FishEffects,
+            Option<&SceneInstance>,
+            Option<&MaterialOverride>,
+            Option<&OverriddenMaterials>,
+        ),
+        Without<SwappedMaterials>,
+    >,
+    children_query: Query<&Children>,
+    material_query: Query<&Handle<StandardMaterial>>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut effect_materials: ResMut<Assets<FishEffectMaterial>>,
+    scene_spawner: Res<SceneSpawner>,
+) {
+    for (entity, effects, instance, material_override, overridden) in query.iter() {
+        if !effects.needs_material() {
+            continue;
+        }
+
+        let ready = instance.map_or(true, |instance| scene_spawner.instance_is_ready(**instance));
+        if !ready || (material_override.is_some() && overridden.is_none()) {
+            continue;
+        }
+
+        let mut originals = Vec::new();
+        let mut materials = Vec::new();
+
+        for target in std::iter::once(entity).chain(children_query.iter_descendants(entity)) {
+            let Ok(original) = material_query.get(target) else {
+                continue;
+            };
+
+            let material = standard_materials.get(original).map_or_else(
+                || FishEffectMaterial::from_standard(&StandardMaterial::default()),
+                FishEffectMaterial::from_standard,
+            );
+            let handle = effect_materials.add(material);
+
+            commands
+                .entity(target)
+                .remove::<Handle<StandardMaterial>>()
+                .insert(handle.clone());
+            originals.push((target, original.clone()));
+            materials.push(handle);
+        }
+
+        commands.entity(entity).insert(SwappedMaterials {
+            originals,
+            materials,
+        });
+    }
+}
+
+fn update_effect_materials(
+    query: Query<(&FishEffects, &SwappedMaterials)>,
+    mut effect_materials: ResMut<Assets<FishEffectMaterial>>,
+    time: Res<Time>,
+) {
+    // Outlines pulse gently so they catch the eye
+    let pulse = 0.75 + (time.elapsed_seconds() * 4.0).sin() * 0.25;
+
+    for (effects, swapped) in query.iter() {
+        for handle in swapped.materials.iter() {
+            let Some(material) = effect_materials.get_mut(handle) else {
+                continue;
+            };
+
+            material.flash = effects.flash / FLASH_DURATION;
+            material.dissolve = effects.dissolve.unwrap_or(0.0);
+
+            if let Some(outline) = effects.outline {
+                material.outline_color = outline;
+                material.outline = pulse;
+            } else {
+                material.outline = 0.0;
+            }
+        }
+    }
+}
+
+fn restore_materials(
+    mut commands: Commands,
+    query: Query<(Entity, &FishEffects, &SwappedMaterials)>,
+) {
+    for (entity, effects, swapped) in query.iter() {
+        if effects.needs_material() {
+            continue;
+        }
+
+        for (target, original) in swapped.originals.iter() {
+            if let Some(mut target) = commands.get_entity(*target) {
+                target
+                    .remove::<Handle<FishEffectMaterial>>()
+                    .insert(original.clone());
+            }
+        }
+
+        commands.entity(entity).remove::<SwappedMaterials>();
+    }
+}
+
+/// Hides the entity every other beat while it's blinking, and shows it again once it stops
+fn blink(mut query: Query<(&FishEffects, &mut Visibility)>, time: Res<Time>) {
+    for (effects, mut visibility) in query.iter_mut() {
+        if effects.blink <= 0.0 && *visibility != Visibility::Hidden {
+            continue;
+        }
+
+        let visible = effects.blink <= 0.0
+            || (time.elapsed_seconds() * BLINKS_PER_SECOND * 2.0) as u32 % 2 == 0;
+        let target = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
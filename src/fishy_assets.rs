@@ -1,11 +1,13 @@
 #![allow(dead_code)]
 use bevy::asset::AssetServer;
-use bevy::prelude::{AnimationClip, Image};
+use bevy::prelude::{AnimationClip, Color, Font, Image};
 use bevy::{
     prelude::{Handle, Resource},
     scene::Scene,
 };
 use bevy_asset_loader::prelude::*;
+use bevy_kira_audio::AudioSource;
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
 #[derive(Debug, Copy, Clone, EnumIter)]
@@ -128,7 +130,7 @@ impl RockType {
     }
 }
 
-#[derive(Debug, Copy, Clone, EnumIter)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter)]
 pub enum SeaweedType {
     // This seaweed is super janky
     // Seaweed,
@@ -212,7 +214,7 @@ impl ShellType {
     }
 }
 
-#[derive(Debug, Copy, Clone, EnumIter)]
+#[derive(Debug, Copy, Clone, EnumIter, Serialize, Deserialize)]
 pub enum FishType {
     BrownFish,
     ClownFish,
@@ -479,14 +481,147 @@ impl FishType {
     }
 }
 
+impl FishType {
+    /// Roughly how long the fish's model is, nose to tail, in world units at a scale of one
+    pub fn body_length(&self) -> f32 {
+        match self {
+            FishType::BrownFish => 0.7,
+            FishType::ClownFish => 0.5,
+            FishType::Crab => 0.8,
+            FishType::DoryFish => 0.6,
+            FishType::Eel => 1.6,
+            FishType::Hammerhead => 2.5,
+            FishType::Lobster => 1.0,
+            FishType::Octopus => 1.8,
+            FishType::Penguin => 1.0,
+            FishType::Seal => 2.0,
+            FishType::Squid => 1.2,
+            FishType::StarFish => 0.5,
+            FishType::StingRay => 1.8,
+            FishType::TunaFish => 1.5,
+            FishType::Turtle => 1.0,
+            FishType::Whale => 8.0,
+        }
+    }
+
+    /// Air-breathers have to come up to the surface every so often
+    pub fn breathes_air(&self) -> bool {
+        matches!(
+            self,
+            FishType::Turtle | FishType::Seal | FishType::Penguin | FishType::Whale
+        )
+    }
+
+    /// The color the species glows in the dark, if it does
+    pub fn glow(&self) -> Option<Color> {
+        match self {
+            FishType::Squid => Some(Color::rgb(0.2, 0.9, 1.0)),
+            FishType::Octopus => Some(Color::rgb(0.9, 0.3, 1.0)),
+            FishType::Eel => Some(Color::rgb(0.4, 1.0, 0.5)),
+            FishType::StarFish => Some(Color::rgb(1.0, 0.6, 0.3)),
+            _ => None,
+        }
+    }
+}
+
 pub struct FishAnimations {
     pub idle: Handle<AnimationClip>,
 
     pub moving: Option<Handle<AnimationClip>>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct FishIconCollection {
+    #[asset(path = "icons/BrownFish.png")]
+    pub brown_fish: Handle<Image>,
+
+    #[asset(path = "icons/ClownFish.png")]
+    pub clown_fish: Handle<Image>,
+
+    #[asset(path = "icons/Crab.png")]
+    pub crab: Handle<Image>,
+
+    #[asset(path = "icons/DoryFish.png")]
+    pub dory_fish: Handle<Image>,
+
+    #[asset(path = "icons/Eel.png")]
+    pub eel: Handle<Image>,
+
+    #[asset(path = "icons/Hammerhead.png")]
+    pub hammerhead: Handle<Image>,
+
+    #[asset(path = "icons/Lobster.png")]
+    pub lobster: Handle<Image>,
+
+    #[asset(path = "icons/Octopus.png")]
+    pub octopus: Handle<Image>,
+
+    #[asset(path = "icons/Penguin.png")]
+    pub penguin: Handle<Image>,
+
+    #[asset(path = "icons/Seal.png")]
+    pub seal: Handle<Image>,
+
+    #[asset(path = "icons/Squid.png")]
+    pub squid: Handle<Image>,
+
+    #[asset(path = "icons/StarFish.png")]
+    pub starfish: Handle<Image>,
+
+    #[asset(path = "icons/StingRay.png")]
+    pub stingray: Handle<Image>,
+
+    #[asset(path = "icons/TunaFish.png")]
+    pub tuna_fish: Handle<Image>,
+
+    #[asset(path = "icons/Turtle.png")]
+    pub turtle: Handle<Image>,
+
+    #[asset(path = "icons/Whale.png")]
+    pub whale: Handle<Image>,
+}
+
+impl FishType {
+    /// Gets the corresponding ui icon for the given fish type
+    pub fn icon_from(&self, collection: &FishIconCollection) -> Handle<Image> {
+        match self {
+            FishType::BrownFish => collection.brown_fish.clone(),
+            FishType::ClownFish => collection.clown_fish.clone(),
+            FishType::Crab => collection.crab.clone(),
+            FishType::DoryFish => collection.dory_fish.clone(),
+            FishType::Eel => collection.eel.clone(),
+            FishType::Hammerhead => collection.hammerhead.clone(),
+            FishType::Lobster => collection.lobster.clone(),
+            FishType::Octopus => collection.octopus.clone(),
+            FishType::Penguin => collection.penguin.clone(),
+            FishType::Seal => collection.seal.clone(),
+            FishType::Squid => collection.squid.clone(),
+            FishType::StarFish => collection.starfish.clone(),
+            FishType::StingRay => collection.stingray.clone(),
+            FishType::TunaFish => collection.tuna_fish.clone(),
+            FishType::Turtle => collection.turtle.clone(),
+            FishType::Whale => collection.whale.clone(),
+        }
+    }
+}
+
 #[derive(AssetCollection, Resource)]
 pub struct TextureCollection {
     #[asset(path = "textures/background.jpg")]
     pub background: Handle<Image>,
+
+    #[asset(path = "textures/arrow.png")]
+    pub arrow: Handle<Image>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct FontCollection {
+    #[asset(path = "fonts/FiraSans-Bold.ttf")]
+    pub bold: Handle<Font>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct AudioCollection {
+    #[asset(path = "sounds/pickup.ogg")]
+    pub pickup: Handle<AudioSource>,
 }
@@ -0,0 +1,262 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::GameState;
+
+// Runs movement on a fixed timestep so the game plays the same at any frame rate. Steps don't
+// line up with frames, so whatever moves in them is drawn part way between its last two steps
+// to keep it smooth.
+pub struct FixedStepPlugin;
+
+impl Plugin for FixedStepPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FixedTime::new_from_secs(TIMESTEP))
+            .init_resource::<SimulationTime>()
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule.configure_sets(
+                    (
+                        StepSet::Input,
+                        StepSet::Logic,
+                        StepSet::Movement,
+                        StepSet::Constraints,
+                    )
+                        .chain()
+                        .after(snapshot_transforms),
+                );
+            })
+            .add_system(restore_simulated_transforms.in_base_set(CoreSet::First))
+            // Not tied to a state, so anything that stops moving settles instead of jittering
+            .add_system(snapshot_transforms.in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(
+                advance_simulation_time
+                    .after(StepSet::Constraints)
+                    .run_if(in_state(GameState::Playing))
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(reset_simulation_time.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(
+                interpolate_transforms
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Seconds per simulation step
+pub const TIMESTEP: f32 = 1.0 / 60.0;
+
+/// The order of things within a step. Anything that moves what an `Interpolated` entity is
+/// simulated at goes in here rather than in `Update`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StepSet {
+    /// Hands the step the player's input
+    Input,
+    /// What the input sets off, like dashing and boosting
+    Logic,
+    Movement,
+    /// Keeping things on screen, once everything has moved
+    Constraints,
+}
+
+/// Seconds of play simulated so far, counted in whole steps. Anything sampled over time in a
+/// step uses this rather than `Time` so it comes out the same at any frame rate.
+#[derive(Resource, Debug, Default)]
+pub struct SimulationTime {
+    pub elapsed: f32,
+}
+
+/// Where an entity was after the last two steps. Its `Transform` is put back to where it really
+/// is at the start of each frame, so everything else only ever sees the simulated position.
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Interpolated {
+    previous_translation: Vec3,
+
+    previous_rotation: Quat,
+
+    translation: Vec3,
+
+    rotation: Quat,
+}
+
+impl From<Transform> for Interpolated {
+    fn from(transform: Transform) -> Interpolated {
+        Interpolated {
+            previous_translation: transform.translation,
+            previous_rotation: transform.rotation,
+            translation: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+}
+
+impl Interpolated {
+    /// Where the entity really is, as of the last frame that was drawn
+    pub fn simulated_translation(&self) -> Vec3 {
+        self.translation
+    }
+
+    /// Where the entity will be drawn this frame. Only right once this frame's steps have run
+    /// and before the transforms are interpolated, while `transform` is the simulated one.
+    pub fn drawn_translation(&self, transform: &Transform, fixed_time: &FixedTime) -> Vec3 {
+        self.previous_translation
+            .lerp(transform.translation, step_fraction(fixed_time))
+    }
+}
+
+/// How far into the next step the frame is, from 0 to 1
+fn step_fraction(fixed_time: &FixedTime) -> f32 {
+    (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).min(1.0)
+}
+
+fn restore_simulated_transforms(mut query: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.translation;
+        transform.rotation = interpolated.rotation;
+    }
+}
+
+/// Runs before every step, so the previous position is always one step behind
+pub fn snapshot_transforms(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous_translation = transform.translation;
+        interpolated.previous_rotation = transform.rotation;
+    }
+}
+
+fn advance_simulation_time(
+    mut simulation_time: ResMut<SimulationTime>,
+    fixed_time: Res<FixedTime>,
+) {
+    simulation_time.elapsed += fixed_time.period.as_secs_f32();
+}
+
+/// Steps left over from before play started would otherwise all run on the first frame
+fn reset_simulation_time(
+    mut simulation_time: ResMut<SimulationTime>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    simulation_time.elapsed = 0.0;
+    *fixed_time = FixedTime::new(fixed_time.period);
+}
+
+/// Keeps the simulated position and draws the entity between it and the one before, by how far
+/// into the next step the frame is
+fn interpolate_transforms(
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+    fixed_time: Res<FixedTime>,
+) {
+    let t = step_fraction(&fixed_time);
+
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        interpolated.translation = transform.translation;
+        interpolated.rotation = transform.rotation;
+
+        transform.translation = interpolated
+            .previous_translation
+            .lerp(interpolated.translation, t);
+        transform.rotation = interpolated
+            .previous_rotation
+            .slerp(interpolated.rotation, t);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::{
+        fishy_assets::FishType,
+        hazard::{move_hazard, Hazard, HazardTimeScale},
+        input::{apply_step_input, move_towards, Player, PlayerStateEvent, StepInput},
+        Fish,
+    };
+
+    const SECONDS: f64 = 2.0;
+
+    /// Where the player and a hazard are simulated to be after `SECONDS` of frames
+    /// `frame_seconds` long
+    fn simulate(frame_seconds: f64) -> (Vec3, Vec3) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(FixedStepPlugin)
+            .add_state::<GameState>()
+            .init_resource::<HazardTimeScale>()
+            .add_event::<PlayerStateEvent>()
+            .add_systems(
+                (
+                    apply_step_input.in_set(StepSet::Input),
+                    move_towards.in_set(StepSet::Movement),
+                    move_hazard.in_set(StepSet::Movement),
+                )
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+
+        let transform = Transform::default();
+        let player = app
+            .world
+            .spawn((
+                transform,
+                Interpolated::from(transform),
+                Player::default(),
+                StepInput {
+                    direction: Vec2::new(0.6, 0.8),
+                    ..default()
+                },
+            ))
+            .id();
+        let hazard = app
+            .world
+            .spawn((
+                transform,
+                Interpolated::from(transform),
+                Hazard {
+                    velocity: Vec2::new(-3.0, 1.0),
+                },
+                Fish {
+                    fish_type: FishType::Crab,
+                },
+            ))
+            .id();
+
+        // Each frame is given its exact time rather than a duration, which Bevy adds to the
+        // real time, so the test doesn't depend on how fast it runs
+        let start = Instant::now();
+        let frames = (SECONDS / frame_seconds).round() as u32;
+        for frame in 0..=frames {
+            let instant = start + Duration::from_secs_f64(SECONDS * frame as f64 / frames as f64);
+            app.insert_resource(TimeUpdateStrategy::ManualInstant(instant));
+            app.update();
+        }
+
+        let position = |entity| {
+            app.world
+                .get::<Interpolated>(entity)
+                .unwrap()
+                .simulated_translation()
+        };
+
+        (position(player), position(hazard))
+    }
+
+    #[test]
+    fn movement_is_the_same_at_any_frame_rate() {
+        let (expected_player, expected_hazard) = simulate(1.0 / 60.0);
+        assert!(expected_player.length() > 10.0);
+        assert!(expected_hazard.length() > 5.0);
+
+        for frame_seconds in [1.0 / 30.0, 1.0 / 144.0] {
+            let (player, hazard) = simulate(frame_seconds);
+
+            assert!(
+                player.distance(expected_player) < 1e-3,
+                "player at {player} instead of {expected_player} at {frame_seconds}s a frame"
+            );
+            assert!(
+                hazard.distance(expected_hazard) < 1e-3,
+                "hazard at {hazard} instead of {expected_hazard} at {frame_seconds}s a frame"
+            );
+        }
+    }
+}
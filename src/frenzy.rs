@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use strum::IntoEnumIterator;
+
+use crate::{
+    collision::{BodyScale, HitGuard, Hitbox, PlayerDamagedEvent, PlayerHitEvent},
+    fish_effects::FishEffects,
+    fishy_assets::FishType,
+    hazard::{swim_rotation, HazardLifecycleSettings, HazardTelegraphSettings, PendingHazard},
+    in_mode,
+    input::Player,
+    rng::GameRng,
+    stats::Score,
+    telegraph::Telegraphed,
+    Bounds, Fish, GameMode, GameState, SimulationSet,
+};
+
+// The "feeding frenzy" mode where the player eats smaller fish to grow and gets eaten by larger
+// ones.
+pub struct FrenzyPlugin;
+
+impl Plugin for FrenzyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrenzySpawnTimer>().add_systems(
+            (
+                init_player_growth,
+                tick_frenzy_spawn_timer,
+                schedule_frenzy_fish,
+                resolve_frenzy_contacts,
+                apply_growth,
+            )
+                .chain()
+                .distributive_run_if(in_mode(GameMode::FeedingFrenzy))
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        );
+    }
+}
+
+/// Fish lengths are bucketed into tiers that double in size, starting from this length
+const TIER_BASE_LENGTH: f32 = 0.5;
+
+/// Hitboxes are a bit smaller than the fish's length so that brushing a fin doesn't count
+const HITBOX_PER_LENGTH: f32 = 0.4;
+
+/// How much of the prey's size goes into growing the player
+const GROWTH_EFFICIENCY: f32 = 0.5;
+
+const POINTS_PER_TIER: u32 = 10;
+
+/// The size tier of a fish, derived from its species and how much it's been scaled
+pub fn size_tier(fish_type: FishType, scale: f32) -> i32 {
+    let length = fish_type.body_length() * scale;
+
+    (length / TIER_BASE_LENGTH).log2().floor() as i32
+}
+
+/// The length a fish needs to be to sit in the middle of the given tier
+pub fn tier_length(tier: i32) -> f32 {
+    TIER_BASE_LENGTH * 2.0f32.powf(tier as f32 + 0.5)
+}
+
+/// The length the player will be after eating a fish of `prey_length`. Growth is by area so
+/// eating lots of tiny fish is slow going.
+pub fn grown_length(length: f32, prey_length: f32) -> f32 {
+    (length.powi(2) + prey_length.powi(2) * GROWTH_EFFICIENCY).sqrt()
+}
+
+/// How big the player is trying to grow to, as a `BodyScale` base
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Growth {
+    pub target_scale: f32,
+}
+
+#[derive(Resource)]
+pub struct FrenzySpawnTimer {
+    pub timer: Timer,
+}
+
+impl Default for FrenzySpawnTimer {
+    fn default() -> FrenzySpawnTimer {
+        FrenzySpawnTimer {
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        }
+    }
+}
+
+fn init_player_growth(
+    mut commands: Commands,
+    query: Query<(Entity, &BodyScale), (With<Player>, Without<Growth>)>,
+) {
+    for (entity, body_scale) in query.iter() {
+        commands.entity(entity).insert(Growth {
+            target_scale: body_scale.base,
+        });
+    }
+}
+
+fn tick_frenzy_spawn_timer(mut frenzy_spawn_timer: ResMut<FrenzySpawnTimer>, time: Res<Time>) {
+    frenzy_spawn_timer.timer.tick(time.delta());
+}
+
+/// Spawns fish around the player's size. Most are edible, with the odd bigger one to avoid.
+fn schedule_frenzy_fish(
+    mut commands: Commands,
+    player_query: Query<(&Fish, &Transform), With<Player>>,
+    bounds: Res<Bounds>,
+    frenzy_spawn_timer: Res<FrenzySpawnTimer>,
+    telegraph_settings: Res<HazardTelegraphSettings>,
+    lifecycle_settings: Res<HazardLifecycleSettings>,
+    game_rng: Res<GameRng>,
+) {
+    if !frenzy_spawn_timer.timer.just_finished() {
+        return;
+    }
+
+    let Ok((player_fish, player_transform)) = player_query.get_single() else {
+        return;
+    };
+
+    const TIER_OFFSETS: [(i32, f32); 5] = [(-2, 3.0), (-1, 4.0), (0, 2.0), (1, 2.0), (2, 1.0)];
+    const EDGE_PADDING: f32 = 1.0;
+
+    // There's no room for it when the visible area is too short to keep it clear of the edges
+    let (min_y, max_y) = (bounds.min.y + EDGE_PADDING, bounds.max.y - EDGE_PADDING);
+    if min_y >= max_y {
+        return;
+    }
+
+    let mut rng = game_rng.stream("frenzy");
+    let player_tier = size_tier(player_fish.fish_type, player_transform.scale.x);
+    let (offset, _) = TIER_OFFSETS
+        .choose_weighted(&mut rng, |(_, weight)| *weight)
+        .unwrap();
+
+    // Whales are saved for bosses and starfish don't swim
+    let species = FishType::iter()
+        .filter(|fish_type| !matches!(fish_type, FishType::Whale | FishType::StarFish))
+        .collect::<Vec<_>>();
+    let fish_type = *species.choose(&mut rng).unwrap();
+    let scale = (tier_length(player_tier + offset) / fish_type.body_length()).clamp(0.3, 4.0);
+
+    let spawn_left = rng.gen_bool(0.5);
+    let margin = lifecycle_settings.spawn_margin + fish_type.body_length() * scale / 2.0;
+    let (x, direction) = if spawn_left {
+        (bounds.min.x - margin, 1.0)
+    } else {
+        (bounds.max.x + margin, -1.0)
+    };
+    let y = rng.gen_range(min_y..max_y);
+    let transform = Transform::from_xyz(x, y, 0.0)
+        .with_rotation(swim_rotation(fish_type, spawn_left))
+        .with_scale(Vec3::splat(scale));
+    // Bigger fish cruise a bit slower
+    let speed = rng.gen_range(1.0..2.5) / scale.sqrt();
+
+    let mut pending = commands.spawn(PendingHazard::new(
+        fish_type,
+        transform,
+        Vec2::new(speed * direction, 0.0),
+        Hitbox::new(fish_type.body_length() * scale * HITBOX_PER_LENGTH),
+        telegraph_settings.lead_time,
+        margin,
+        &bounds,
+    ));
+
+    // Only fish big enough to eat the player are worth a warning
+    if size_tier(fish_type, scale) > player_tier {
+        pending.insert(Telegraphed);
+    }
+}
+
+fn resolve_frenzy_contacts(
+    mut commands: Commands,
+    mut player_hit_events: EventReader<PlayerHitEvent>,
+    mut player_query: Query<(&Fish, &Transform, &mut Growth, Option<&mut HitGuard>), With<Player>>,
+    fish_query: Query<(&Fish, &Transform), Without<Player>>,
+    mut score: ResMut<Score>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for PlayerHitEvent { player, hazard } in player_hit_events.iter() {
+        let Ok((player_fish, player_transform, mut growth, guard)) = player_query.get_mut(*player)
+        else {
+            continue;
+        };
+        let Ok((fish, transform)) = fish_query.get(*hazard) else {
+            continue;
+        };
+
+        let player_tier = size_tier(player_fish.fish_type, player_transform.scale.x);
+        let tier = size_tier(fish.fish_type, transform.scale.x);
+
+        if tier < player_tier {
+            let length = player_fish.fish_type.body_length() * growth.target_scale;
+            let prey_length = fish.fish_type.body_length() * transform.scale.x;
+
+            growth.target_scale =
+                grown_length(length, prey_length) / player_fish.fish_type.body_length();
+            score.points += POINTS_PER_TIER * (tier.max(0) as u32 + 1);
+            // Stops it being eaten twice while it dissolves away
+            commands
+                .entity(*hazard)
+                .remove::<Hitbox>()
+                .insert(FishEffects::dissolve_and_despawn());
+        } else if tier > player_tier {
+            if guard.map_or(false, |mut guard| guard.absorb()) {
+                continue;
+            }
+
+            player_damaged_events.send(PlayerDamagedEvent { player: *player });
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+/// Eases the player's scale towards its target. The hitbox follows through `BodyScale`.
+fn apply_growth(mut query: Query<(&mut BodyScale, &Growth), With<Player>>, time: Res<Time>) {
+    const GROWTH_SPEED: f32 = 2.0;
+
+    for (mut body_scale, growth) in query.iter_mut() {
+        if (growth.target_scale - body_scale.base).abs() < f32::EPSILON {
+            continue;
+        }
+
+        let t = (GROWTH_SPEED * time.delta_seconds()).min(1.0);
+        body_scale.base += (growth.target_scale - body_scale.base) * t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    #[test]
+    fn tiers_double_in_length() {
+        // A clownfish is exactly the base length
+        assert_eq!(size_tier(FishType::ClownFish, 1.0), 0);
+        assert_eq!(size_tier(FishType::ClownFish, 1.99), 0);
+        assert_eq!(size_tier(FishType::ClownFish, 2.0), 1);
+        assert_eq!(size_tier(FishType::ClownFish, 3.99), 1);
+        assert_eq!(size_tier(FishType::ClownFish, 4.0), 2);
+        assert_eq!(size_tier(FishType::ClownFish, 0.99), -1);
+        assert_eq!(size_tier(FishType::ClownFish, 0.5), -1);
+        assert_eq!(size_tier(FishType::ClownFish, 0.49), -2);
+    }
+
+    #[test]
+    fn tiers_account_for_the_species() {
+        // The same scale puts a whale far above a clownfish
+        assert_eq!(size_tier(FishType::Whale, 1.0), 4);
+        assert_eq!(size_tier(FishType::Turtle, 1.0), 1);
+        assert_eq!(size_tier(FishType::Turtle, 0.5), 0);
+    }
+
+    #[test]
+    fn tier_lengths_sit_inside_their_tier() {
+        for tier in -3..=5 {
+            let length = tier_length(tier);
+
+            assert_eq!(size_tier(FishType::ClownFish, length / 0.5), tier);
+            assert!((tier_length(tier + 1) / length - 2.0).abs() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn growth_adds_area() {
+        let length = 3.0;
+        let prey_length = 4.0;
+        let grown = grown_length(length, prey_length);
+
+        assert!(
+            (grown.powi(2) - (length.powi(2) + prey_length.powi(2) * GROWTH_EFFICIENCY)).abs()
+                < TOLERANCE
+        );
+        assert!(grown > length);
+    }
+
+    #[test]
+    fn two_small_fish_make_one_of_the_same_total_area() {
+        let length = 2.0;
+        let twice = grown_length(grown_length(length, 1.0), 1.0);
+        let once = grown_length(length, 2.0f32.sqrt());
+
+        assert!((twice - once).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn eating_nothing_doesnt_grow() {
+        assert_eq!(grown_length(2.0, 0.0), 2.0);
+    }
+}
@@ -0,0 +1,337 @@
+use bevy::{
+    core_pipeline::{
+        bloom::BloomSettings, clear_color::ClearColorConfig, tonemapping::Tonemapping,
+    },
+    pbr::{DirectionalLightShadowMap, PointLightShadowMap},
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+    },
+    window::{PrimaryWindow, WindowRef},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{camera::CameraController, seabed::SeabedSettings, GameState, SimulationSet};
+
+// How good the game looks against how fast it runs. Everything here can be changed while
+// playing, from the settings menu.
+pub struct GraphicsPlugin;
+
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GraphicsSettings>()
+            .add_system(setup_scaled_view.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (
+                    apply_graphics_settings.run_if(resource_changed::<GraphicsSettings>()),
+                    update_scaled_view,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const SHADOW_RESOLUTIONS: [usize; 4] = [512, 1024, 2048, 4096];
+const PROP_DENSITIES: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const RESOLUTION_SCALES: [f32; 3] = [0.5, 0.75, 1.0];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphicsPreset {
+    Low,
+    Medium,
+    High,
+    /// Anything that has been changed by hand
+    Custom,
+}
+
+impl GraphicsPreset {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GraphicsPreset::Low => "Low",
+            GraphicsPreset::Medium => "Medium",
+            GraphicsPreset::High => "High",
+            GraphicsPreset::Custom => "Custom",
+        }
+    }
+
+    /// The next preset to switch to. Custom is skipped since it can't be switched to.
+    pub fn next(&self) -> GraphicsPreset {
+        match self {
+            GraphicsPreset::Low => GraphicsPreset::Medium,
+            GraphicsPreset::Medium => GraphicsPreset::High,
+            GraphicsPreset::High | GraphicsPreset::Custom => GraphicsPreset::Low,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsSettings {
+    pub preset: GraphicsPreset,
+
+    pub shadows: bool,
+
+    /// Width and height of the shadow maps, in texels
+    pub shadow_resolution: usize,
+
+    pub bloom: bool,
+
+    pub fog: bool,
+
+    pub msaa: bool,
+
+    /// How many seabed props there are compared to the hand tuned amount
+    pub prop_density: f32,
+
+    /// The 3D scene is drawn at this fraction of the window's resolution and scaled up to fit
+    pub resolution_scale: f32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> GraphicsSettings {
+        // The web build can't afford the MSAA and bigger shadow maps of High
+        GraphicsSettings::from_preset(if cfg!(target_arch = "wasm32") {
+            GraphicsPreset::Medium
+        } else {
+            GraphicsPreset::High
+        })
+    }
+}
+
+impl GraphicsSettings {
+    pub fn from_preset(preset: GraphicsPreset) -> GraphicsSettings {
+        match preset {
+            GraphicsPreset::Low => GraphicsSettings {
+                preset,
+                shadows: false,
+                shadow_resolution: 512,
+                bloom: false,
+                fog: false,
+                msaa: false,
+                prop_density: 0.5,
+                resolution_scale: 0.75,
+            },
+            GraphicsPreset::Medium => GraphicsSettings {
+                preset,
+                shadows: true,
+                shadow_resolution: 1024,
+                bloom: true,
+                fog: true,
+                msaa: false,
+                prop_density: 1.0,
+                resolution_scale: 1.0,
+            },
+            // Custom starts off from the best looking settings and gets tweaked from there
+            GraphicsPreset::High | GraphicsPreset::Custom => GraphicsSettings {
+                preset,
+                shadows: true,
+                shadow_resolution: 2048,
+                bloom: true,
+                fog: true,
+                msaa: true,
+                prop_density: 1.0,
+                resolution_scale: 1.0,
+            },
+        }
+    }
+
+    pub fn cycle_shadow_resolution(&mut self) {
+        self.shadow_resolution = next_option(&SHADOW_RESOLUTIONS, self.shadow_resolution);
+    }
+
+    pub fn cycle_prop_density(&mut self) {
+        self.prop_density = next_option(&PROP_DENSITIES, self.prop_density);
+    }
+
+    pub fn cycle_resolution_scale(&mut self) {
+        self.resolution_scale = next_option(&RESOLUTION_SCALES, self.resolution_scale);
+    }
+}
+
+/// The option after `current`, wrapping around. Goes back to the first if `current` isn't one of
+/// the options.
+fn next_option<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    let next = options
+        .iter()
+        .position(|option| *option == current)
+        .map_or(0, |index| (index + 1) % options.len());
+
+    options[next]
+}
+
+/// What the 3D camera draws into when the resolution is scaled down
+#[derive(Resource, Debug)]
+struct ScaledView {
+    image: Handle<Image>,
+}
+
+/// Shows the scaled down scene stretched over the whole window, underneath the rest of the UI
+#[derive(Component, Debug)]
+struct ScaledViewImage;
+
+fn setup_scaled_view(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("scaled view"),
+            size: Extent3d::default(),
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(Extent3d::default());
+    let image = images.add(image);
+
+    // The UI gets a camera of its own so that it stays sharp when the scene is scaled down
+    commands.spawn(Camera2dBundle {
+        camera: Camera {
+            order: 1,
+            ..default()
+        },
+        camera_2d: Camera2d {
+            clear_color: ClearColorConfig::None,
+        },
+        tonemapping: Tonemapping::None,
+        ..default()
+    });
+
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            image: image.clone().into(),
+            z_index: ZIndex::Global(-1),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ScaledViewImage,
+    ));
+
+    commands.insert_resource(ScaledView { image });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_graphics_settings(
+    mut commands: Commands,
+    settings: Res<GraphicsSettings>,
+    camera_query: Query<(Entity, Option<&FogSettings>), With<CameraController>>,
+    mut directional_light_query: Query<&mut DirectionalLight>,
+    mut point_light_query: Query<&mut PointLight>,
+    mut directional_shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut point_shadow_map: ResMut<PointLightShadowMap>,
+    mut msaa: ResMut<Msaa>,
+    mut seabed_settings: ResMut<SeabedSettings>,
+) {
+    for mut light in directional_light_query.iter_mut() {
+        light.shadows_enabled = settings.shadows;
+    }
+
+    for mut light in point_light_query.iter_mut() {
+        light.shadows_enabled = settings.shadows;
+    }
+
+    directional_shadow_map.size = settings.shadow_resolution;
+    point_shadow_map.size = settings.shadow_resolution;
+
+    for (entity, fog) in camera_query.iter() {
+        let mut camera = commands.entity(entity);
+
+        if settings.bloom {
+            camera.insert(BloomSettings::default());
+        } else {
+            camera.remove::<BloomSettings>();
+        }
+
+        // The day/night cycle sets the fog's color and thickness, so there's no need to keep
+        // the old settings around
+        if !settings.fog {
+            camera.remove::<FogSettings>();
+        } else if fog.is_none() {
+            camera.insert(FogSettings::default());
+        }
+    }
+
+    *msaa = if settings.msaa {
+        Msaa::Sample4
+    } else {
+        Msaa::Off
+    };
+
+    // Respawning the props is slow, so only when the density actually changes
+    if seabed_settings.density != settings.prop_density {
+        seabed_settings.density = settings.prop_density;
+    }
+}
+
+/// Points the camera at the window or at the scaled down image, and keeps the image the right
+/// size as the window is resized
+fn update_scaled_view(
+    settings: Res<GraphicsSettings>,
+    scaled_view: Res<ScaledView>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Camera, With<CameraController>>,
+    mut image_query: Query<&mut Visibility, With<ScaledViewImage>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let scaled = settings.resolution_scale < 1.0;
+
+    if scaled {
+        let size = Extent3d {
+            width: ((window.physical_width() as f32 * settings.resolution_scale) as u32).max(1),
+            height: ((window.physical_height() as f32 * settings.resolution_scale) as u32).max(1),
+            ..default()
+        };
+
+        // Only touch the image when it needs resizing, since that sends it to the GPU again
+        let needs_resize = images
+            .get(&scaled_view.image)
+            .map_or(false, |image| image.texture_descriptor.size != size);
+        if needs_resize {
+            if let Some(image) = images.get_mut(&scaled_view.image) {
+                image.resize(size);
+            }
+        }
+    }
+
+    for mut camera in camera_query.iter_mut() {
+        if matches!(camera.target, RenderTarget::Image(_)) == scaled {
+            continue;
+        }
+
+        camera.target = if scaled {
+            RenderTarget::Image(scaled_view.image.clone())
+        } else {
+            RenderTarget::Window(WindowRef::Primary)
+        };
+    }
+
+    let visibility = if scaled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for mut image_visibility in image_query.iter_mut() {
+        if *image_visibility != visibility {
+            *image_visibility = visibility;
+        }
+    }
+}
@@ -6,40 +6,113 @@ use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::{
-    fishy_assets::{FishAnimationCollection, FishAnimations, FishCollection, FishType},
-    Bounds, Fish, FishBundle, GameState, InitialAnimation, SimulationSet,
+    collision::{Health, HitGuard, Hitbox, PlayerDamagedEvent, PlayerHitEvent},
+    current::{CurrentField, Drift},
+    daynight::TimeOfDay,
+    fishy_assets::{FishAnimationCollection, FishCollection, FishType, FontCollection},
+    fixed_step::{Interpolated, SimulationTime, StepSet},
+    hud::{spawn_meter, HudRoot, Meter, MeterFill},
+    in_mode,
+    input::Player,
+    rng::GameRng,
+    stats::Score,
+    telegraph::Telegraphed,
+    Bounds, Fish, FishBundle, GameMode, GameState, InitialAnimation, SimulationSet,
 };
 
 pub struct HazardPlugin;
 
 impl Plugin for HazardPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<HazardSpawnTimer>().add_systems(
-            (
-                tick_hazard_spawn_timer,
-                spawn_hazard,
-                despawn_hazard,
-                move_hazard,
+        app.init_resource::<HazardSpawnTimer>()
+            .init_resource::<HazardTelegraphSettings>()
+            .init_resource::<HazardLifecycleSettings>()
+            .init_resource::<HazardTimeScale>()
+            .add_event::<HazardExited>()
+            .add_event::<PlayerHitEvent>()
+            .add_event::<PlayerDamagedEvent>()
+            .add_systems(
+                (
+                    tick_hazard_spawn_timer,
+                    schedule_hazard.run_if(in_mode(GameMode::Classic)),
+                    spawn_pending_hazards,
+                    tick_hazard_status,
+                    track_hazard_lifecycle,
+                    resolve_classic_hits.run_if(in_mode(GameMode::Classic)),
+                    despawn_exited_hazards,
+                    score_dodged_hazards,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
             )
-                .distributive_run_if(in_state(GameState::Playing))
-                .in_set(SimulationSet::Logic),
-        );
+            .add_systems(
+                (spawn_health_meter, update_health_meter)
+                    .chain()
+                    .distributive_run_if(in_mode(GameMode::Classic))
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            )
+            .add_system(
+                move_hazard
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(StepSet::Movement)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
     }
 }
 
 #[derive(Component, Debug, Copy, Clone, PartialEq)]
 pub struct Hazard {
-    speed: f32,
+    pub velocity: Vec2,
 }
 
 impl Default for Hazard {
     fn default() -> Hazard {
-        Hazard { speed: 0.0 }
+        Hazard {
+            velocity: Vec2::ZERO,
+        }
     }
 }
 
+/// Tracks where a hazard is in its life on screen
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq)]
+pub struct HazardLifecycle {
+    pub entered_screen: bool,
+
+    pub touched_player: bool,
+}
+
+impl HazardLifecycle {
+    /// A hazard only counts as dodged if the player actually saw it and didn't get hit
+    pub fn dodged(&self) -> bool {
+        self.entered_screen && !self.touched_player
+    }
+}
+
+/// A blinded hazard can't find the player and swims straight through them
+#[derive(Component)]
+pub struct Blinded {
+    pub timer: Timer,
+}
+
+/// A stunned hazard stops dead in the water and can't hurt the player
+#[derive(Component)]
+pub struct Stunned {
+    pub timer: Timer,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HazardExited {
+    pub entity: Entity,
+
+    pub fish_type: FishType,
+
+    pub dodged: bool,
+}
+
 #[derive(Debug, Copy, Clone, EnumIter)]
-enum HazardType {
+pub enum HazardType {
     Crab,
     Squid,
     Octopus,
@@ -67,10 +140,27 @@ impl HazardType {
     //         .unwrap()
     // }
 
-    pub fn animations_from(&self, collection: &FishAnimationCollection) -> FishAnimations {
-        self.into_fish_type()
-            .and_then(|fish_type| Some(fish_type.animations_from(collection)))
-            .unwrap()
+    /// How often the hazard turns up compared to the others. Night brings out the hunters that
+    /// like the dark.
+    pub fn spawn_weight(&self, night: bool) -> f32 {
+        match (self, night) {
+            (_, false) => 1.0,
+            (HazardType::Crab, true) => 0.5,
+            (HazardType::Hammerhead, true) => 0.5,
+            (HazardType::Octopus, true) => 1.5,
+            (HazardType::Eel, true) => 2.0,
+            (HazardType::Squid, true) => 3.0,
+        }
+    }
+
+    pub fn hitbox(&self) -> Hitbox {
+        match self {
+            HazardType::Crab => Hitbox::new(0.6),
+            HazardType::Eel => Hitbox::new(0.7),
+            HazardType::Hammerhead => Hitbox::new(1.2),
+            HazardType::Octopus => Hitbox::new(1.0),
+            HazardType::Squid => Hitbox::new(0.8),
+        }
     }
 }
 
@@ -87,125 +177,686 @@ impl Default for HazardSpawnTimer {
     }
 }
 
+/// Points for each hazard that makes it across the screen without touching the player
+pub const DODGE_POINTS: u32 = 5;
+
+/// How far ahead of a hazard entering the screen it gets announced
+#[derive(Resource)]
+pub struct HazardTelegraphSettings {
+    pub lead_time: f32,
+}
+
+impl Default for HazardTelegraphSettings {
+    fn default() -> HazardTelegraphSettings {
+        HazardTelegraphSettings { lead_time: 1.5 }
+    }
+}
+
+/// How far outside of the `Bounds` hazards are spawned and culled, so that they swim in and out
+/// of view instead of popping.
+#[derive(Resource)]
+pub struct HazardLifecycleSettings {
+    pub spawn_margin: f32,
+}
+
+impl Default for HazardLifecycleSettings {
+    fn default() -> HazardLifecycleSettings {
+        HazardLifecycleSettings { spawn_margin: 2.0 }
+    }
+}
+
+/// Scales how fast hazards move. Slow-downs multiply into it and divide back out when they end.
+#[derive(Resource)]
+pub struct HazardTimeScale {
+    pub scale: f32,
+}
+
+impl Default for HazardTimeScale {
+    fn default() -> HazardTimeScale {
+        HazardTimeScale { scale: 1.0 }
+    }
+}
+
+/// A hazard that has been scheduled but hasn't entered the screen yet. Its path is decided up
+/// front so that warnings can be shown before it arrives. The camera can move in the meantime,
+/// so where it enters is kept relative to the screen and only placed in the world when it spawns.
+#[derive(Component, Debug, Clone)]
+pub struct PendingHazard {
+    pub fish_type: FishType,
+
+    /// The rotation and scale to spawn with. The translation is filled in by `spawn_transform`.
+    pub transform: Transform,
+
+    /// How far up the screen edge the hazard enters, from 0 at the bottom to 1 at the top
+    pub height: f32,
+
+    /// Only horizontal paths are supported, the hazard enters from the edge it swims away from
+    pub velocity: Vec2,
+
+    pub hitbox: Hitbox,
+
+    /// Counts down to the hazard being spawned just outside of the screen
+    pub countdown: Timer,
+
+    /// How far outside of the screen the hazard is spawned
+    pub margin: f32,
+
+    /// How long it takes to swim from the spawn point to the edge of the screen
+    pub travel_time: f32,
+}
+
+impl PendingHazard {
+    /// `transform` is where the hazard would spawn in the current `bounds`, `margin` outside of
+    /// the screen
+    pub fn new(
+        fish_type: FishType,
+        transform: Transform,
+        velocity: Vec2,
+        hitbox: Hitbox,
+        lead_time: f32,
+        margin: f32,
+        bounds: &Bounds,
+    ) -> PendingHazard {
+        let travel_time = margin / velocity.length();
+        // The lead time is measured to the hazard entering the screen, not to it being spawned
+        let countdown = (lead_time - travel_time).max(0.0);
+        let screen_height = bounds.max.y - bounds.min.y;
+        let height = if screen_height > 0.0 {
+            (transform.translation.y - bounds.min.y) / screen_height
+        } else {
+            0.5
+        };
+
+        PendingHazard {
+            fish_type,
+            transform,
+            height,
+            velocity,
+            hitbox,
+            countdown: Timer::from_seconds(countdown, TimerMode::Once),
+            margin,
+            travel_time,
+        }
+    }
+
+    /// The point `margin` outside of the edge the hazard enters from
+    fn outside_edge(&self, bounds: &Bounds, margin: f32) -> Vec2 {
+        let x = if self.velocity.x >= 0.0 {
+            bounds.min.x - margin
+        } else {
+            bounds.max.x + margin
+        };
+
+        Vec2::new(
+            x,
+            bounds.min.y + (bounds.max.y - bounds.min.y) * self.height,
+        )
+    }
+
+    /// Where the hazard will be when it enters the screen
+    pub fn entry_point(&self, bounds: &Bounds) -> Vec2 {
+        self.outside_edge(bounds, 0.0)
+    }
+
+    /// Where the hazard spawns, just outside of the screen as it is now
+    pub fn spawn_transform(&self, bounds: &Bounds) -> Transform {
+        let position = self.outside_edge(bounds, self.margin);
+
+        Transform {
+            translation: position.extend(self.transform.translation.z),
+            ..self.transform
+        }
+    }
+
+    /// Seconds until the hazard enters the screen
+    pub fn eta(&self) -> f32 {
+        self.countdown.remaining_secs() + self.travel_time
+    }
+}
+
+/// Rotates a fish so that it faces the way it's swimming along the x axis
+pub fn swim_rotation(fish_type: FishType, moving_right: bool) -> Quat {
+    let angle = if moving_right { PI / 2.0 } else { -PI / 2.0 };
+
+    match fish_type {
+        // Crabs scuttle sideways
+        FishType::Crab => Quat::IDENTITY,
+        // The hammerhead model faces the other way
+        FishType::Hammerhead => Quat::from_rotation_y(-angle),
+        _ => Quat::from_rotation_y(angle),
+    }
+}
+
 pub fn tick_hazard_spawn_timer(mut hazard_spawn_timer: ResMut<HazardSpawnTimer>, time: Res<Time>) {
     hazard_spawn_timer.timer.tick(time.delta());
 }
 
-pub fn spawn_hazard(
+pub fn schedule_hazard(
     mut commands: Commands,
     bounds: Res<Bounds>,
-    fish_collection: Res<FishCollection>,
-    animation_collection: Res<FishAnimationCollection>,
     hazard_spawn_timer: Res<HazardSpawnTimer>,
+    telegraph_settings: Res<HazardTelegraphSettings>,
+    lifecycle_settings: Res<HazardLifecycleSettings>,
+    time_of_day: Res<TimeOfDay>,
+    game_rng: Res<GameRng>,
 ) {
     if !hazard_spawn_timer.timer.just_finished() {
         return;
     }
 
-    let mut rng = rand::thread_rng();
+    let mut rng = game_rng.stream("hazards");
     let hazard_types = HazardType::iter().collect::<Vec<_>>();
-    let hazard_type = hazard_types.choose(&mut rng).unwrap();
+    let night = time_of_day.is_night();
+    let hazard_type = hazard_types
+        .choose_weighted(&mut rng, |hazard_type| hazard_type.spawn_weight(night))
+        .unwrap();
     // let hazard_type = HazardType::Eel;
     let spawn_left = rng.gen_bool(0.5);
     let mut speed = rng.gen_range(1.0..3.0);
+    let margin = lifecycle_settings.spawn_margin;
     let x = if spawn_left {
         speed *= 1.0;
-        bounds.min.x
+        bounds.min.x - margin
     } else {
         speed *= -1.0;
-        bounds.max.x
+        bounds.max.x + margin
     };
-    let animations = hazard_type.animations_from(&animation_collection);
-    let animation = animations.moving.unwrap_or(animations.idle);
-    // TODO: This will not always be true once you add different hazards!
-    let fish_type = hazard_type.into_fish_type().unwrap();
-
-    let (transform, speed_multiplier) = match hazard_type {
+    let (y, speed_multiplier) = match hazard_type {
         HazardType::Crab => {
             let bottom = (bounds.max.y - bounds.min.y) / 5.0;
-            let y = rng.gen_range(bounds.min.y..bounds.min.y + bottom);
-            let transform = Transform::from_xyz(x, y, 0.0);
 
-            (transform, 1.0)
+            (rng.gen_range(bounds.min.y..bounds.min.y + bottom), 1.0)
         }
         HazardType::Eel => {
             let bottom = (bounds.max.y - bounds.min.y) / 3.0;
-            let y = rng.gen_range(bounds.min.y..bounds.min.y + bottom);
-            let mut transform = Transform::from_xyz(x, y, 0.0);
 
-            if spawn_left {
-                transform.rotate_y(PI / 2.0);
-            } else {
-                transform.rotate_y(-PI / 2.0);
-            }
-
-            (transform, 1.25)
+            (rng.gen_range(bounds.min.y..bounds.min.y + bottom), 1.25)
         }
         HazardType::Hammerhead => {
             let top = (bounds.max.y - bounds.min.y) / 2.0;
-            let y = rng.gen_range(bounds.min.y + top..bounds.max.y);
-            let mut transform = Transform::from_xyz(x, y, 0.0);
-
-            if spawn_left {
-                transform.rotate_y(-PI / 2.0);
-            } else {
-                transform.rotate_y(PI / 2.0);
-            }
 
-            (transform, 2.0)
+            (rng.gen_range(bounds.min.y + top..bounds.max.y), 2.0)
         }
         _ => {
             let top = (bounds.max.y - bounds.min.y) / 2.0;
-            let y = rng.gen_range(bounds.min.y + top..bounds.max.y);
-            let mut transform = Transform::from_xyz(x, y, 0.0);
-            if spawn_left {
-                transform.rotate_y(PI / 2.0);
-            } else {
-                transform.rotate_y(-PI / 2.0);
-            }
 
-            (transform, 1.5)
+            (rng.gen_range(bounds.min.y + top..bounds.max.y), 1.5)
         }
     };
+    // TODO: This will not always be true once you add different hazards!
+    let fish_type = hazard_type.into_fish_type().unwrap();
+    let transform =
+        Transform::from_xyz(x, y, 0.0).with_rotation(swim_rotation(fish_type, spawn_left));
 
     commands.spawn((
-        InitialAnimation {
-            animation,
-            repeat: true,
-        },
-        FishBundle {
-            fish: Fish { fish_type },
-            scene: SceneBundle {
-                scene: fish_type.model_from(&fish_collection),
-                transform,
-                ..default()
-            },
-        },
-        Hazard {
-            speed: speed * speed_multiplier,
-        },
+        PendingHazard::new(
+            fish_type,
+            transform,
+            Vec2::new(speed * speed_multiplier, 0.0),
+            hazard_type.hitbox(),
+            telegraph_settings.lead_time,
+            margin,
+            &bounds,
+        ),
+        Telegraphed,
     ));
 }
 
-// Just moves the fish horizontally across the screen
-pub fn move_hazard(mut query: Query<(&mut Transform, &Hazard), With<Fish>>, time: Res<Time>) {
+pub fn spawn_pending_hazards(
+    mut commands: Commands,
+    mut pending_query: Query<(Entity, &mut PendingHazard)>,
+    fish_collection: Res<FishCollection>,
+    animation_collection: Res<FishAnimationCollection>,
+    bounds: Res<Bounds>,
+    time: Res<Time>,
+) {
+    for (entity, mut pending) in pending_query.iter_mut() {
+        if !pending.countdown.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let fish_type = pending.fish_type;
+        let transform = pending.spawn_transform(&bounds);
+        let animations = fish_type.animations_from(&animation_collection);
+        let animation = animations.moving.unwrap_or(animations.idle);
+
+        commands.entity(entity).despawn();
+        commands.spawn((
+            InitialAnimation {
+                animation,
+                repeat: true,
+            },
+            FishBundle {
+                fish: Fish { fish_type },
+                scene: SceneBundle {
+                    scene: fish_type.model_from(&fish_collection),
+                    transform,
+                    ..default()
+                },
+            },
+            Hazard {
+                velocity: pending.velocity,
+            },
+            Interpolated::from(transform),
+            HazardLifecycle::default(),
+            pending.hitbox,
+            // Hazards are strong swimmers and only get nudged by the currents
+            Drift { response: 0.5 },
+        ));
+    }
+}
+
+pub fn tick_hazard_status(
+    mut commands: Commands,
+    mut blinded_query: Query<(Entity, &mut Blinded)>,
+    mut stunned_query: Query<(Entity, &mut Stunned)>,
+    time: Res<Time>,
+) {
+    for (entity, mut blinded) in blinded_query.iter_mut() {
+        if blinded.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Blinded>();
+        }
+    }
+
+    for (entity, mut stunned) in stunned_query.iter_mut() {
+        if stunned.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Stunned>();
+        }
+    }
+}
+
+pub fn move_hazard(
+    mut query: Query<(&mut Transform, &Hazard), (With<Fish>, Without<Stunned>)>,
+    hazard_time_scale: Res<HazardTimeScale>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32() * hazard_time_scale.scale;
+
     for (mut transform, hazard) in query.iter_mut() {
-        let speed = hazard.speed;
+        transform.translation += (hazard.velocity * delta_seconds).extend(0.0);
+    }
+}
+
+pub fn is_on_screen(bounds: &Bounds, position: Vec2) -> bool {
+    position.x >= bounds.min.x
+        && position.x <= bounds.max.x
+        && position.y >= bounds.min.y
+        && position.y <= bounds.max.y
+}
+
+/// Whether a hazard has left the screen. Only the edges the hazard is travelling towards are
+/// considered so that a hazard waiting in the spawn margin behind an edge is never culled. A
+/// hazard that isn't moving can't be waiting to come in, so any edge counts for it.
+pub fn has_exited(bounds: &Bounds, margin: f32, position: Vec2, velocity: Vec2) -> bool {
+    if velocity == Vec2::ZERO {
+        return position.x > bounds.max.x + margin
+            || position.x < bounds.min.x - margin
+            || position.y > bounds.max.y + margin
+            || position.y < bounds.min.y - margin;
+    }
+
+    let exited_x = (velocity.x > 0.0 && position.x > bounds.max.x + margin)
+        || (velocity.x < 0.0 && position.x < bounds.min.x - margin);
+    let exited_y = (velocity.y > 0.0 && position.y > bounds.max.y + margin)
+        || (velocity.y < 0.0 && position.y < bounds.min.y - margin);
+
+    exited_x || exited_y
+}
+
+pub fn track_hazard_lifecycle(
+    mut hazard_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Hitbox,
+            &mut HazardLifecycle,
+            Option<&Blinded>,
+            Option<&Stunned>,
+        ),
+        With<Hazard>,
+    >,
+    player_query: Query<(Entity, &Transform, &Hitbox), With<Player>>,
+    mut player_hit_events: EventWriter<PlayerHitEvent>,
+    bounds: Res<Bounds>,
+) {
+    for (hazard, transform, hitbox, mut lifecycle, blinded, stunned) in hazard_query.iter_mut() {
+        let position = transform.translation;
+
+        if !lifecycle.entered_screen && is_on_screen(&bounds, position.truncate()) {
+            lifecycle.entered_screen = true;
+        }
+
+        if lifecycle.touched_player || blinded.is_some() || stunned.is_some() {
+            continue;
+        }
+
+        for (player, player_transform, player_hitbox) in player_query.iter() {
+            if hitbox.overlaps(position, player_hitbox, player_transform.translation) {
+                lifecycle.touched_player = true;
+                player_hit_events.send(PlayerHitEvent { player, hazard });
+            }
+        }
+    }
+}
+
+/// In the classic mode every hit that gets past the player's guard costs some health, and
+/// losing the last of it ends the run
+pub fn resolve_classic_hits(
+    mut player_hit_events: EventReader<PlayerHitEvent>,
+    mut player_query: Query<(&mut Health, Option<&mut HitGuard>)>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for PlayerHitEvent { player, .. } in player_hit_events.iter() {
+        let Ok((mut health, guard)) = player_query.get_mut(*player) else {
+            continue;
+        };
+
+        if guard.map_or(false, |mut guard| guard.absorb()) {
+            continue;
+        }
+
+        player_damaged_events.send(PlayerDamagedEvent { player: *player });
+
+        if health.hurt() {
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+fn spawn_health_meter(
+    mut commands: Commands,
+    health_query: Query<(), (With<Player>, Added<Health>)>,
+    root_query: Query<Entity, With<HudRoot>>,
+    font_collection: Res<FontCollection>,
+) {
+    if health_query.is_empty() {
+        return;
+    }
+
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
 
-        transform.translation.x += speed * time.delta_seconds();
+    commands.entity(root).with_children(|parent| {
+        spawn_meter(parent, &font_collection, Meter::Health);
+    });
+}
+
+fn update_health_meter(
+    health_query: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut fill_query: Query<&mut MeterFill>,
+) {
+    for health in health_query.iter() {
+        for mut fill in fill_query.iter_mut() {
+            if fill.meter == Meter::Health {
+                fill.fraction = health.fraction();
+            }
+        }
     }
 }
 
-pub fn despawn_hazard(
+/// Hazards are judged by where they're actually heading, currents and all, so one that gets
+/// swept out backwards or off the top is still cleaned up
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn despawn_exited_hazards(
     mut commands: Commands,
-    mut query: Query<(Entity, &Transform), With<Hazard>>,
+    query: Query<(
+        Entity,
+        &Transform,
+        &Hazard,
+        &HazardLifecycle,
+        &Fish,
+        Option<&Drift>,
+        Option<&Stunned>,
+    )>,
+    mut hazard_exited_events: EventWriter<HazardExited>,
     bounds: Res<Bounds>,
+    lifecycle_settings: Res<HazardLifecycleSettings>,
+    hazard_time_scale: Res<HazardTimeScale>,
+    current_field: Res<CurrentField>,
+    simulation_time: Res<SimulationTime>,
+) {
+    for (entity, transform, hazard, lifecycle, fish, drift, stunned) in query.iter() {
+        let position = transform.translation.truncate();
+        let velocity = if stunned.is_some() {
+            Vec2::ZERO
+        } else {
+            let flow = drift.map_or(Vec2::ZERO, |drift| {
+                current_field.flow_at(&bounds, position, simulation_time.elapsed) * drift.response
+            });
+
+            hazard.velocity * hazard_time_scale.scale + flow
+        };
+
+        if !has_exited(&bounds, lifecycle_settings.spawn_margin, position, velocity) {
+            continue;
+        }
+
+        hazard_exited_events.send(HazardExited {
+            entity,
+            fish_type: fish.fish_type,
+            dodged: lifecycle.dodged(),
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn score_dodged_hazards(
+    mut hazard_exited_events: EventReader<HazardExited>,
+    mut score: ResMut<Score>,
 ) {
-    for (entity, transform) in query.iter_mut() {
-        if transform.translation.x < bounds.min.x || transform.translation.x > bounds.max.x {
-            commands.entity(entity).despawn_recursive();
-        } else if transform.translation.y < bounds.min.y || transform.translation.y > bounds.max.y {
-            commands.entity(entity).despawn_recursive();
+    for hazard_exited in hazard_exited_events.iter() {
+        if hazard_exited.dodged {
+            score.points += DODGE_POINTS;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARGIN: f32 = 2.0;
+
+    fn bounds() -> Bounds {
+        Bounds {
+            min: Vec2::new(-8.0, -6.0),
+            max: Vec2::new(8.0, 6.0),
+        }
+    }
+
+    #[test]
+    fn on_screen_includes_the_edges() {
+        let bounds = bounds();
+
+        assert!(is_on_screen(&bounds, Vec2::ZERO));
+        assert!(is_on_screen(&bounds, bounds.min));
+        assert!(is_on_screen(&bounds, bounds.max));
+        assert!(!is_on_screen(&bounds, Vec2::new(8.1, 0.0)));
+        assert!(!is_on_screen(&bounds, Vec2::new(0.0, -6.1)));
+    }
+
+    #[test]
+    fn exits_through_each_edge_it_swims_towards() {
+        let bounds = bounds();
+        let beyond = MARGIN + 0.1;
+
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(8.0 + beyond, 0.0),
+            Vec2::X
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(-8.0 - beyond, 0.0),
+            Vec2::NEG_X
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(0.0, 6.0 + beyond),
+            Vec2::Y
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(0.0, -6.0 - beyond),
+            Vec2::NEG_Y
+        ));
+    }
+
+    #[test]
+    fn stays_within_the_margin() {
+        let bounds = bounds();
+
+        assert!(!has_exited(&bounds, MARGIN, Vec2::new(9.0, 0.0), Vec2::X));
+        assert!(!has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(0.0, -7.0),
+            Vec2::NEG_Y
+        ));
+    }
+
+    #[test]
+    fn spawning_just_inside_the_margin_is_kept() {
+        let bounds = bounds();
+
+        assert!(!has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(-8.0 - MARGIN, 0.0),
+            Vec2::X
+        ));
+        assert!(!has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(8.0 + MARGIN, 0.0),
+            Vec2::NEG_X
+        ));
+    }
+
+    #[test]
+    fn spawning_just_outside_the_margin_is_kept_while_swimming_in() {
+        let bounds = bounds();
+        let spawn = Vec2::new(-8.0 - MARGIN - 0.1, 0.0);
+
+        assert!(!has_exited(&bounds, MARGIN, spawn, Vec2::X));
+        assert!(has_exited(&bounds, MARGIN, spawn, Vec2::NEG_X));
+    }
+
+    #[test]
+    fn a_hazard_that_isnt_moving_exits_through_any_edge() {
+        let bounds = bounds();
+        let beyond = MARGIN + 0.1;
+
+        assert!(!has_exited(&bounds, MARGIN, Vec2::ZERO, Vec2::ZERO));
+        assert!(!has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(-9.0, 7.0),
+            Vec2::ZERO
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(8.0 + beyond, 0.0),
+            Vec2::ZERO
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(-8.0 - beyond, 0.0),
+            Vec2::ZERO
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(0.0, 6.0 + beyond),
+            Vec2::ZERO
+        ));
+        assert!(has_exited(
+            &bounds,
+            MARGIN,
+            Vec2::new(0.0, -6.0 - beyond),
+            Vec2::ZERO
+        ));
+    }
+
+    #[test]
+    fn only_dodged_hazards_score() {
+        let mut app = App::new();
+        app.init_resource::<Score>()
+            .add_event::<HazardExited>()
+            .add_system(score_dodged_hazards);
+
+        let entity = app.world.spawn_empty().id();
+        for dodged in [true, false, true] {
+            app.world.send_event(HazardExited {
+                entity,
+                fish_type: FishType::Crab,
+                dodged,
+            });
+        }
+        app.update();
+
+        assert_eq!(app.world.resource::<Score>().points, 2 * DODGE_POINTS);
+    }
+
+    fn hit_app(guard: HitGuard) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_state::<GameState>()
+            .add_event::<PlayerHitEvent>()
+            .add_event::<PlayerDamagedEvent>()
+            .add_system(resolve_classic_hits);
+
+        let player = app.world.spawn((Health::default(), guard)).id();
+
+        (app, player)
+    }
+
+    fn hit(app: &mut App, player: Entity) {
+        let hazard = app.world.spawn_empty().id();
+        app.world.send_event(PlayerHitEvent { player, hazard });
+        app.update();
+    }
+
+    #[test]
+    fn guarded_hits_cost_nothing() {
+        let (mut app, player) = hit_app(HitGuard {
+            charges: 1,
+            shields: 1,
+        });
+
+        hit(&mut app, player);
+        hit(&mut app, player);
+
+        assert_eq!(app.world.get::<Health>(player), Some(&Health::default()));
+        assert!(app
+            .world
+            .resource::<Events<PlayerDamagedEvent>>()
+            .is_empty());
+    }
+
+    #[test]
+    fn unguarded_hits_cost_health_until_the_game_is_over() {
+        let (mut app, player) = hit_app(HitGuard::default());
+        let max = Health::default().max;
+
+        for hits in 1..max {
+            hit(&mut app, player);
+            assert_eq!(app.world.get::<Health>(player).unwrap().current, max - hits);
+            assert_eq!(app.world.resource::<NextState<GameState>>().0, None);
         }
+
+        hit(&mut app, player);
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 0);
+        assert_eq!(
+            app.world.resource::<NextState<GameState>>().0,
+            Some(GameState::GameOver)
+        );
     }
 }
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use crate::{fishy_assets::FontCollection, GameState, SimulationSet};
+
+// The in-game overlay with the player's meters
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup_hud.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(
+                sync_meter_fills
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Meter {
+    Health,
+    Stamina,
+    Oxygen,
+    BossHealth,
+}
+
+impl Meter {
+    fn label(&self) -> &'static str {
+        match self {
+            Meter::Health => "Health",
+            Meter::Stamina => "Boost",
+            Meter::Oxygen => "Air",
+            Meter::BossHealth => "Boss",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Meter::Health => Color::hex("ef476f").unwrap(),
+            Meter::Stamina => Color::hex("f2c14e").unwrap(),
+            Meter::Oxygen => Color::hex("48cae4").unwrap(),
+            Meter::BossHealth => Color::hex("d62828").unwrap(),
+        }
+    }
+}
+
+/// The top-left column that meters are stacked in
+#[derive(Component)]
+pub struct HudRoot;
+
+/// The top-right row that active effects are listed in
+#[derive(Component)]
+pub struct EffectStrip;
+
+/// The filled part of a meter. Whatever owns the value being shown writes `fraction` and the
+/// width follows.
+#[derive(Component, Debug)]
+pub struct MeterFill {
+    pub meter: Meter,
+
+    pub fraction: f32,
+}
+
+const METER_WIDTH: f32 = 160.0;
+const METER_HEIGHT: f32 = 12.0;
+
+fn setup_hud(mut commands: Commands, font_collection: Res<FontCollection>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(12.0),
+                        top: Val::Px(12.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    gap: Size::height(Val::Px(6.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot,
+        ))
+        .with_children(|parent| {
+            spawn_meter(parent, &font_collection, Meter::Stamina);
+        });
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(12.0),
+                    top: Val::Px(12.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Row,
+                gap: Size::width(Val::Px(6.0)),
+                ..default()
+            },
+            ..default()
+        },
+        EffectStrip,
+    ));
+}
+
+pub fn spawn_meter(parent: &mut ChildBuilder, font_collection: &FontCollection, meter: Meter) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                gap: Size::width(Val::Px(6.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                meter.label(),
+                TextStyle {
+                    font: font_collection.bold.clone(),
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(METER_WIDTH), Val::Px(METER_HEIGHT)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: meter.color().into(),
+                            ..default()
+                        },
+                        MeterFill {
+                            meter,
+                            fraction: 1.0,
+                        },
+                    ));
+                });
+        });
+}
+
+fn sync_meter_fills(mut query: Query<(&MeterFill, &mut Style), Changed<MeterFill>>) {
+    for (fill, mut style) in query.iter_mut() {
+        style.size.width = Val::Percent(fill.fraction.clamp(0.0, 1.0) * 100.0);
+    }
+}
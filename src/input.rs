@@ -1,8 +1,10 @@
 use bevy::prelude::*;
 use leafwing_input_manager::orientation::Direction;
 use leafwing_input_manager::prelude::*;
+use leafwing_input_manager::user_input::InputKind;
+use serde::{Deserialize, Serialize};
 
-use crate::{GameState, SimulationSet};
+use crate::{fixed_step::StepSet, pointer::PointerSteering, GameState, SimulationSet};
 
 // This plugin maps inputs to an input-type agnostic action-state
 // We need to provide it with an enum which stores the possible actions a player could take
@@ -11,11 +13,23 @@ pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(InputManagerPlugin::<MovementAction>::default())
+            .init_resource::<Bindings>()
             .add_event::<PlayerStateEvent>()
             .add_systems(
-                (set_direction, move_towards)
+                (
+                    apply_bindings.run_if(resource_changed::<Bindings>()),
+                    read_step_input,
+                )
                     .distributive_run_if(in_state(GameState::Playing))
                     .in_set(SimulationSet::Input),
+            )
+            .add_systems(
+                (
+                    apply_step_input.in_set(StepSet::Input),
+                    move_towards.in_set(StepSet::Movement),
+                )
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_schedule(CoreSchedule::FixedUpdate),
             );
     }
 }
@@ -29,16 +43,39 @@ pub struct PlayerStateEvent {
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MovementState {
     Idle,
-    Moving { direction: Vec2 },
+    /// `direction` is shorter than one when steering at less than full speed
+    Moving {
+        direction: Vec2,
+    },
+}
+
+/// What the player is asking for, as the next simulation step sees it. The `ActionState` only
+/// changes once a frame, so presses are held on to until a step takes them. That way one isn't
+/// missed on a frame without a step, or acted on twice on a frame with two.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq)]
+pub struct StepInput {
+    /// Shorter than one when steering at less than full speed
+    pub direction: Vec2,
+
+    pub boost: bool,
+
+    pub dash: bool,
+
+    pub special: bool,
 }
 
 #[derive(Component)]
 pub struct Player {
+    /// World units per second
     speed: f32,
     state: MovementState,
-    lerp_factor: f32,
+    /// How quickly the player turns to face where it's swimming, higher is snappier
+    turn_speed: f32,
     acceleration: f32,
     max_speed: f32,
+    /// Scales both `acceleration` and `max_speed`. Modifiers multiply into it when applied and
+    /// divide back out when they're reverted so they can stack.
+    pub speed_multiplier: f32,
 }
 
 impl Default for Player {
@@ -46,17 +83,42 @@ impl Default for Player {
         Self {
             speed: 0.0,
             state: MovementState::Idle,
-            lerp_factor: 0.1,
-            acceleration: 6.0,
-            max_speed: 1.6,
+            turn_speed: 6.3,
+            acceleration: 36.0,
+            max_speed: 9.6,
+            speed_multiplier: 1.0,
         }
     }
 }
 
+impl Player {
+    pub fn state(&self) -> MovementState {
+        self.state
+    }
+
+    /// Which way and how fast the player is swimming, in world units per second
+    pub fn velocity(&self) -> Vec2 {
+        match self.state {
+            MovementState::Moving { direction } => direction * self.speed,
+            MovementState::Idle => Vec2::ZERO,
+        }
+    }
+
+    /// Kicks the player's speed up to a multiple of its max speed. Anything over the max speed
+    /// is eased back down in `move_towards`.
+    pub fn impulse(&mut self, multiplier: f32) {
+        self.speed = self
+            .speed
+            .max(self.max_speed * self.speed_multiplier * multiplier);
+    }
+}
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     pub player: Player,
 
+    pub step_input: StepInput,
+
     // This bundle must be added to your player entity
     // (or whatever else you wish to control)
     #[bundle]
@@ -65,34 +127,83 @@ pub struct PlayerBundle {
 
 impl PlayerBundle {
     pub fn default_input_map() -> InputMap<MovementAction> {
-        // This allows us to replace `ArpgAction::Up` with `Up`,
-        // significantly reducing boilerplate
-        use MovementAction::*;
         let mut input_map = InputMap::default();
 
-        // Movement
-        input_map.insert(KeyCode::Up, Up);
-        input_map.insert(GamepadButtonType::DPadUp, Up);
+        for version in 0..=BINDINGS_VERSION {
+            let added = default_bindings_added_in(version);
 
-        input_map.insert(KeyCode::Down, Down);
-        input_map.insert(GamepadButtonType::DPadDown, Down);
+            for action in MovementAction::variants() {
+                for input in added.get(action).iter() {
+                    input_map.insert(input.clone(), action);
+                }
+            }
+        }
 
-        input_map.insert(KeyCode::Left, Left);
-        input_map.insert(GamepadButtonType::DPadLeft, Left);
+        input_map
+    }
+}
 
-        input_map.insert(KeyCode::Right, Right);
-        input_map.insert(GamepadButtonType::DPadRight, Right);
+/// Goes up by one whenever a default binding is added, so saved bindings can pick it up
+pub const BINDINGS_VERSION: u32 = 1;
 
-        input_map
+/// The default bindings that were added in each version
+fn default_bindings_added_in(version: u32) -> InputMap<MovementAction> {
+    // This allows us to replace `ArpgAction::Up` with `Up`,
+    // significantly reducing boilerplate
+    use MovementAction::*;
+    let mut input_map = InputMap::default();
+
+    match version {
+        0 => {
+            // Movement
+            input_map.insert(KeyCode::Up, Up);
+            input_map.insert(GamepadButtonType::DPadUp, Up);
+
+            input_map.insert(KeyCode::Down, Down);
+            input_map.insert(GamepadButtonType::DPadDown, Down);
+
+            input_map.insert(KeyCode::Left, Left);
+            input_map.insert(GamepadButtonType::DPadLeft, Left);
+
+            input_map.insert(KeyCode::Right, Right);
+            input_map.insert(GamepadButtonType::DPadRight, Right);
+
+            // Abilities
+            input_map.insert(KeyCode::Space, Dash);
+            input_map.insert(GamepadButtonType::South, Dash);
+
+            input_map.insert(KeyCode::LShift, Boost);
+            input_map.insert(GamepadButtonType::RightTrigger2, Boost);
+
+            input_map.insert(KeyCode::X, Special);
+            input_map.insert(GamepadButtonType::West, Special);
+        }
+        1 => {
+            // WASD works alongside the arrow keys
+            input_map.insert(KeyCode::W, Up);
+            input_map.insert(KeyCode::S, Down);
+            input_map.insert(KeyCode::A, Left);
+            input_map.insert(KeyCode::D, Right);
+
+            input_map.insert(DualAxis::left_stick(), Move);
+        }
+        _ => {}
     }
+
+    input_map
 }
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum MovementAction {
     Up,
     Down,
     Left,
     Right,
+    /// Analog steering, how far the stick is pushed sets how fast the player swims
+    Move,
+    Dash,
+    Boost,
+    Special,
 }
 
 impl MovementAction {
@@ -110,15 +221,126 @@ impl MovementAction {
             MovementAction::Down => Some(Direction::SOUTH),
             MovementAction::Left => Some(Direction::WEST),
             MovementAction::Right => Some(Direction::EAST),
+            _ => None,
         }
     }
+
+    /// Whether the action can be bound to a single key or button from the settings menu
+    pub fn is_rebindable(self) -> bool {
+        self != MovementAction::Move
+    }
 }
 
-fn set_direction(
-    mut query: Query<(Entity, &ActionState<MovementAction>, &mut Player)>,
-    mut player_state_events: EventWriter<PlayerStateEvent>,
+/// The player's controls, which can be rebound from the settings menu. Copied onto the player's
+/// `InputMap` whenever they change.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Bindings {
+    pub input_map: InputMap<MovementAction>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings {
+            input_map: PlayerBundle::default_input_map(),
+        }
+    }
+}
+
+impl Bindings {
+    /// Adds the default bindings that have come along since the bindings were saved. One the
+    /// player has since put to use for something else is left out.
+    pub fn upgrade(&mut self, saved_version: u32) {
+        for version in saved_version + 1..=BINDINGS_VERSION {
+            let added = default_bindings_added_in(version);
+
+            for action in MovementAction::variants() {
+                for input in added.get(action).iter() {
+                    if self.bound_action(input).is_none() {
+                        self.input_map.insert(input.clone(), action);
+                    }
+                }
+            }
+        }
+    }
+
+    fn bound_action(&self, input: &UserInput) -> Option<MovementAction> {
+        MovementAction::variants().find(|action| self.input_map.get(*action).contains(input))
+    }
+
+    /// Binds `input` to `action` in place of whatever key, or gamepad button, it was bound to
+    /// before. Fails with the action that already uses the input, if there is one.
+    pub fn rebind(
+        &mut self,
+        action: MovementAction,
+        input: InputKind,
+    ) -> Result<(), MovementAction> {
+        let input = UserInput::Single(input);
+
+        if let Some(other) = self.bound_action(&input).filter(|other| *other != action) {
+            return Err(other);
+        }
+
+        let kept: Vec<UserInput> = self
+            .input_map
+            .get(action)
+            .iter()
+            .filter(|bound| !same_device(bound, &input))
+            .cloned()
+            .collect();
+
+        self.input_map.clear_action(action);
+        for bound in kept {
+            self.input_map.insert(bound, action);
+        }
+        self.input_map.insert(input, action);
+
+        Ok(())
+    }
+
+    /// Everything bound to the action, for showing in the menu
+    pub fn describe(&self, action: MovementAction) -> String {
+        self.input_map
+            .get(action)
+            .iter()
+            .map(|input| match input {
+                UserInput::Single(InputKind::Keyboard(key)) => format!("{key:?}"),
+                UserInput::Single(InputKind::GamepadButton(button)) => format!("{button:?}"),
+                other => format!("{other:?}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+/// Whether both inputs come from the keyboard or both from a gamepad
+fn same_device(a: &UserInput, b: &UserInput) -> bool {
+    matches!(
+        (a, b),
+        (
+            UserInput::Single(InputKind::Keyboard(_)),
+            UserInput::Single(InputKind::Keyboard(_))
+        ) | (
+            UserInput::Single(InputKind::GamepadButton(_)),
+            UserInput::Single(InputKind::GamepadButton(_))
+        )
+    )
+}
+
+fn apply_bindings(bindings: Res<Bindings>, mut query: Query<&mut InputMap<MovementAction>>) {
+    for mut input_map in query.iter_mut() {
+        *input_map = bindings.input_map.clone();
+    }
+}
+
+/// Pushing the stick less than this far is ignored, so a worn stick doesn't make the player drift
+const STICK_DEADZONE: f32 = 0.2;
+
+/// Fills in the next step's input from this frame's
+pub fn read_step_input(
+    mut query: Query<(&ActionState<MovementAction>, &mut StepInput)>,
+    pointer_steering: Res<PointerSteering>,
 ) {
-    for (entity, action_state, mut player) in query.iter_mut() {
+    for (action_state, mut step_input) in query.iter_mut() {
         let mut intended_direction = Vec2::ZERO;
 
         MovementAction::DIRECTIONS
@@ -132,15 +354,40 @@ fn set_direction(
             intended_direction = intended_direction.normalize();
         }
 
-        let next_state = if intended_direction != Vec2::ZERO {
+        // The keys and D-pad take priority, then the stick, then the mouse or touch
+        if intended_direction == Vec2::ZERO {
+            intended_direction = action_state
+                .axis_pair(MovementAction::Move)
+                .map_or(Vec2::ZERO, |axis_pair| apply_deadzone(axis_pair.xy()));
+        }
+
+        if intended_direction == Vec2::ZERO {
+            intended_direction = pointer_steering.direction.unwrap_or(Vec2::ZERO);
+        }
+
+        step_input.direction = intended_direction;
+        step_input.boost = action_state.pressed(MovementAction::Boost);
+        step_input.dash |= action_state.just_pressed(MovementAction::Dash);
+        step_input.special |= action_state.just_pressed(MovementAction::Special);
+    }
+}
+
+pub fn apply_step_input(
+    mut query: Query<(Entity, &StepInput, &mut Player)>,
+    mut player_state_events: EventWriter<PlayerStateEvent>,
+) {
+    for (entity, step_input, mut player) in query.iter_mut() {
+        let next_state = if step_input.direction != Vec2::ZERO {
             MovementState::Moving {
-                direction: intended_direction,
+                direction: step_input.direction,
             }
         } else {
             MovementState::Idle
         };
 
-        if player.state != next_state {
+        // Analog steering changes the direction nearly every frame, so only starting and
+        // stopping are worth telling anyone about
+        if std::mem::discriminant(&player.state) != std::mem::discriminant(&next_state) {
             player_state_events.send(PlayerStateEvent {
                 state: next_state,
                 entity,
@@ -151,34 +398,123 @@ fn set_direction(
     }
 }
 
-fn move_towards(mut query: Query<(&mut Transform, &mut Player)>, time: Res<Time>) {
-    let delta_seconds = time.delta_seconds();
+/// Ignores small pushes and rescales the rest so speed still builds smoothly from nothing
+fn apply_deadzone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+
+    if length <= STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+
+    let scaled = ((length - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0);
+
+    stick / length * scaled
+}
+
+/// Runs once per simulation step, so it steps by the fixed timestep rather than the frame time
+pub fn move_towards(mut query: Query<(&mut Transform, &mut Player)>, fixed_time: Res<FixedTime>) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
 
     for (mut transform, mut player) in query.iter_mut() {
+        let acceleration = player.acceleration * player.speed_multiplier;
+        let max_speed = player.max_speed * player.speed_multiplier;
+
         if let MovementState::Idle = player.state {
-            player.speed = (player.speed - player.acceleration * 3.0 * delta_seconds).max(0.0);
+            player.speed = (player.speed - acceleration * 3.0 * delta_seconds).max(0.0);
         } else if let MovementState::Moving { direction } = player.state {
-            player.speed =
-                (player.speed + player.acceleration * delta_seconds).min(player.max_speed);
+            player.speed = if player.speed > max_speed {
+                // Ease back down after a dash or when a boost ends
+                (player.speed - acceleration * 3.0 * delta_seconds).max(max_speed)
+            } else {
+                (player.speed + acceleration * delta_seconds).min(max_speed)
+            };
 
-            let target_translation = transform.translation.lerp(
-                transform.translation + (direction * player.speed).extend(0.0),
-                player.lerp_factor,
-            );
-
-            transform.translation = target_translation;
+            transform.translation += (direction * player.speed * delta_seconds).extend(0.0);
 
             // If the direction is vertical just continue
             if (direction.x - 0.0).abs() < f32::EPSILON {
                 continue;
             }
 
+            // Eases in the same amount of turn over the same time however the steps are cut up
             let target_rotation = transform.rotation.lerp(
                 Quat::from_rotation_y(direction.angle_between(-Vec2::Y)),
-                player.lerp_factor,
+                1.0 - (-player.turn_speed * delta_seconds).exp(),
             );
 
             transform.rotation = target_rotation;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_bindings() -> Bindings {
+        Bindings {
+            input_map: default_bindings_added_in(0),
+        }
+    }
+
+    #[test]
+    fn upgrading_adds_new_default_bindings() {
+        let mut bindings = first_bindings();
+        bindings.upgrade(0);
+
+        assert_eq!(bindings, Bindings::default());
+    }
+
+    #[test]
+    fn upgrading_keeps_rebound_keys() {
+        let mut bindings = first_bindings();
+        bindings
+            .rebind(MovementAction::Dash, InputKind::Keyboard(KeyCode::W))
+            .unwrap();
+        bindings.upgrade(0);
+
+        let w = UserInput::Single(InputKind::Keyboard(KeyCode::W));
+        assert_eq!(bindings.bound_action(&w), Some(MovementAction::Dash));
+        // The rest of WASD still comes in
+        let s = UserInput::Single(InputKind::Keyboard(KeyCode::S));
+        assert_eq!(bindings.bound_action(&s), Some(MovementAction::Down));
+    }
+
+    #[test]
+    fn upgrading_from_the_current_version_changes_nothing() {
+        let mut bindings = first_bindings();
+        bindings.upgrade(BINDINGS_VERSION);
+
+        assert_eq!(bindings, first_bindings());
+    }
+
+    #[test]
+    fn rebinding_to_an_input_in_use_fails_and_changes_nothing() {
+        let mut bindings = Bindings::default();
+
+        assert_eq!(
+            bindings.rebind(MovementAction::Dash, InputKind::Keyboard(KeyCode::Up)),
+            Err(MovementAction::Up)
+        );
+        assert_eq!(
+            bindings.rebind(
+                MovementAction::Dash,
+                InputKind::GamepadButton(GamepadButtonType::DPadLeft)
+            ),
+            Err(MovementAction::Left)
+        );
+        assert_eq!(bindings, Bindings::default());
+    }
+
+    #[test]
+    fn rebinding_to_the_same_input_is_allowed() {
+        let mut bindings = Bindings::default();
+
+        assert_eq!(
+            bindings.rebind(MovementAction::Up, InputKind::Keyboard(KeyCode::Up)),
+            Ok(())
+        );
+        let up = UserInput::Single(InputKind::Keyboard(KeyCode::Up));
+        assert_eq!(bindings.bound_action(&up), Some(MovementAction::Up));
+    }
+}
@@ -0,0 +1,602 @@
+use std::collections::HashMap;
+
+use bevy::{
+    core_pipeline::{
+        core_3d::{AlphaMask3d, Opaque3d, Transparent3d},
+        tonemapping::{DebandDither, Tonemapping},
+    },
+    ecs::{
+        change_detection::Ref,
+        system::{lifetimeless::*, SystemParamItem},
+    },
+    pbr::{
+        CascadesVisibleEntities, CubemapVisibleEntities, EnvironmentMapLight,
+        ExtractedDirectionalLight, ExtractedPointLight, LightEntity, MaterialPipeline,
+        MaterialPipelineKey, MeshPipelineKey, MeshUniform, NotShadowCaster, PrepassPipeline,
+        RenderLightSystems, RenderMaterials, SetMeshBindGroup, SetMeshViewBindGroup,
+        SetPrepassViewBindGroup, Shadow, ViewLightEntities,
+    },
+    prelude::*,
+    render::{
+        mesh::{GpuBufferInfo, MeshVertexBufferLayout},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::RenderDevice,
+        view::{ExtractedView, VisibleEntities},
+        Extract, ExtractSchedule, RenderApp, RenderSet,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+// Draws one mesh many times in a single draw call, each instance with its own transform. Only
+// the vertex shader is our own: the material's bind group and the PBR fragment shader are
+// Bevy's, so instances are shaded, fogged, lit and shadowed just like any other
+// `StandardMaterial` mesh.
+pub struct InstancingPlugin;
+
+impl Plugin for InstancingPlugin {
+    fn build(&self, app: &mut App) {
+        // Headless runs, like replays in CI, have no renderer
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .add_render_command::<Opaque3d, DrawInstanced>()
+            .add_render_command::<AlphaMask3d, DrawInstanced>()
+            .add_render_command::<Transparent3d, DrawInstanced>()
+            .add_render_command::<Shadow, DrawInstancedShadow>()
+            .init_resource::<InstancedPipeline>()
+            .init_resource::<SpecializedMeshPipelines<InstancedPipeline>>()
+            .init_resource::<InstancedShadowPipelines>()
+            .init_resource::<ChangedInstances>()
+            .init_resource::<InstanceBuffers>()
+            .add_system(extract_instanced_meshes.in_schedule(ExtractSchedule))
+            .add_system(queue_instanced_meshes.in_set(RenderSet::Queue))
+            .add_system(queue_instanced_shadows.in_set(RenderLightSystems::QueueShadows))
+            .add_system(prepare_instance_buffers.in_set(RenderSet::Prepare));
+    }
+}
+
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct InstanceData {
+    /// Relative to the entity the instances are attached to
+    pub transform: [[f32; 4]; 4],
+}
+
+impl InstanceData {
+    pub fn new(transform: Mat4) -> InstanceData {
+        InstanceData {
+            transform: transform.to_cols_array_2d(),
+        }
+    }
+}
+
+/// Goes next to a `Handle<Mesh>`, but instead of a `Handle<StandardMaterial>` so that the mesh
+/// isn't drawn a second time without its instances. Use `NoFrustumCulling` too, since the mesh's
+/// own bounds don't cover the instances.
+#[derive(Component, Debug, Clone, Default)]
+pub struct InstancedMesh {
+    pub material: Handle<StandardMaterial>,
+
+    pub instances: Vec<InstanceData>,
+}
+
+/// The render world's copy of an entity with an `InstancedMesh`. The instances themselves only
+/// come across when they change.
+#[derive(Component)]
+struct ExtractedInstancedMesh {
+    material: Handle<StandardMaterial>,
+}
+
+/// Instances that changed this frame, waiting to be uploaded
+#[derive(Resource, Default)]
+struct ChangedInstances(Vec<(Entity, Vec<InstanceData>)>);
+
+struct InstanceBuffer {
+    buffer: Buffer,
+
+    length: usize,
+}
+
+/// Kept from frame to frame, unlike the render world's entities, so a buffer is only created
+/// again when its instances change
+#[derive(Resource, Default)]
+struct InstanceBuffers(HashMap<Entity, InstanceBuffer>);
+
+fn extract_instanced_meshes(
+    mut commands: Commands,
+    mut changed_instances: ResMut<ChangedInstances>,
+    query: Extract<Query<(Entity, Ref<InstancedMesh>)>>,
+) {
+    for (entity, instanced_mesh) in query.iter() {
+        commands
+            .get_or_spawn(entity)
+            .insert(ExtractedInstancedMesh {
+                material: instanced_mesh.material.clone_weak(),
+            });
+
+        if instanced_mesh.is_changed() {
+            changed_instances
+                .0
+                .push((entity, instanced_mesh.instances.clone()));
+        }
+    }
+}
+
+/// The parts of the pipeline key that come from the view, the same as Bevy works them out for
+/// its own materials
+fn view_key(
+    msaa: &Msaa,
+    view: &ExtractedView,
+    tonemapping: Option<&Tonemapping>,
+    dither: Option<&DebandDither>,
+    environment_map_loaded: bool,
+) -> MeshPipelineKey {
+    let mut key =
+        MeshPipelineKey::from_msaa_samples(msaa.samples()) | MeshPipelineKey::from_hdr(view.hdr);
+
+    if environment_map_loaded {
+        key |= MeshPipelineKey::ENVIRONMENT_MAP;
+    }
+
+    // HDR views are tonemapped in a pass of their own afterwards
+    if !view.hdr {
+        if let Some(tonemapping) = tonemapping {
+            key |= MeshPipelineKey::TONEMAP_IN_SHADER
+                | match tonemapping {
+                    Tonemapping::None => MeshPipelineKey::TONEMAP_METHOD_NONE,
+                    Tonemapping::Reinhard => MeshPipelineKey::TONEMAP_METHOD_REINHARD,
+                    Tonemapping::ReinhardLuminance => {
+                        MeshPipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE
+                    }
+                    Tonemapping::AcesFitted => MeshPipelineKey::TONEMAP_METHOD_ACES_FITTED,
+                    Tonemapping::AgX => MeshPipelineKey::TONEMAP_METHOD_AGX,
+                    Tonemapping::SomewhatBoringDisplayTransform => {
+                        MeshPipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM
+                    }
+                    Tonemapping::TonyMcMapface => MeshPipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
+                    Tonemapping::BlenderFilmic => MeshPipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
+                };
+        }
+
+        if let Some(DebandDither::Enabled) = dither {
+            key |= MeshPipelineKey::DEBAND_DITHER;
+        }
+    }
+
+    key
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn queue_instanced_meshes(
+    opaque_draw_functions: Res<DrawFunctions<Opaque3d>>,
+    alpha_mask_draw_functions: Res<DrawFunctions<AlphaMask3d>>,
+    transparent_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    instanced_pipeline: Res<InstancedPipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<InstancedPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    render_materials: Res<RenderMaterials<StandardMaterial>>,
+    images: Res<RenderAssets<Image>>,
+    instanced_meshes: Query<(Entity, &MeshUniform, &Handle<Mesh>, &ExtractedInstancedMesh)>,
+    mut views: Query<(
+        &ExtractedView,
+        Option<&Tonemapping>,
+        Option<&DebandDither>,
+        Option<&EnvironmentMapLight>,
+        &mut RenderPhase<Opaque3d>,
+        &mut RenderPhase<AlphaMask3d>,
+        &mut RenderPhase<Transparent3d>,
+    )>,
+) {
+    let draw_opaque = opaque_draw_functions.read().id::<DrawInstanced>();
+    let draw_alpha_mask = alpha_mask_draw_functions.read().id::<DrawInstanced>();
+    let draw_transparent = transparent_draw_functions.read().id::<DrawInstanced>();
+
+    for (
+        view,
+        tonemapping,
+        dither,
+        environment_map,
+        mut opaque_phase,
+        mut alpha_mask_phase,
+        mut transparent_phase,
+    ) in views.iter_mut()
+    {
+        let environment_map_loaded =
+            environment_map.map_or(false, |environment_map| environment_map.is_loaded(&images));
+        let view_key = view_key(&msaa, view, tonemapping, dither, environment_map_loaded);
+        let rangefinder = view.rangefinder3d();
+
+        for (entity, mesh_uniform, mesh_handle, instanced_mesh) in instanced_meshes.iter() {
+            let (Some(mesh), Some(material)) = (
+                meshes.get(mesh_handle),
+                render_materials.get(&instanced_mesh.material),
+            ) else {
+                continue;
+            };
+
+            let mut mesh_key =
+                view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            match material.properties.alpha_mode {
+                AlphaMode::Blend => mesh_key |= MeshPipelineKey::BLEND_ALPHA,
+                AlphaMode::Premultiplied | AlphaMode::Add => {
+                    mesh_key |= MeshPipelineKey::BLEND_PREMULTIPLIED_ALPHA;
+                }
+                AlphaMode::Multiply => mesh_key |= MeshPipelineKey::BLEND_MULTIPLY,
+                AlphaMode::Opaque | AlphaMode::Mask(_) => {}
+            }
+
+            let key = MaterialPipelineKey {
+                mesh_key,
+                bind_group_data: material.key.clone(),
+            };
+            let Ok(pipeline) =
+                pipelines.specialize(&pipeline_cache, &instanced_pipeline, key, &mesh.layout)
+            else {
+                continue;
+            };
+
+            let distance =
+                rangefinder.distance(&mesh_uniform.transform) + material.properties.depth_bias;
+            match material.properties.alpha_mode {
+                AlphaMode::Opaque => opaque_phase.add(Opaque3d {
+                    entity,
+                    pipeline,
+                    draw_function: draw_opaque,
+                    distance,
+                }),
+                AlphaMode::Mask(_) => alpha_mask_phase.add(AlphaMask3d {
+                    entity,
+                    pipeline,
+                    draw_function: draw_alpha_mask,
+                    distance,
+                }),
+                AlphaMode::Blend
+                | AlphaMode::Premultiplied
+                | AlphaMode::Add
+                | AlphaMode::Multiply => transparent_phase.add(Transparent3d {
+                    entity,
+                    pipeline,
+                    draw_function: draw_transparent,
+                    distance,
+                }),
+            }
+        }
+    }
+}
+
+/// Draws the instances into the shadow maps of each light that can see them
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn queue_instanced_shadows(
+    shadow_draw_functions: Res<DrawFunctions<Shadow>>,
+    prepass_pipeline: Res<PrepassPipeline<StandardMaterial>>,
+    mut shadow_pipelines: ResMut<InstancedShadowPipelines>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    render_materials: Res<RenderMaterials<StandardMaterial>>,
+    casting_meshes: Query<(&Handle<Mesh>, &ExtractedInstancedMesh), Without<NotShadowCaster>>,
+    view_lights: Query<(Entity, &ViewLightEntities)>,
+    mut view_light_shadow_phases: Query<(&LightEntity, &mut RenderPhase<Shadow>)>,
+    point_light_entities: Query<&CubemapVisibleEntities, With<ExtractedPointLight>>,
+    directional_light_entities: Query<&CascadesVisibleEntities, With<ExtractedDirectionalLight>>,
+    spot_light_entities: Query<&VisibleEntities, With<ExtractedPointLight>>,
+) {
+    let draw_shadow = shadow_draw_functions.read().id::<DrawInstancedShadow>();
+
+    for (view, view_lights) in view_lights.iter() {
+        for view_light in view_lights.lights.iter().copied() {
+            let Ok((light_entity, mut shadow_phase)) = view_light_shadow_phases.get_mut(view_light)
+            else {
+                continue;
+            };
+
+            let is_directional_light = matches!(light_entity, LightEntity::Directional { .. });
+            let visible_entities = match light_entity {
+                LightEntity::Directional {
+                    light_entity,
+                    cascade_index,
+                } => directional_light_entities
+                    .get(*light_entity)
+                    .ok()
+                    .and_then(|cascades| cascades.entities.get(&view))
+                    .and_then(|cascades| cascades.get(*cascade_index)),
+                LightEntity::Point {
+                    light_entity,
+                    face_index,
+                } => point_light_entities
+                    .get(*light_entity)
+                    .ok()
+                    .map(|faces| faces.get(*face_index)),
+                LightEntity::Spot { light_entity } => spot_light_entities.get(*light_entity).ok(),
+            };
+            // Lights without shadows don't see anything
+            let Some(visible_entities) = visible_entities else {
+                continue;
+            };
+
+            for entity in visible_entities.iter().copied() {
+                let Ok((mesh_handle, instanced_mesh)) = casting_meshes.get(entity) else {
+                    continue;
+                };
+                let (Some(mesh), Some(material)) = (
+                    meshes.get(mesh_handle),
+                    render_materials.get(&instanced_mesh.material),
+                ) else {
+                    continue;
+                };
+
+                let mut mesh_key =
+                    MeshPipelineKey::from_primitive_topology(mesh.primitive_topology)
+                        | MeshPipelineKey::DEPTH_PREPASS;
+                if is_directional_light {
+                    mesh_key |= MeshPipelineKey::DEPTH_CLAMP_ORTHO;
+                }
+                match material.properties.alpha_mode {
+                    AlphaMode::Mask(_) => mesh_key |= MeshPipelineKey::ALPHA_MASK,
+                    AlphaMode::Blend | AlphaMode::Premultiplied | AlphaMode::Add => {
+                        mesh_key |= MeshPipelineKey::BLEND_PREMULTIPLIED_ALPHA;
+                    }
+                    AlphaMode::Opaque | AlphaMode::Multiply => {}
+                }
+
+                let key = MaterialPipelineKey {
+                    mesh_key,
+                    bind_group_data: material.key.clone(),
+                };
+                let Some(pipeline) = shadow_pipelines.specialize(
+                    &pipeline_cache,
+                    &prepass_pipeline,
+                    key,
+                    &mesh.layout,
+                ) else {
+                    continue;
+                };
+
+                shadow_phase.add(Shadow {
+                    draw_function: draw_shadow,
+                    pipeline,
+                    entity,
+                    distance: 0.0,
+                });
+            }
+        }
+    }
+}
+
+fn prepare_instance_buffers(
+    query: Query<Entity, With<ExtractedInstancedMesh>>,
+    mut changed_instances: ResMut<ChangedInstances>,
+    mut instance_buffers: ResMut<InstanceBuffers>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, instances) in changed_instances.0.drain(..) {
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("instance data buffer"),
+            contents: bytemuck::cast_slice(instances.as_slice()),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        instance_buffers.0.insert(
+            entity,
+            InstanceBuffer {
+                buffer,
+                length: instances.len(),
+            },
+        );
+    }
+
+    // Anything that wasn't extracted this frame has been despawned or lost its instances
+    instance_buffers
+        .0
+        .retain(|entity, _| query.contains(*entity));
+}
+
+/// Mesh attributes take up the first few shader locations, so instance data starts after them
+const FIRST_INSTANCE_LOCATION: u32 = 8;
+
+/// Swaps in a vertex shader that reads the instance buffer as well as the mesh
+fn add_instance_buffer(descriptor: &mut RenderPipelineDescriptor, vertex_shader: &Handle<Shader>) {
+    // The transform goes in as four columns
+    let attributes = (0..4)
+        .map(|index| VertexAttribute {
+            format: VertexFormat::Float32x4,
+            offset: VertexFormat::Float32x4.size() * index as u64,
+            shader_location: FIRST_INSTANCE_LOCATION + index,
+        })
+        .collect();
+
+    descriptor.vertex.shader = vertex_shader.clone();
+    descriptor.vertex.buffers.push(VertexBufferLayout {
+        array_stride: std::mem::size_of::<InstanceData>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes,
+    });
+}
+
+#[derive(Resource)]
+struct InstancedPipeline {
+    shader: Handle<Shader>,
+
+    material_pipeline: MaterialPipeline<StandardMaterial>,
+}
+
+impl FromWorld for InstancedPipeline {
+    fn from_world(world: &mut World) -> InstancedPipeline {
+        let shader = world
+            .resource::<AssetServer>()
+            .load("shaders/instanced_mesh.wgsl");
+        let material_pipeline = world
+            .resource::<MaterialPipeline<StandardMaterial>>()
+            .clone();
+
+        InstancedPipeline {
+            shader,
+            material_pipeline,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for InstancedPipeline {
+    type Key = MaterialPipelineKey<StandardMaterial>;
+
+    fn specialize(
+        &self,
+        key: MaterialPipelineKey<StandardMaterial>,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.material_pipeline.specialize(key, layout)?;
+        add_instance_buffer(&mut descriptor, &self.shader);
+
+        Ok(descriptor)
+    }
+}
+
+/// The same as the prepass pipelines Bevy draws shadows with, plus the instance buffer. Bevy's
+/// `PrepassPipeline` can't be cloned into a pipeline of our own, so these are specialized and
+/// cached by hand rather than through `SpecializedMeshPipelines`.
+#[derive(Resource)]
+struct InstancedShadowPipelines {
+    shader: Handle<Shader>,
+
+    pipelines: HashMap<
+        (
+            MaterialPipelineKey<StandardMaterial>,
+            MeshVertexBufferLayout,
+        ),
+        CachedRenderPipelineId,
+    >,
+}
+
+impl FromWorld for InstancedShadowPipelines {
+    fn from_world(world: &mut World) -> InstancedShadowPipelines {
+        let shader = world
+            .resource::<AssetServer>()
+            .load("shaders/instanced_prepass.wgsl");
+
+        InstancedShadowPipelines {
+            shader,
+            pipelines: HashMap::new(),
+        }
+    }
+}
+
+impl InstancedShadowPipelines {
+    fn specialize(
+        &mut self,
+        pipeline_cache: &PipelineCache,
+        prepass_pipeline: &PrepassPipeline<StandardMaterial>,
+        key: MaterialPipelineKey<StandardMaterial>,
+        layout: &MeshVertexBufferLayout,
+    ) -> Option<CachedRenderPipelineId> {
+        if let Some(pipeline) = self.pipelines.get(&(key.clone(), layout.clone())) {
+            return Some(*pipeline);
+        }
+
+        let mut descriptor = match prepass_pipeline.specialize(key.clone(), layout) {
+            Ok(descriptor) => descriptor,
+            Err(err) => {
+                error!("{}", err);
+                return None;
+            }
+        };
+        add_instance_buffer(&mut descriptor, &self.shader);
+
+        let pipeline = pipeline_cache.queue_render_pipeline(descriptor);
+        self.pipelines.insert((key, layout.clone()), pipeline);
+
+        Some(pipeline)
+    }
+}
+
+type DrawInstanced = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetInstancedMaterialBindGroup<1>,
+    SetMeshBindGroup<2>,
+    DrawMeshInstanced,
+);
+
+type DrawInstancedShadow = (
+    SetItemPipeline,
+    SetPrepassViewBindGroup<0>,
+    SetInstancedMaterialBindGroup<1>,
+    SetMeshBindGroup<2>,
+    DrawMeshInstanced,
+);
+
+/// Bevy's `SetMaterialBindGroup`, but for the material kept in `ExtractedInstancedMesh`
+struct SetInstancedMaterialBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetInstancedMaterialBindGroup<I> {
+    type Param = SRes<RenderMaterials<StandardMaterial>>;
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<ExtractedInstancedMesh>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        instanced_mesh: &'w ExtractedInstancedMesh,
+        render_materials: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(material) = render_materials.into_inner().get(&instanced_mesh.material) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, &material.bind_group, &[]);
+
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawMeshInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (SRes<RenderAssets<Mesh>>, SRes<InstanceBuffers>);
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<Handle<Mesh>>;
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        mesh_handle: &'w Handle<Mesh>,
+        (meshes, instance_buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_handle) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(instance_buffer) = instance_buffers.into_inner().0.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, 0..instance_buffer.length as u32);
+            }
+            GpuBufferInfo::NonIndexed { vertex_count } => {
+                pass.draw(0..*vertex_count, 0..instance_buffer.length as u32);
+            }
+        }
+
+        RenderCommandResult::Success
+    }
+}
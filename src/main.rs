@@ -1,54 +1,191 @@
 use std::{f32::consts::PI, time::Duration};
 
+use abilities::{Abilities, AbilitiesPlugin};
+use ambient::AmbientPlugin;
+use background::BackgroundPlugin;
+use bend::{BendPlugin, Bendable};
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
     math::vec3,
     pbr::CascadeShadowConfigBuilder,
     prelude::*,
-    render::{camera::ScalingMode, mesh::Indices, render_resource::PrimitiveTopology},
+    render::{
+        camera::{CameraProjection, ScalingMode},
+        mesh::Indices,
+        render_resource::PrimitiveTopology,
+        settings::WgpuSettings,
+        RenderPlugin,
+    },
     window::PrimaryWindow,
 };
 use bevy_asset_loader::prelude::{LoadingState, LoadingStateAppExt};
+use bevy_kira_audio::AudioPlugin;
+use biome::CurrentBiome;
+use boss::BossPlugin;
+use camera::{CameraController, CameraControllerPlugin};
+use collectible::CollectiblePlugin;
+use collision::{apply_body_scale, BodyScale, Health, HitGuard, Hitbox};
+use current::{CurrentPlugin, Drift};
+use daynight::{DayNightPlugin, Sun};
+use depth::DepthPlugin;
+use fish_effects::FishEffectsPlugin;
 use fishy_assets::{
-    CoralCollection, FishAnimationCollection, FishCollection, FishType, RockCollection,
-    SeaweedAnimationCollection, SeaweedCollection, ShellsCollection, TextureCollection,
+    AudioCollection, CoralCollection, FishAnimationCollection, FishCollection, FishIconCollection,
+    FishType, FontCollection, RockCollection, SeaweedAnimationCollection, SeaweedCollection,
+    ShellsCollection, TextureCollection,
 };
+use fixed_step::{FixedStepPlugin, Interpolated, StepSet};
+use frenzy::FrenzyPlugin;
+use graphics::GraphicsPlugin;
 use hazard::HazardPlugin;
-use input::{InputPlugin, MovementState, Player, PlayerBundle, PlayerStateEvent};
+use hud::HudPlugin;
+use input::{
+    Bindings, InputPlugin, MovementState, Player, PlayerBundle, PlayerStateEvent, StepInput,
+};
+use instancing::InstancingPlugin;
 use leafwing_input_manager::InputManagerBundle;
+use material_override::MaterialOverridePlugin;
 use noisy_bevy::{fbm_simplex_3d, NoisyShaderPlugin};
+use particles::ParticlePlugin;
+use pointer::PointerPlugin;
+use powerup::{Magnet, PowerUpPlugin};
 use rand::{seq::SliceRandom, thread_rng, Rng};
+use replay::{ReplayMode, ReplayPlugin};
+use rng::RngPlugin;
+use seabed::SeabedPlugin;
+use serde::{Deserialize, Serialize};
+use settings::SettingsPlugin;
+use settings_menu::SettingsMenuPlugin;
+use stats::{Energy, Score};
 use strum::IntoEnumIterator;
-
-use crate::fishy_assets::{CoralType, RockType, SeaweedType, ShellType};
-
+use sway::{SeaweedSwaySettings, ShaderSway, SwayMode, SwayPlugin};
+use telegraph::TelegraphPlugin;
+use water_effects::WaterEffectsPlugin;
+
+use crate::fishy_assets::SeaweedType;
+
+mod abilities;
+mod ambient;
+mod background;
+mod bend;
+mod biome;
+mod boss;
+mod camera;
+mod collectible;
+mod collision;
 mod compute_normals;
+mod current;
+mod daynight;
+mod depth;
+mod fish_effects;
 mod fishy_assets;
+mod fixed_step;
+mod frenzy;
+mod graphics;
 mod hazard;
+mod hud;
 mod input;
+mod instancing;
+mod material_override;
+mod particles;
+mod pointer;
+mod powerup;
+mod replay;
+mod rng;
+mod seabed;
+mod settings;
+mod settings_menu;
+mod stats;
+mod sway;
+mod telegraph;
+mod water_effects;
 
 const WINDOW_WIDTH: f32 = 800.0;
 const WINDOW_HEIGHT: f32 = 600.0;
 
+/// How long the player is from nose to tail, whatever the species
+const PLAYER_LENGTH: f32 = 2.0;
+
 fn main() {
-    App::new()
-        // Window resource
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
+    // Runs without a window or renderer, for replaying recordings in CI
+    let headless = std::env::args().any(|arg| arg == "--headless");
+
+    let mut plugins = DefaultPlugins
+        .set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Fishy".to_string(), // ToDo
                 resolution: (WINDOW_WIDTH, WINDOW_HEIGHT).into(),
                 canvas: Some("#bevy".to_owned()),
                 position: WindowPosition::At((0, 0).into()),
+                visible: !headless,
                 ..default()
             }),
             ..default()
-        }))
+        })
+        .set(RenderPlugin {
+            wgpu_settings: if headless {
+                WgpuSettings {
+                    backends: None,
+                    ..default()
+                }
+            } else {
+                default()
+            },
+        })
+        // Audio is handled by kira instead
+        .disable::<bevy::audio::AudioPlugin>();
+    if headless {
+        // The primary window is still created so everything sized by it works, but nothing
+        // opens it and the app is driven by a plain loop instead of the window's event loop
+        plugins = plugins
+            .disable::<bevy::winit::WinitPlugin>()
+            .add(bevy::app::ScheduleRunnerPlugin::default());
+    }
+
+    App::new()
+        // Window resource
+        .add_plugins(plugins)
+        .add_plugin(AudioPlugin)
         .add_plugin(NoisyShaderPlugin)
         .add_plugin(InputPlugin)
         .add_plugin(HazardPlugin)
-        // A deepwater blue
-        .insert_resource(ClearColor(Color::rgb(0.6, 0.8, 9.0)))
+        .add_plugin(TelegraphPlugin)
+        .add_plugin(CollectiblePlugin)
+        .add_plugin(FrenzyPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(AbilitiesPlugin)
+        .add_plugin(PowerUpPlugin)
+        .add_plugin(BossPlugin)
+        .add_plugin(CurrentPlugin)
+        .add_plugin(AmbientPlugin)
+        .add_plugin(DepthPlugin)
+        .add_plugin(CameraControllerPlugin)
+        .add_plugin(BackgroundPlugin)
+        .add_plugin(WaterEffectsPlugin)
+        .add_plugin(ParticlePlugin)
+        .add_plugin(InstancingPlugin)
+        .add_plugin(SeabedPlugin)
+        .add_plugin(SwayPlugin)
+        .add_plugin(BendPlugin)
+        .add_plugin(DayNightPlugin)
+        .add_plugin(MaterialOverridePlugin)
+        .add_plugin(FishEffectsPlugin)
+        .add_plugin(GraphicsPlugin)
+        .add_plugin(SettingsMenuPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(PointerPlugin)
+        .add_plugin(FixedStepPlugin)
+        .add_plugin(RngPlugin)
+        .add_plugin(ReplayPlugin)
+        // A deepwater blue. Kept within range since HDR would take it literally.
+        .insert_resource(ClearColor(Color::rgb(0.6, 0.8, 1.0)))
         .insert_resource(Bounds::default())
+        .insert_resource(GameMode::from_args())
+        .insert_resource(PlayerSpecies::from_args())
+        .insert_resource(ReplayMode::from_args())
+        .init_resource::<CurrentBiome>()
+        .init_resource::<Score>()
+        .init_resource::<Energy>()
         .add_state::<GameState>()
         .add_loading_state(
             LoadingState::new(GameState::AssetLoading).continue_to_state(GameState::Playing),
@@ -61,12 +198,20 @@ fn main() {
         .add_collection_to_loading_state::<_, TextureCollection>(GameState::AssetLoading)
         .add_collection_to_loading_state::<_, CoralCollection>(GameState::AssetLoading)
         .add_collection_to_loading_state::<_, ShellsCollection>(GameState::AssetLoading)
+        .add_collection_to_loading_state::<_, FishIconCollection>(GameState::AssetLoading)
+        .add_collection_to_loading_state::<_, FontCollection>(GameState::AssetLoading)
+        .add_collection_to_loading_state::<_, AudioCollection>(GameState::AssetLoading)
         .insert_resource(AmbientLight {
             color: Color::WHITE,
             brightness: 1.0 / 5.0f32,
         })
         .configure_sets(
-            (SimulationSet::Input, SimulationSet::Logic)
+            (
+                SimulationSet::Camera,
+                SimulationSet::Bounds,
+                SimulationSet::Input,
+                SimulationSet::Logic,
+            )
                 .chain()
                 .in_base_set(CoreSet::Update),
         )
@@ -75,20 +220,32 @@ fn main() {
                 .in_set(SimulationSet::Logic)
                 .in_schedule(OnEnter(GameState::Playing)),
         )
+        .add_system(setup_game_over.in_schedule(OnEnter(GameState::GameOver)))
+        // Bounds have to be up to date before anything is spawned or culled against them
+        .add_system(
+            update_bounds
+                .run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Bounds),
+        )
         .add_systems(
             (
                 play_initial_animations,
-                update_bounds,
-                constrain_to_bounds,
                 update_player_animations,
+                apply_body_scale,
             )
                 .distributive_run_if(in_state(GameState::Playing))
                 .in_set(SimulationSet::Logic),
         )
+        .add_system(
+            constrain_to_bounds
+                .run_if(in_state(GameState::Playing))
+                .in_set(StepSet::Constraints)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
         .run();
 }
 
-#[derive(Resource, Default)]
+#[derive(Resource, Default, Debug, PartialEq)]
 pub struct Bounds {
     pub min: Vec2,
 
@@ -98,6 +255,8 @@ pub struct Bounds {
 // System sets can be used to group systems and configured to control relative ordering
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SimulationSet {
+    Camera,
+    Bounds,
     Input,
     Logic,
 }
@@ -110,11 +269,110 @@ enum GameState {
     GameOver,
 }
 
+#[derive(Resource, Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Dodge the hazards
+    #[default]
+    Classic,
+    /// Eat smaller fish to grow, avoid bigger ones
+    FeedingFrenzy,
+}
+
+impl GameMode {
+    /// Picks the mode from the command line, e.g. `fishy --frenzy`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_args() -> GameMode {
+        if std::env::args().any(|arg| arg == "--frenzy") {
+            GameMode::FeedingFrenzy
+        } else {
+            GameMode::Classic
+        }
+    }
+
+    /// The web build has no command line, so the mode comes from the page's query string
+    /// instead, e.g. `index.html?mode=frenzy`
+    #[cfg(target_arch = "wasm32")]
+    fn from_args() -> GameMode {
+        let search = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        let frenzy = search
+            .trim_start_matches('?')
+            .split('&')
+            .any(|pair| pair == "mode=frenzy");
+
+        if frenzy {
+            GameMode::FeedingFrenzy
+        } else {
+            GameMode::Classic
+        }
+    }
+}
+
+/// The species the player swims as. Each one has its own special ability.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PlayerSpecies {
+    pub fish_type: FishType,
+}
+
+impl Default for PlayerSpecies {
+    fn default() -> PlayerSpecies {
+        PlayerSpecies {
+            fish_type: FishType::Turtle,
+        }
+    }
+}
+
+impl PlayerSpecies {
+    /// Looks a species up by name, ignoring case, e.g. `squid`
+    fn from_name(name: &str) -> Option<PlayerSpecies> {
+        FishType::iter()
+            .find(|fish_type| format!("{fish_type:?}").eq_ignore_ascii_case(name))
+            .map(|fish_type| PlayerSpecies { fish_type })
+    }
+
+    /// Picks the species from the command line, e.g. `fishy --species eel`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_args() -> PlayerSpecies {
+        let args = std::env::args().collect::<Vec<_>>();
+
+        args.iter()
+            .position(|arg| arg == "--species")
+            .and_then(|index| args.get(index + 1))
+            .and_then(|name| PlayerSpecies::from_name(name))
+            .unwrap_or_default()
+    }
+
+    /// Picks the species from the page's query string, e.g. `index.html?species=eel`
+    #[cfg(target_arch = "wasm32")]
+    fn from_args() -> PlayerSpecies {
+        let search = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("species="))
+            .and_then(PlayerSpecies::from_name)
+            .unwrap_or_default()
+    }
+}
+
+/// Run condition that checks the current `GameMode`, like `in_state` does for states
+pub fn in_mode(mode: GameMode) -> impl FnMut(Res<GameMode>) -> bool + Clone {
+    move |current_mode: Res<GameMode>| *current_mode == mode
+}
+
 #[derive(Component, Debug)]
 pub struct Fish {
     pub fish_type: FishType,
 }
 
+/// The seabed mesh
+#[derive(Component)]
+pub struct Terrain;
+
 #[derive(Bundle)]
 struct FishBundle {
     fish: Fish,
@@ -123,34 +381,44 @@ struct FishBundle {
     scene: SceneBundle,
 }
 
-const RADIUS: f32 = 100.;
+pub const RADIUS: f32 = 100.;
+
+/// Roughly how tall the seaweed models are before they're scaled
+pub const SEAWEED_HEIGHT: f32 = 2.0;
+
+/// Everything on the seabed hangs off this
+#[derive(Component)]
+pub struct Seabed;
+
+/// The height of the seabed at a point, relative to the `Seabed`
+pub fn terrain_height(x: f32, z: f32) -> f32 {
+    const FREQUENCY_SCALE: f32 = 0.1;
+    const AMPLITUDE_SCALE: f32 = 2.0;
+    const OCTAVES: usize = 3;
+    const LACUNARITY: f32 = 1.5; // Increase this value to create more peaks
+    const GAIN: f32 = 0.001; // Decrease this value to create more peaks
+
+    let p = vec3(x, 0.0, z);
+    let offset = fbm_simplex_3d(p * FREQUENCY_SCALE, OCTAVES, LACUNARITY, GAIN) * AMPLITUDE_SCALE;
+
+    offset + 0.5
+}
 
 fn setup_level_gen(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    coral_collection: Res<CoralCollection>,
-    rock_collection: Res<RockCollection>,
     seaweed_collection: Res<SeaweedCollection>,
     seaweed_animation_collection: Res<SeaweedAnimationCollection>,
-    shells_collection: Res<ShellsCollection>,
+    sway_settings: Res<SeaweedSwaySettings>,
 ) {
-    const FREQUENCY_SCALE: f32 = 0.1;
-    const AMPLITUDE_SCALE: f32 = 2.0;
-    const OCTAVES: usize = 3;
-    const LACUNARITY: f32 = 1.5; // Increase this value to create more peaks
-    const GAIN: f32 = 0.001; // Decrease this value to create more peaks
-
     let grid_half_size = RADIUS as i32 + 1;
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
 
     for x in -grid_half_size..=grid_half_size {
         for z in -grid_half_size..=grid_half_size {
-            let p = vec3(x as f32, 0.0, z as f32);
-            let offset =
-                fbm_simplex_3d(p * FREQUENCY_SCALE, OCTAVES, LACUNARITY, GAIN) * AMPLITUDE_SCALE;
-            let height = offset + 0.5;
+            let height = terrain_height(x as f32, z as f32);
 
             // Add a new vertex at the calculated position.
             vertices.push([x as f32, height, z as f32]);
@@ -180,86 +448,40 @@ fn setup_level_gen(
     terrain_mesh.duplicate_vertices();
     compute_normals::compute_normals(&mut terrain_mesh);
 
-    const CORAL_TYPES: usize = 100;
-    const ROCK_TYPES: usize = 80;
+    // Corals, rocks and shells are scattered by the seabed plugin
     const SEAWEED_TYPES: usize = 100;
-    const SHELL_TYPES: usize = 60;
     const Y_OFFSET: f32 = -16.0;
 
     let mut rng = thread_rng();
-    let coral_types = CoralType::iter().collect::<Vec<_>>();
-    let rock_types = RockType::iter().collect::<Vec<_>>();
     let seaweed_types = SeaweedType::iter().collect::<Vec<_>>();
-    let shell_types = ShellType::iter().collect::<Vec<_>>();
 
-    let mut underwater_scene = commands.spawn(SpatialBundle {
-        transform: Transform::from_xyz(0.0, Y_OFFSET, RADIUS / 4.0),
-        ..default()
-    });
+    let mut underwater_scene = commands.spawn((
+        SpatialBundle {
+            transform: Transform::from_xyz(0.0, Y_OFFSET, RADIUS / 4.0),
+            ..default()
+        },
+        Seabed,
+    ));
 
     underwater_scene.with_children(|parent| {
-        parent.spawn(MaterialMeshBundle {
-            mesh: meshes.add(terrain_mesh.clone()),
-            transform: Transform::from_xyz(0.0, 0.0, 0.0),
-            material: materials.add(StandardMaterial {
-                // solid White
-                // base_color: Color::hex("ffffff").unwrap(),
-                // dark blue
-                base_color: Color::hex("0a0a2c").unwrap(),
-                perceptual_roughness: 0.8,
+        parent.spawn((
+            MaterialMeshBundle {
+                mesh: meshes.add(terrain_mesh.clone()),
+                transform: Transform::from_xyz(0.0, 0.0, 0.0),
+                material: materials.add(StandardMaterial {
+                    // solid White
+                    // base_color: Color::hex("ffffff").unwrap(),
+                    // dark blue
+                    base_color: Color::hex("0a0a2c").unwrap(),
+                    perceptual_roughness: 0.8,
+                    ..default()
+                }),
                 ..default()
-            }),
-            ..default()
-        });
+            },
+            Terrain,
+        ));
     });
 
-    for _ in 0..CORAL_TYPES {
-        // let coral_type = &CoralType::Coral6;
-        let coral_type = coral_types.choose(&mut rng).unwrap();
-        let coral_scene = coral_type.model_from(&coral_collection);
-        let scale = rng.gen_range(0.5..=3.0);
-        let x = rng.gen_range(-RADIUS..=RADIUS);
-        let z = rng.gen_range(-RADIUS..=RADIUS);
-        // Just get N nearest vertices by x and z and take the average height
-        let y = vertices
-            .iter()
-            .filter(|v| (v[0] - x as f32).abs() < 1.0 && (v[2] - z as f32).abs() < 1.0)
-            .map(|v| v[1])
-            .sum::<f32>()
-            / 4.0;
-
-        underwater_scene.with_children(|parent| {
-            parent.spawn(SceneBundle {
-                scene: coral_scene,
-                transform: Transform::from_xyz(x, y - 2.0, z).with_scale(Vec3::splat(scale)),
-                ..default()
-            });
-        });
-    }
-
-    for _ in 0..ROCK_TYPES {
-        let rock_type = rock_types.choose(&mut rng).unwrap();
-        let rock_scene = rock_type.model_from(&rock_collection);
-        let scale = rng.gen_range(0.5..=4.0);
-        let x = rng.gen_range(-RADIUS..=RADIUS);
-        let z = rng.gen_range(-RADIUS..=RADIUS);
-        // Just get N nearest vertices by x and z and take the average height
-        let y = vertices
-            .iter()
-            .filter(|v| (v[0] - x as f32).abs() < 1.0 && (v[2] - z as f32).abs() < 1.0)
-            .map(|v| v[1])
-            .sum::<f32>()
-            / 4.0;
-
-        underwater_scene.with_children(|parent| {
-            parent.spawn(SceneBundle {
-                scene: rock_scene,
-                transform: Transform::from_xyz(x, y - 4.0, z).with_scale(Vec3::splat(scale)),
-                ..default()
-            });
-        });
-    }
-
     for _ in 0..SEAWEED_TYPES {
         // let seaweed_type = SeaweedType::Seaweed;
         let seaweed_type = seaweed_types.choose(&mut rng).unwrap();
@@ -277,69 +499,53 @@ fn setup_level_gen(
             / 4.0;
 
         underwater_scene.with_children(|parent| {
-            parent.spawn((
+            let mut seaweed = parent.spawn((
                 SceneBundle {
                     scene: seaweed_scene,
                     transform: Transform::from_xyz(x, y - 2.0, z).with_scale(Vec3::splat(scale)),
                     ..default()
                 },
-                InitialAnimation {
-                    animation,
-                    repeat: true,
-                },
+                Bendable::new(Quat::IDENTITY, SEAWEED_HEIGHT * scale),
             ));
-        });
-    }
-
-    for _ in 0..SHELL_TYPES {
-        let shell_type = shell_types.choose(&mut rng).unwrap();
-        let shell_scene = shell_type.model_from(&shells_collection);
-        let scale = rng.gen_range(0.5..=1.0);
-        let x = rng.gen_range(-RADIUS..=RADIUS);
-        let z = rng.gen_range(-RADIUS..=RADIUS);
-        // Just get N nearest vertices by x and z and take the average height
-        let y = vertices
-            .iter()
-            .filter(|v| (v[0] - x as f32).abs() < 1.0 && (v[2] - z as f32).abs() < 1.0)
-            .map(|v| v[1])
-            .sum::<f32>()
-            / 4.0;
 
-        underwater_scene.with_children(|parent| {
-            parent.spawn(SceneBundle {
-                scene: shell_scene,
-                transform: Transform::from_xyz(x, y, z).with_scale(Vec3::splat(scale)),
-                ..default()
-            });
+            match sway_settings.mode(*seaweed_type) {
+                SwayMode::Skeletal => {
+                    seaweed.insert(InitialAnimation {
+                        animation,
+                        repeat: true,
+                    });
+                }
+                SwayMode::Shader => {
+                    seaweed.insert(ShaderSway);
+                }
+            }
         });
     }
 }
 
-fn setup_graphics(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    texture_collection: Res<TextureCollection>,
-) {
-    // directional 'sun' light
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            illuminance: 10000.0,
-            shadows_enabled: true,
+fn setup_graphics(mut commands: Commands) {
+    // directional 'sun' light, moved around by the day/night cycle
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: 10000.0,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 30.0, 0.01).looking_at(Vec3::ZERO, Vec3::Y),
+            // The default cascade config is designed to handle large scenes.
+            // As this example has a much smaller world, we can tighten the shadow
+            // bounds for better visual quality.
+            cascade_shadow_config: CascadeShadowConfigBuilder {
+                first_cascade_far_bound: 4.0,
+                maximum_distance: 10.0,
+                ..default()
+            }
+            .into(),
             ..default()
         },
-        transform: Transform::from_xyz(0.0, 30.0, 0.01).looking_at(Vec3::ZERO, Vec3::Y),
-        // The default cascade config is designed to handle large scenes.
-        // As this example has a much smaller world, we can tighten the shadow
-        // bounds for better visual quality.
-        cascade_shadow_config: CascadeShadowConfigBuilder {
-            first_cascade_far_bound: 4.0,
-            maximum_distance: 10.0,
-            ..default()
-        }
-        .into(),
-        ..default()
-    });
+        Sun,
+    ));
 
     commands.spawn(PointLightBundle {
         transform: Transform::from_xyz(0.0, 30.0, -50.0).looking_at(Vec3::ZERO, Vec3::Y),
@@ -370,18 +576,28 @@ fn setup_graphics(
     let mut camera_transform = Transform::from_xyz(0.0, 0.0, 30.0);
     camera_transform.rotate_x(-PI / 40.0);
 
+    let projection = Projection::Orthographic(OrthographicProjection {
+        scale: 8.0,
+        scaling_mode: ScalingMode::FixedVertical(4.0),
+        ..default()
+    });
+
     // Bevy is a right handed, Y-up system.
     commands.spawn((
         Camera3dBundle {
-            tonemapping: Tonemapping::TonyMcMapface,
-            projection: Projection::Orthographic(OrthographicProjection {
-                scale: 8.0,
-                scaling_mode: ScalingMode::FixedVertical(4.0),
+            // Bloom only works in HDR, and glowing things need the bloom
+            camera: Camera {
+                hdr: true,
                 ..default()
-            }),
+            },
+            tonemapping: Tonemapping::TonyMcMapface,
+            projection: projection.clone(),
             transform: camera_transform,
             ..default()
         },
+        CameraController::new(camera_transform, &projection),
+        // The UI has a camera of its own, see `GraphicsPlugin`
+        UiCameraConfig { show_ui: false },
         FogSettings {
             // A greenish blue fog
             color: Color::rgba(0.0, 0.5, 0.8, 1.0),
@@ -393,22 +609,42 @@ fn setup_graphics(
         },
         BloomSettings::default(),
     ));
+}
 
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(
-            shape::Quad {
-                size: Vec2::new(50.0, 30.0),
-                flip: false,
-            }
-            .into(),
-        ),
-        material: materials.add(StandardMaterial {
-            base_color_texture: Some(texture_collection.background.clone()),
+fn setup_game_over(
+    mut commands: Commands,
+    font_collection: Res<FontCollection>,
+    score: Res<Score>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
             ..default()
-        }),
-        transform: Transform::from_xyz(0.0, -5.0, -RADIUS),
-        ..default()
-    });
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Game Over",
+                TextStyle {
+                    font: font_collection.bold.clone(),
+                    font_size: 64.0,
+                    color: Color::WHITE,
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Score: {}", score.points),
+                TextStyle {
+                    font: font_collection.bold.clone(),
+                    font_size: 32.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
 }
 
 #[derive(Component)]
@@ -422,10 +658,14 @@ fn setup_player(
     mut commands: Commands,
     fish_collection: Res<FishCollection>,
     animation_collection: Res<FishAnimationCollection>,
+    bindings: Res<Bindings>,
+    player_species: Res<PlayerSpecies>,
 ) {
-    let fish_type = FishType::Turtle;
-    // Scale up by two cause he's smol
-    let transform = Transform::from_xyz(0.0, 0.0, 0.01).with_scale(Vec3::splat(2.0));
+    let fish_type = player_species.fish_type;
+    // Every species is scaled to the same length so that none of them is easier to hit
+    let scale = PLAYER_LENGTH / fish_type.body_length();
+    let hitbox = Hitbox::new(PLAYER_LENGTH * 0.4);
+    let transform = Transform::from_xyz(0.0, 0.0, 0.01).with_scale(Vec3::splat(scale));
     let fish_animations = fish_type.animations_from(&animation_collection);
     let idle_animation = fish_animations.idle;
 
@@ -442,10 +682,19 @@ fn setup_player(
                 ..default()
             },
         },
+        hitbox,
+        BodyScale::new(scale, hitbox),
+        HitGuard::default(),
+        Health::default(),
+        Abilities::default(),
+        Magnet::default(),
+        Drift::default(),
+        Interpolated::from(transform),
         PlayerBundle {
             player: Player::default(),
+            step_input: StepInput::default(),
             input_manager: InputManagerBundle {
-                input_map: PlayerBundle::default_input_map(),
+                input_map: bindings.input_map.clone(),
                 ..default()
             },
         },
@@ -497,32 +746,72 @@ fn update_bounds(
 ) {
     let (camera_transform, projection) = camera_projection.single();
     let resolution = &window.single().resolution;
-    let camera_transform = camera_transform.translation;
-    let aspect_ratio = resolution.width() / resolution.height();
-    let (horizontal_view, vertical_view) =
-        if let Projection::Orthographic(orthographic) = projection {
-            let scale = orthographic.scale;
-            let (fixed_dim, other_dim) =
-                if let ScalingMode::FixedVertical(vertical) = orthographic.scaling_mode {
-                    ((vertical * aspect_ratio), vertical)
-                } else if let ScalingMode::FixedHorizontal(horizontal) = orthographic.scaling_mode {
-                    (horizontal, (horizontal / aspect_ratio))
-                } else {
-                    unimplemented!()
-                };
-            (fixed_dim * scale, other_dim * scale)
-        } else {
-            unimplemented!()
-        };
+    let viewport_size = Vec2::new(resolution.width(), resolution.height());
 
-    bounds.min = Vec2::new(
-        camera_transform.x - horizontal_view / 2.0,
-        camera_transform.y - vertical_view / 2.0,
-    );
-    bounds.max = Vec2::new(
-        camera_transform.x + horizontal_view / 2.0,
-        camera_transform.y + vertical_view / 2.0,
+    if let Some(visible_area) = visible_play_area(camera_transform, projection, viewport_size) {
+        *bounds = visible_area;
+    }
+}
+
+/// The corners of the screen in normalized device coordinates, counter-clockwise from the bottom
+/// left
+const NDC_CORNERS: [Vec2; 4] = [
+    Vec2::new(-1.0, -1.0),
+    Vec2::new(1.0, -1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(-1.0, 1.0),
+];
+
+/// The part of the z = 0 play plane that the camera can see. The camera is tilted so that area
+/// isn't quite a rectangle, so this is the largest axis-aligned rectangle that fits inside it.
+/// Works for any projection and scaling mode. Returns `None` if the plane is out of view.
+pub fn visible_play_area(
+    camera_transform: &Transform,
+    projection: &Projection,
+    viewport_size: Vec2,
+) -> Option<Bounds> {
+    if viewport_size.x <= 0.0 || viewport_size.y <= 0.0 {
+        return None;
+    }
+
+    let mut projection = projection.clone();
+    projection.update(viewport_size.x, viewport_size.y);
+
+    let ndc_to_world =
+        camera_transform.compute_matrix() * projection.get_projection_matrix().inverse();
+    let mut corners = [Vec2::ZERO; 4];
+
+    for (corner, ndc) in corners.iter_mut().zip(NDC_CORNERS) {
+        // Depth is reversed so 1 is the near plane. Perspective projections are infinite so a
+        // depth of 0 would be infinitely far away, anything in between will do for the ray.
+        let near = ndc_to_world.project_point3(ndc.extend(1.0));
+        let far = ndc_to_world.project_point3(ndc.extend(0.5));
+
+        *corner = intersect_play_plane(near, far)?;
+    }
+
+    let [bottom_left, bottom_right, top_right, top_left] = corners;
+    let min = Vec2::new(
+        bottom_left.x.max(top_left.x),
+        bottom_left.y.max(bottom_right.y),
     );
+    let max = Vec2::new(bottom_right.x.min(top_right.x), top_left.y.min(top_right.y));
+
+    (min.x < max.x && min.y < max.y).then_some(Bounds { min, max })
+}
+
+/// Where the ray from `near` through `far` hits the z = 0 plane, if it does in front of the
+/// camera
+pub fn intersect_play_plane(near: Vec3, far: Vec3) -> Option<Vec2> {
+    let direction = far - near;
+
+    if direction.z.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let t = -near.z / direction.z;
+
+    (t >= 0.0).then(|| (near + direction * t).truncate())
 }
 
 fn constrain_to_bounds(mut query: Query<&mut Transform, With<Player>>, bounds: Res<Bounds>) {
@@ -562,3 +851,106 @@ pub fn play_initial_animations(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-3;
+
+    const LANDSCAPE: Vec2 = Vec2::new(800.0, 600.0);
+    const WIDESCREEN: Vec2 = Vec2::new(1600.0, 900.0);
+    const PORTRAIT: Vec2 = Vec2::new(600.0, 800.0);
+
+    /// Looking straight down the z axis at the play plane, so the visible area is a rectangle
+    fn camera_transform() -> Transform {
+        Transform::from_xyz(0.0, 0.0, 10.0)
+    }
+
+    fn orthographic(scaling_mode: ScalingMode) -> Projection {
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode,
+            ..default()
+        })
+    }
+
+    fn assert_half_size(projection: &Projection, viewport_size: Vec2, half_size: Vec2) {
+        let bounds = visible_play_area(&camera_transform(), projection, viewport_size)
+            .expect("the play plane should be in view");
+
+        assert!(
+            bounds.min.abs_diff_eq(-half_size, TOLERANCE)
+                && bounds.max.abs_diff_eq(half_size, TOLERANCE),
+            "expected +-{half_size} for {viewport_size}, got {bounds:?}"
+        );
+    }
+
+    #[test]
+    fn fixed_vertical_keeps_the_height() {
+        let projection = orthographic(ScalingMode::FixedVertical(12.0));
+
+        assert_half_size(&projection, LANDSCAPE, Vec2::new(8.0, 6.0));
+        assert_half_size(&projection, WIDESCREEN, Vec2::new(32.0 / 3.0, 6.0));
+        assert_half_size(&projection, PORTRAIT, Vec2::new(4.5, 6.0));
+    }
+
+    #[test]
+    fn window_size_scales_with_the_window() {
+        // 100 pixels to a world unit
+        let projection = orthographic(ScalingMode::WindowSize(100.0));
+
+        assert_half_size(&projection, LANDSCAPE, Vec2::new(4.0, 3.0));
+        assert_half_size(&projection, WIDESCREEN, Vec2::new(8.0, 4.5));
+        assert_half_size(&projection, PORTRAIT, Vec2::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn auto_shows_at_least_the_minimum() {
+        let projection = orthographic(ScalingMode::Auto {
+            min_width: 16.0,
+            min_height: 12.0,
+        });
+
+        assert_half_size(&projection, LANDSCAPE, Vec2::new(8.0, 6.0));
+        assert_half_size(&projection, WIDESCREEN, Vec2::new(32.0 / 3.0, 6.0));
+        assert_half_size(&projection, PORTRAIT, Vec2::new(8.0, 32.0 / 3.0));
+    }
+
+    #[test]
+    fn perspective_widens_with_the_aspect_ratio() {
+        let projection = Projection::Perspective(PerspectiveProjection::default());
+        let fov = PerspectiveProjection::default().fov;
+        let half_height = 10.0 * (fov / 2.0).tan();
+
+        for viewport_size in [LANDSCAPE, WIDESCREEN, PORTRAIT] {
+            let aspect_ratio = viewport_size.x / viewport_size.y;
+
+            assert_half_size(
+                &projection,
+                viewport_size,
+                Vec2::new(half_height * aspect_ratio, half_height),
+            );
+        }
+    }
+
+    #[test]
+    fn nothing_is_visible_when_facing_away() {
+        let projection = Projection::Perspective(PerspectiveProjection::default());
+        let camera_transform = camera_transform().looking_at(Vec3::new(0.0, 0.0, 20.0), Vec3::Y);
+
+        assert_eq!(
+            visible_play_area(&camera_transform, &projection, LANDSCAPE),
+            None
+        );
+    }
+
+    #[test]
+    fn an_empty_viewport_has_no_bounds() {
+        let projection = orthographic(ScalingMode::FixedVertical(12.0));
+
+        assert_eq!(
+            visible_play_area(&camera_transform(), &projection, Vec2::ZERO),
+            None
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, scene::SceneInstance};
+
+use crate::{
+    daynight::{Glow, GLOW_INTENSITY},
+    Fish, GameState, SimulationSet,
+};
+
+// Tweaks to the materials that come out of the glb files. The scene's materials are copied once
+// it has finished spawning, so overriding one model doesn't change every other copy of it.
+pub struct MaterialOverridePlugin;
+
+impl Plugin for MaterialOverridePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                add_species_overrides,
+                apply_material_overrides,
+                update_material_overrides,
+            )
+                .chain()
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        );
+    }
+}
+
+/// Goes on the root of a `SceneBundle`
+#[derive(Component, Debug, Copy, Clone, PartialEq, Default)]
+pub struct MaterialOverride {
+    /// Multiplied into the base color
+    pub tint: Option<Color>,
+
+    pub emissive: Option<Color>,
+
+    /// How many times brighter than `emissive` the light given off is
+    pub emissive_strength: f32,
+
+    /// Only gives off light as strongly as the current `Glow`, so it lights up at night and in
+    /// the deep
+    pub glows: bool,
+
+    pub roughness: Option<f32>,
+}
+
+impl MaterialOverride {
+    /// Bioluminescence
+    pub fn glow(color: Color) -> MaterialOverride {
+        MaterialOverride {
+            emissive: Some(color),
+            emissive_strength: GLOW_INTENSITY,
+            glows: true,
+            ..default()
+        }
+    }
+
+    /// Applies the override on top of the material from the scene
+    pub fn apply(&self, material: &mut StandardMaterial, glow: f32) {
+        if let Some(tint) = self.tint {
+            let tinted =
+                Vec4::from(material.base_color.as_rgba_f32()) * Vec4::from(tint.as_rgba_f32());
+            material.base_color = Color::from(tinted);
+        }
+
+        if let Some(emissive) = self.emissive {
+            let strength = if self.glows {
+                self.emissive_strength * glow
+            } else {
+                self.emissive_strength
+            };
+
+            material.emissive = emissive * strength;
+        }
+
+        if let Some(roughness) = self.roughness {
+            material.perceptual_roughness = roughness;
+        }
+    }
+}
+
+/// The copies made of a scene's materials, next to the materials they were copied from
+#[derive(Component, Debug)]
+pub struct OverriddenMaterials {
+    pub materials: Vec<(Handle<StandardMaterial>, Handle<StandardMaterial>)>,
+}
+
+fn add_species_overrides(mut commands: Commands, query: Query<(Entity, &Fish), Added<Fish>>) {
+    for (entity, fish) in query.iter() {
+        if let Some(color) = fish.fish_type.glow() {
+            commands
+                .entity(entity)
+                .insert(MaterialOverride::glow(color));
+        }
+    }
+}
+
+/// Waits for the scene to finish spawning, then swaps every material in it for an overridden
+/// copy
+fn apply_material_overrides(
+    mut commands: Commands,
+    query: Query<(Entity, &SceneInstance, &MaterialOverride), Without<OverriddenMaterials>>,
+    children_query: Query<&Children>,
+    mut material_query: Query<&mut Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scene_spawner: Res<SceneSpawner>,
+    glow: Res<Glow>,
+) {
+    for (entity, instance, material_override) in query.iter() {
+        if !scene_spawner.instance_is_ready(**instance) {
+            continue;
+        }
+
+        // Meshes that shared a material keep sharing the copy
+        let mut copies = HashMap::<Handle<StandardMaterial>, Handle<StandardMaterial>>::new();
+
+        for descendant in children_query.iter_descendants(entity) {
+            let Ok(mut handle) = material_query.get_mut(descendant) else {
+                continue;
+            };
+
+            let copy = copies
+                .entry(handle.clone())
+                .or_insert_with(|| {
+                    let mut material = materials.get(&handle).cloned().unwrap_or_default();
+                    material_override.apply(&mut material, glow.strength);
+                    materials.add(material)
+                })
+                .clone();
+
+            *handle = copy;
+        }
+
+        commands.entity(entity).insert(OverriddenMaterials {
+            materials: copies.into_iter().collect(),
+        });
+    }
+}
+
+/// Redoes the copies when the override changes, or when the glow changes for ones that glow
+fn update_material_overrides(
+    query: Query<(Ref<MaterialOverride>, &OverriddenMaterials)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    glow: Res<Glow>,
+) {
+    for (material_override, overridden) in query.iter() {
+        if !material_override.is_changed() && !(material_override.glows && glow.is_changed()) {
+            continue;
+        }
+
+        for (original, copy) in overridden.materials.iter() {
+            let Some(mut material) = materials.get(original).cloned() else {
+                continue;
+            };
+
+            material_override.apply(&mut material, glow.strength);
+
+            if let Some(copy) = materials.get_mut(copy) {
+                *copy = material;
+            }
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    collectible::CollectiblePickedUp,
+    collision::PlayerHitEvent,
+    fishy_assets::FishType,
+    input::{MovementState, Player},
+    powerup::PowerUpPickedUp,
+    Bounds, Fish, GameState, SimulationSet,
+};
+
+// Bubbles, silt and marine snow. Particles are plain entities taken from a fixed pool so that
+// nothing is spawned or despawned while playing, which keeps the web build smooth.
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticleBurst>()
+            .add_system(setup_particle_pool.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (
+                    attach_emitters,
+                    toggle_player_bubbles,
+                    burst_on_hits,
+                    burst_on_pickups,
+                    emit_marine_snow,
+                    run_emitters,
+                    spawn_bursts,
+                    update_particles,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const PARTICLE_CAPACITY: usize = 600;
+const MARINE_SNOW_PER_SECOND: f32 = 6.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParticleKind {
+    Bubble,
+    Silt,
+    Spark,
+    Snow,
+}
+
+const PARTICLE_KINDS: [ParticleKind; 4] = [
+    ParticleKind::Bubble,
+    ParticleKind::Silt,
+    ParticleKind::Spark,
+    ParticleKind::Snow,
+];
+
+impl ParticleKind {
+    fn color(&self) -> Color {
+        match self {
+            ParticleKind::Bubble => Color::rgba(0.85, 0.95, 1.0, 0.6),
+            ParticleKind::Silt => Color::rgba(0.55, 0.45, 0.3, 0.5),
+            ParticleKind::Spark => Color::rgba(1.0, 0.9, 0.5, 0.9),
+            ParticleKind::Snow => Color::rgba(0.9, 0.9, 0.85, 0.5),
+        }
+    }
+
+    fn lifetime(&self) -> f32 {
+        match self {
+            ParticleKind::Bubble => 1.6,
+            ParticleKind::Silt => 1.2,
+            ParticleKind::Spark => 0.6,
+            ParticleKind::Snow => 10.0,
+        }
+    }
+
+    fn size(&self) -> f32 {
+        match self {
+            ParticleKind::Bubble => 0.12,
+            ParticleKind::Silt => 0.2,
+            ParticleKind::Spark => 0.08,
+            ParticleKind::Snow => 0.04,
+        }
+    }
+
+    /// Upwards acceleration. Bubbles float, silt settles.
+    fn buoyancy(&self) -> f32 {
+        match self {
+            ParticleKind::Bubble => 2.5,
+            ParticleKind::Silt => -0.3,
+            ParticleKind::Spark => 0.0,
+            ParticleKind::Snow => 0.0,
+        }
+    }
+
+    /// How much velocity is lost per second
+    fn drag(&self) -> f32 {
+        match self {
+            ParticleKind::Bubble => 1.5,
+            ParticleKind::Silt => 2.0,
+            ParticleKind::Spark => 3.0,
+            ParticleKind::Snow => 0.0,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Particle {
+    pub kind: ParticleKind,
+
+    pub velocity: Vec3,
+
+    pub age: f32,
+
+    pub lifetime: f32,
+
+    /// Sideways wobble so bubbles don't rise in a dead straight line
+    pub wobble_phase: f32,
+
+    active: bool,
+}
+
+/// The particles that aren't in use
+#[derive(Resource)]
+pub struct ParticlePool {
+    free: Vec<Entity>,
+
+    materials: Vec<(ParticleKind, Handle<StandardMaterial>)>,
+}
+
+impl ParticlePool {
+    fn material(&self, kind: ParticleKind) -> Handle<StandardMaterial> {
+        self.materials
+            .iter()
+            .find(|(material_kind, _)| *material_kind == kind)
+            .map(|(_, material)| material.clone())
+            .unwrap()
+    }
+}
+
+/// Emits particles from wherever the entity is, at a steady rate while enabled
+#[derive(Component, Debug)]
+pub struct ParticleEmitter {
+    pub kind: ParticleKind,
+
+    /// Particles per second
+    pub rate: f32,
+
+    /// Where particles come from, relative to the entity
+    pub offset: Vec3,
+
+    /// The speed particles leave at, in a random direction
+    pub spread: f32,
+
+    pub enabled: bool,
+
+    accumulated: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(kind: ParticleKind, rate: f32, offset: Vec3, spread: f32) -> ParticleEmitter {
+        ParticleEmitter {
+            kind,
+            rate,
+            offset,
+            spread,
+            enabled: true,
+            accumulated: 0.0,
+        }
+    }
+}
+
+/// A one-off puff of particles
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ParticleBurst {
+    pub kind: ParticleKind,
+
+    pub position: Vec3,
+
+    pub count: usize,
+
+    pub speed: f32,
+}
+
+fn setup_particle_pool(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(
+        shape::UVSphere {
+            radius: 1.0,
+            sectors: 6,
+            stacks: 4,
+        }
+        .into(),
+    );
+    let particle_materials = PARTICLE_KINDS
+        .iter()
+        .map(|kind| {
+            let material = materials.add(StandardMaterial {
+                base_color: kind.color(),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            });
+
+            (*kind, material)
+        })
+        .collect::<Vec<_>>();
+
+    let free = (0..PARTICLE_CAPACITY)
+        .map(|_| {
+            commands
+                .spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: particle_materials[0].1.clone(),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    Particle {
+                        kind: ParticleKind::Bubble,
+                        velocity: Vec3::ZERO,
+                        age: 0.0,
+                        lifetime: 0.0,
+                        wobble_phase: 0.0,
+                        active: false,
+                    },
+                ))
+                .id()
+        })
+        .collect();
+
+    commands.insert_resource(ParticlePool {
+        free,
+        materials: particle_materials,
+    });
+}
+
+/// Takes a particle out of the pool and sets it going. Does nothing if the pool has run dry.
+fn emit(
+    pool: &mut ParticlePool,
+    particle_query: &mut Query<(
+        &mut Particle,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
+    kind: ParticleKind,
+    position: Vec3,
+    velocity: Vec3,
+) {
+    let Some(entity) = pool.free.pop() else {
+        return;
+    };
+    let Ok((mut particle, mut transform, mut visibility, mut material)) =
+        particle_query.get_mut(entity)
+    else {
+        return;
+    };
+
+    let mut rng = rand::thread_rng();
+
+    *particle = Particle {
+        kind,
+        velocity,
+        age: 0.0,
+        lifetime: kind.lifetime() * rng.gen_range(0.7..1.3),
+        wobble_phase: rng.gen_range(0.0..TAU),
+        active: true,
+    };
+    *transform = Transform::from_translation(position)
+        .with_scale(Vec3::splat(kind.size() * rng.gen_range(0.6..1.4)));
+    *visibility = Visibility::Inherited;
+    *material = pool.material(kind);
+}
+
+fn random_direction(rng: &mut impl Rng) -> Vec3 {
+    let angle = rng.gen_range(0.0..TAU);
+
+    Vec3::new(angle.cos(), angle.sin(), rng.gen_range(-0.3..0.3))
+}
+
+/// Players trail bubbles and crabs kick up silt
+fn attach_emitters(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    fish_query: Query<(Entity, &Fish), (Added<Fish>, Without<Player>)>,
+) {
+    for entity in player_query.iter() {
+        commands.entity(entity).insert(ParticleEmitter::new(
+            ParticleKind::Bubble,
+            8.0,
+            Vec3::ZERO,
+            0.3,
+        ));
+    }
+
+    for (entity, fish) in fish_query.iter() {
+        if matches!(fish.fish_type, FishType::Crab) {
+            commands.entity(entity).insert(ParticleEmitter::new(
+                ParticleKind::Silt,
+                5.0,
+                Vec3::new(0.0, -0.3, 0.0),
+                0.6,
+            ));
+        }
+    }
+}
+
+fn toggle_player_bubbles(mut query: Query<(&Player, &mut ParticleEmitter)>) {
+    for (player, mut emitter) in query.iter_mut() {
+        let moving = matches!(player.state(), MovementState::Moving { .. });
+
+        if emitter.enabled != moving {
+            emitter.enabled = moving;
+        }
+    }
+}
+
+fn burst_on_hits(
+    mut player_hit_events: EventReader<PlayerHitEvent>,
+    player_query: Query<&Transform>,
+    mut particle_bursts: EventWriter<ParticleBurst>,
+) {
+    for PlayerHitEvent { player, .. } in player_hit_events.iter() {
+        let Ok(transform) = player_query.get(*player) else {
+            continue;
+        };
+
+        particle_bursts.send(ParticleBurst {
+            kind: ParticleKind::Bubble,
+            position: transform.translation,
+            count: 24,
+            speed: 3.0,
+        });
+    }
+}
+
+fn burst_on_pickups(
+    mut collectible_picked_up_events: EventReader<CollectiblePickedUp>,
+    mut power_up_picked_up_events: EventReader<PowerUpPickedUp>,
+    mut particle_bursts: EventWriter<ParticleBurst>,
+) {
+    let positions = collectible_picked_up_events
+        .iter()
+        .map(|event| event.position)
+        .chain(power_up_picked_up_events.iter().map(|event| event.position));
+
+    for position in positions {
+        particle_bursts.send(ParticleBurst {
+            kind: ParticleKind::Spark,
+            position,
+            count: 12,
+            speed: 2.0,
+        });
+    }
+}
+
+/// Specks of falling debris that drift down through the whole screen
+fn emit_marine_snow(
+    mut particle_bursts: EventWriter<ParticleBurst>,
+    bounds: Res<Bounds>,
+    time: Res<Time>,
+    mut accumulated: Local<f32>,
+) {
+    if bounds.max.x <= bounds.min.x {
+        return;
+    }
+
+    *accumulated += MARINE_SNOW_PER_SECOND * time.delta_seconds();
+
+    let mut rng = rand::thread_rng();
+    while *accumulated >= 1.0 {
+        *accumulated -= 1.0;
+
+        let x = rng.gen_range(bounds.min.x..bounds.max.x);
+        let z = rng.gen_range(-4.0..2.0);
+
+        particle_bursts.send(ParticleBurst {
+            kind: ParticleKind::Snow,
+            position: Vec3::new(x, bounds.max.y + 1.0, z),
+            count: 1,
+            speed: 0.0,
+        });
+    }
+}
+
+fn run_emitters(
+    mut emitter_query: Query<(&GlobalTransform, &mut ParticleEmitter)>,
+    mut particle_bursts: EventWriter<ParticleBurst>,
+    time: Res<Time>,
+) {
+    for (transform, mut emitter) in emitter_query.iter_mut() {
+        if !emitter.enabled {
+            emitter.accumulated = 0.0;
+            continue;
+        }
+
+        emitter.accumulated += emitter.rate * time.delta_seconds();
+
+        let count = emitter.accumulated.floor();
+        if count < 1.0 {
+            continue;
+        }
+
+        emitter.accumulated -= count;
+        particle_bursts.send(ParticleBurst {
+            kind: emitter.kind,
+            position: transform.translation() + emitter.offset,
+            count: count as usize,
+            speed: emitter.spread,
+        });
+    }
+}
+
+fn spawn_bursts(
+    mut particle_bursts: EventReader<ParticleBurst>,
+    mut particle_query: Query<(
+        &mut Particle,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
+    pool: Option<ResMut<ParticlePool>>,
+) {
+    let Some(mut pool) = pool else {
+        return;
+    };
+
+    let mut rng = rand::thread_rng();
+
+    for burst in particle_bursts.iter() {
+        for _ in 0..burst.count {
+            let velocity = match burst.kind {
+                // Snow just sinks
+                ParticleKind::Snow => Vec3::new(0.0, -rng.gen_range(0.2..0.5), 0.0),
+                _ => random_direction(&mut rng) * burst.speed * rng.gen_range(0.5..1.0),
+            };
+
+            emit(
+                &mut pool,
+                &mut particle_query,
+                burst.kind,
+                burst.position,
+                velocity,
+            );
+        }
+    }
+}
+
+fn update_particles(
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Visibility)>,
+    pool: Option<ResMut<ParticlePool>>,
+    time: Res<Time>,
+) {
+    let Some(mut pool) = pool else {
+        return;
+    };
+
+    let delta_seconds = time.delta_seconds();
+
+    for (entity, mut particle, mut transform, mut visibility) in query.iter_mut() {
+        if !particle.active {
+            continue;
+        }
+
+        particle.age += delta_seconds;
+
+        if particle.age >= particle.lifetime {
+            particle.active = false;
+            *visibility = Visibility::Hidden;
+            pool.free.push(entity);
+            continue;
+        }
+
+        let kind = particle.kind;
+        let drag = (1.0 - kind.drag() * delta_seconds).max(0.0);
+        particle.velocity = particle.velocity * drag + Vec3::Y * kind.buoyancy() * delta_seconds;
+
+        let wobble = if kind == ParticleKind::Bubble {
+            (particle.age * 8.0 + particle.wobble_phase).sin() * 0.3
+        } else {
+            0.0
+        };
+
+        transform.translation += (particle.velocity + Vec3::X * wobble) * delta_seconds;
+
+        // Shrink away towards the end instead of fading so the materials can be shared
+        let remaining = 1.0 - particle.age / particle.lifetime;
+        if remaining < 0.25 {
+            transform.scale *= 1.0 - (1.0 - remaining / 0.25).min(1.0) * delta_seconds * 4.0;
+        }
+    }
+}
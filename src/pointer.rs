@@ -0,0 +1,264 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    camera::CameraController,
+    input::{read_step_input, Player},
+    intersect_play_plane, GameState, SimulationSet,
+};
+
+// Steering with the mouse or a finger, mostly for the web canvas. Holding the mouse button has
+// the player swim towards the pointer. On touch screens a touch places a virtual joystick under
+// the finger, or the player follows the finger if the joystick is turned off.
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PointerSteering>()
+            .init_resource::<PointerSettings>()
+            .init_resource::<VirtualJoystick>()
+            .add_system(setup_virtual_joystick.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (steer_with_pointer, update_virtual_joystick)
+                    .chain()
+                    .before(read_step_input)
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Input),
+            );
+    }
+}
+
+/// Closer to the pointer than this, in world units, and the player stops
+const STOP_DISTANCE: f32 = 0.3;
+
+/// Further from the pointer than this, in world units, and the player swims at full speed
+const FULL_SPEED_DISTANCE: f32 = 2.0;
+
+/// How far the joystick's knob can be dragged from its center, in logical pixels
+const JOYSTICK_RADIUS: f32 = 60.0;
+
+const KNOB_SIZE: f32 = 48.0;
+
+#[derive(Resource, Debug)]
+pub struct PointerSettings {
+    /// Touches steer with a virtual joystick instead of having the player follow the finger
+    pub touch_joystick: bool,
+}
+
+impl Default for PointerSettings {
+    fn default() -> PointerSettings {
+        PointerSettings {
+            touch_joystick: true,
+        }
+    }
+}
+
+/// Which way and how hard the mouse or a finger is steering the player, `None` while nothing
+/// is steering
+#[derive(Resource, Debug, Default)]
+pub struct PointerSteering {
+    pub direction: Option<Vec2>,
+}
+
+/// The touch the joystick follows, and where that touch started, in window coordinates
+#[derive(Resource, Debug, Default)]
+struct VirtualJoystick {
+    touch: Option<u64>,
+
+    center: Vec2,
+
+    knob: Vec2,
+}
+
+#[derive(Component, Debug)]
+struct JoystickBase;
+
+#[derive(Component, Debug)]
+struct JoystickKnob;
+
+fn setup_virtual_joystick(mut commands: Commands) {
+    let diameter = JOYSTICK_RADIUS * 2.0;
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Px(diameter), Val::Px(diameter)),
+                ..default()
+            },
+            background_color: Color::rgba(1.0, 1.0, 1.0, 0.15).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        JoystickBase,
+    ));
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Px(KNOB_SIZE), Val::Px(KNOB_SIZE)),
+                ..default()
+            },
+            background_color: Color::rgba(1.0, 1.0, 1.0, 0.4).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        JoystickKnob,
+    ));
+}
+
+/// Where on the play plane a point on the window is. The camera might be drawing at a lower
+/// resolution than the window, so the point is scaled to fit its viewport first.
+fn pointer_to_world(
+    position: Vec2,
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let viewport_size = camera.logical_viewport_size()?;
+    let scale = viewport_size / Vec2::new(window.width(), window.height());
+    let ray = camera.viewport_to_world(camera_transform, position * scale)?;
+
+    intersect_play_plane(ray.origin, ray.get_point(1.0))
+}
+
+/// Touches count down from the top of the window, except on mobile where they're flipped to count
+/// up from the bottom like the cursor does. This flips the rest to match.
+fn touch_to_window(position: Vec2, window: &Window) -> Vec2 {
+    if cfg!(any(target_os = "android", target_os = "ios")) {
+        position
+    } else {
+        Vec2::new(position.x, window.height() - position.y)
+    }
+}
+
+/// Heads towards `target`, easing off as it gets close
+fn follow(player: Vec2, target: Vec2) -> Vec2 {
+    let offset = target - player;
+    let distance = offset.length();
+
+    if distance < STOP_DISTANCE {
+        return Vec2::ZERO;
+    }
+
+    offset / distance * ((distance - STOP_DISTANCE) / FULL_SPEED_DISTANCE).min(1.0)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn steer_with_pointer(
+    mut steering: ResMut<PointerSteering>,
+    mut joystick: ResMut<VirtualJoystick>,
+    settings: Res<PointerSettings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    player_query: Query<&Transform, With<Player>>,
+    interaction_query: Query<&Interaction>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+) {
+    steering.direction = None;
+
+    let (Ok(window), Ok((camera, camera_transform)), Ok(player_transform)) = (
+        window_query.get_single(),
+        camera_query.get_single(),
+        player_query.get_single(),
+    ) else {
+        return;
+    };
+    let player = player_transform.translation.truncate();
+
+    // The joystick keeps hold of its touch until it lifts
+    if let Some(touch) = joystick.touch.and_then(|id| touches.get_pressed(id)) {
+        let position = touch_to_window(touch.position(), window);
+        let drag = (position - joystick.center).clamp_length_max(JOYSTICK_RADIUS);
+
+        joystick.knob = joystick.center + drag;
+        steering.direction = Some(drag / JOYSTICK_RADIUS);
+        return;
+    }
+
+    if joystick.touch.is_some() {
+        joystick.touch = None;
+    }
+
+    // Clicking or tapping on the UI, say the settings menu, shouldn't steer
+    if interaction_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if let Some(touch) = touches.iter().next() {
+        if settings.touch_joystick {
+            joystick.touch = Some(touch.id());
+            joystick.center = touch_to_window(touch.start_position(), window);
+            joystick.knob = touch_to_window(touch.position(), window);
+            steering.direction = Some(Vec2::ZERO);
+        } else if let Some(target) = pointer_to_world(
+            touch_to_window(touch.position(), window),
+            window,
+            camera,
+            camera_transform,
+        ) {
+            steering.direction = Some(follow(player, target));
+        }
+        return;
+    }
+
+    if !mouse_buttons.pressed(MouseButton::Left) {
+        return;
+    }
+
+    if let Some(target) = window
+        .cursor_position()
+        .and_then(|cursor| pointer_to_world(cursor, window, camera, camera_transform))
+    {
+        steering.direction = Some(follow(player, target));
+    }
+}
+
+/// Shows the joystick where the touch started, with the knob under the finger
+#[allow(clippy::type_complexity)]
+fn update_virtual_joystick(
+    joystick: Res<VirtualJoystick>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut base_query: Query<
+        (&mut Style, &mut Visibility),
+        (With<JoystickBase>, Without<JoystickKnob>),
+    >,
+    mut knob_query: Query<(&mut Style, &mut Visibility), With<JoystickKnob>>,
+) {
+    if !joystick.is_changed() {
+        return;
+    }
+
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let visibility = if joystick.touch.is_some() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    // Window positions count up from the bottom, UI positions down from the top
+    let place = |style: &mut Style, center: Vec2, size: f32| {
+        style.position = UiRect {
+            left: Val::Px(center.x - size / 2.0),
+            top: Val::Px(window.height() - center.y - size / 2.0),
+            ..default()
+        };
+    };
+
+    for (mut style, mut base_visibility) in base_query.iter_mut() {
+        place(&mut style, joystick.center, JOYSTICK_RADIUS * 2.0);
+        *base_visibility = visibility;
+    }
+
+    for (mut style, mut knob_visibility) in knob_query.iter_mut() {
+        place(&mut style, joystick.knob, KNOB_SIZE);
+        *knob_visibility = visibility;
+    }
+}
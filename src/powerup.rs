@@ -0,0 +1,630 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+use rand::Rng;
+
+use crate::{
+    collectible::{Bobbing, Collectible},
+    collision::{BodyScale, HitGuard, Hitbox},
+    fishy_assets::{AudioCollection, FontCollection},
+    hazard::HazardTimeScale,
+    hud::EffectStrip,
+    input::Player,
+    rng::GameRng,
+    settings::AudioSettings,
+    Bounds, GameState, SimulationSet,
+};
+
+// Pickups that grant the player timed effects
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerUpSpawnTimer>()
+            .add_event::<PowerUpPickedUp>()
+            .add_system(setup_power_up_assets.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (
+                    tick_power_up_spawn_timer,
+                    spawn_power_up,
+                    expire_power_ups,
+                    pick_up_power_ups,
+                    update_effects,
+                    attract_collectibles,
+                    spawn_effect_chips,
+                    update_effect_chips,
+                    despawn_effect_chips,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    Shield,
+    SpeedBoost,
+    Magnet,
+    SlowMotion,
+    Shrink,
+}
+
+const POWER_UP_KINDS: [PowerUpKind; 5] = [
+    PowerUpKind::Shield,
+    PowerUpKind::SpeedBoost,
+    PowerUpKind::Magnet,
+    PowerUpKind::SlowMotion,
+    PowerUpKind::Shrink,
+];
+
+const SPEED_BOOST_MULTIPLIER: f32 = 1.5;
+const SLOW_MOTION_SCALE: f32 = 0.5;
+const SHRINK_MULTIPLIER: f32 = 0.6;
+const MAGNET_RADIUS: f32 = 5.0;
+const MAGNET_PULL_SPEED: f32 = 6.0;
+/// Seconds a power-up floats around before it's gone, so missed ones don't pile up off screen
+const POWER_UP_LIFETIME: f32 = 15.0;
+
+impl PowerUpKind {
+    pub fn duration(&self) -> f32 {
+        match self {
+            PowerUpKind::Shield => 6.0,
+            PowerUpKind::SpeedBoost => 8.0,
+            PowerUpKind::Magnet => 10.0,
+            PowerUpKind::SlowMotion => 5.0,
+            PowerUpKind::Shrink => 8.0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::SpeedBoost => "Speed",
+            PowerUpKind::Magnet => "Magnet",
+            PowerUpKind::SlowMotion => "Slow-mo",
+            PowerUpKind::Shrink => "Shrink",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUpKind::Shield => Color::hex("4ea8de").unwrap(),
+            PowerUpKind::SpeedBoost => Color::hex("f9844a").unwrap(),
+            PowerUpKind::Magnet => Color::hex("e63946").unwrap(),
+            PowerUpKind::SlowMotion => Color::hex("9b5de5").unwrap(),
+            PowerUpKind::Shrink => Color::hex("43aa8b").unwrap(),
+        }
+    }
+}
+
+/// A power-up floating in the water, waiting to be picked up
+#[derive(Component, Debug)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+
+    pub lifetime: Timer,
+}
+
+/// A running effect. Effects are their own entities so that several of the same kind can stack,
+/// each one is applied when it's spawned and reverted when it runs out.
+#[derive(Component, Debug)]
+pub struct TimedEffect {
+    pub kind: PowerUpKind,
+
+    pub target: Entity,
+
+    pub timer: Timer,
+
+    applied: bool,
+}
+
+impl TimedEffect {
+    pub fn new(kind: PowerUpKind, target: Entity) -> TimedEffect {
+        TimedEffect {
+            kind,
+            target,
+            timer: Timer::from_seconds(kind.duration(), TimerMode::Once),
+            applied: false,
+        }
+    }
+}
+
+/// Pulls nearby collectibles towards the player while it has any stacks
+#[derive(Component, Debug, Default)]
+pub struct Magnet {
+    pub stacks: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PowerUpPickedUp {
+    pub player: Entity,
+
+    pub kind: PowerUpKind,
+
+    pub position: Vec3,
+}
+
+#[derive(Resource)]
+pub struct PowerUpSpawnTimer {
+    pub timer: Timer,
+}
+
+impl Default for PowerUpSpawnTimer {
+    fn default() -> PowerUpSpawnTimer {
+        PowerUpSpawnTimer {
+            timer: Timer::from_seconds(12.0, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct PowerUpAssets {
+    pub mesh: Handle<Mesh>,
+
+    pub materials: Vec<(PowerUpKind, Handle<StandardMaterial>)>,
+}
+
+impl PowerUpAssets {
+    fn material(&self, kind: PowerUpKind) -> Handle<StandardMaterial> {
+        self.materials
+            .iter()
+            .find(|(material_kind, _)| *material_kind == kind)
+            .map(|(_, material)| material.clone())
+            .unwrap()
+    }
+}
+
+/// The HUD entry for an active effect
+#[derive(Component, Debug)]
+pub struct EffectChip {
+    pub effect: Entity,
+}
+
+fn setup_power_up_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let power_up_materials = POWER_UP_KINDS
+        .iter()
+        .map(|kind| {
+            let material = materials.add(StandardMaterial {
+                base_color: kind.color(),
+                emissive: kind.color(),
+                perceptual_roughness: 0.3,
+                ..default()
+            });
+
+            (*kind, material)
+        })
+        .collect();
+
+    commands.insert_resource(PowerUpAssets {
+        mesh: meshes.add(
+            shape::Icosphere {
+                radius: 0.35,
+                subdivisions: 1,
+            }
+            .try_into()
+            .unwrap(),
+        ),
+        materials: power_up_materials,
+    });
+}
+
+fn tick_power_up_spawn_timer(mut power_up_spawn_timer: ResMut<PowerUpSpawnTimer>, time: Res<Time>) {
+    power_up_spawn_timer.timer.tick(time.delta());
+}
+
+fn spawn_power_up(
+    mut commands: Commands,
+    bounds: Res<Bounds>,
+    power_up_spawn_timer: Res<PowerUpSpawnTimer>,
+    power_up_assets: Res<PowerUpAssets>,
+    game_rng: Res<GameRng>,
+) {
+    if !power_up_spawn_timer.timer.just_finished() {
+        return;
+    }
+
+    const EDGE_PADDING: f32 = 2.0;
+
+    let min = bounds.min + EDGE_PADDING;
+    let max = bounds.max - EDGE_PADDING;
+
+    // Nowhere to put it that's clear of the edges
+    if min.x >= max.x || min.y >= max.y {
+        return;
+    }
+
+    let mut rng = game_rng.stream("power_ups");
+    let kind = POWER_UP_KINDS[rng.gen_range(0..POWER_UP_KINDS.len())];
+    let x = rng.gen_range(min.x..max.x);
+    let y = rng.gen_range(min.y..max.y);
+
+    commands.spawn((
+        PbrBundle {
+            mesh: power_up_assets.mesh.clone(),
+            material: power_up_assets.material(kind),
+            transform: Transform::from_xyz(x, y, 0.0),
+            ..default()
+        },
+        PowerUp {
+            kind,
+            lifetime: Timer::from_seconds(POWER_UP_LIFETIME, TimerMode::Once),
+        },
+        Bobbing {
+            base_y: y,
+            elapsed: 0.0,
+            amplitude: 0.2,
+            frequency: 0.75,
+            phase: rng.gen_range(0.0..TAU),
+            spin_speed: 2.0,
+        },
+        Hitbox::new(0.5),
+    ));
+}
+
+fn expire_power_ups(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut PowerUp)>,
+    time: Res<Time>,
+) {
+    for (entity, mut power_up) in query.iter_mut() {
+        if power_up.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn pick_up_power_ups(
+    mut commands: Commands,
+    power_up_query: Query<(Entity, &Transform, &Hitbox, &PowerUp)>,
+    player_query: Query<(Entity, &Transform, &Hitbox), With<Player>>,
+    mut power_up_picked_up_events: EventWriter<PowerUpPickedUp>,
+    audio: Res<Audio>,
+    audio_collection: Res<AudioCollection>,
+    audio_settings: Res<AudioSettings>,
+) {
+    for (entity, transform, hitbox, power_up) in power_up_query.iter() {
+        for (player, player_transform, player_hitbox) in player_query.iter() {
+            if !hitbox.overlaps(
+                transform.translation,
+                player_hitbox,
+                player_transform.translation,
+            ) {
+                continue;
+            }
+
+            commands.spawn(TimedEffect::new(power_up.kind, player));
+            commands.entity(entity).despawn_recursive();
+            audio
+                .play(audio_collection.pickup.clone())
+                .with_volume(audio_settings.effects);
+            power_up_picked_up_events.send(PowerUpPickedUp {
+                player,
+                kind: power_up.kind,
+                position: transform.translation,
+            });
+            break;
+        }
+    }
+}
+
+/// Applies or reverts a single stack of an effect
+fn modify(
+    kind: PowerUpKind,
+    apply: bool,
+    player: &mut Player,
+    guard: &mut HitGuard,
+    body_scale: &mut BodyScale,
+    magnet: &mut Magnet,
+    hazard_time_scale: &mut HazardTimeScale,
+) {
+    match (kind, apply) {
+        (PowerUpKind::Shield, true) => guard.shields += 1,
+        (PowerUpKind::Shield, false) => guard.shields = guard.shields.saturating_sub(1),
+        (PowerUpKind::SpeedBoost, true) => player.speed_multiplier *= SPEED_BOOST_MULTIPLIER,
+        (PowerUpKind::SpeedBoost, false) => player.speed_multiplier /= SPEED_BOOST_MULTIPLIER,
+        (PowerUpKind::Magnet, true) => magnet.stacks += 1,
+        (PowerUpKind::Magnet, false) => magnet.stacks = magnet.stacks.saturating_sub(1),
+        (PowerUpKind::SlowMotion, true) => hazard_time_scale.scale *= SLOW_MOTION_SCALE,
+        (PowerUpKind::SlowMotion, false) => hazard_time_scale.scale /= SLOW_MOTION_SCALE,
+        (PowerUpKind::Shrink, true) => body_scale.multiplier *= SHRINK_MULTIPLIER,
+        (PowerUpKind::Shrink, false) => body_scale.multiplier /= SHRINK_MULTIPLIER,
+    }
+}
+
+fn update_effects(
+    mut commands: Commands,
+    mut effect_query: Query<(Entity, &mut TimedEffect)>,
+    mut player_query: Query<(&mut Player, &mut HitGuard, &mut BodyScale, &mut Magnet)>,
+    mut hazard_time_scale: ResMut<HazardTimeScale>,
+    time: Res<Time>,
+) {
+    for (entity, mut effect) in effect_query.iter_mut() {
+        let Ok((mut player, mut guard, mut body_scale, mut magnet)) =
+            player_query.get_mut(effect.target)
+        else {
+            // Nothing left to revert
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        if !effect.applied {
+            effect.applied = true;
+            modify(
+                effect.kind,
+                true,
+                &mut player,
+                &mut guard,
+                &mut body_scale,
+                &mut magnet,
+                &mut hazard_time_scale,
+            );
+        }
+
+        if effect.timer.tick(time.delta()).finished() {
+            modify(
+                effect.kind,
+                false,
+                &mut player,
+                &mut guard,
+                &mut body_scale,
+                &mut magnet,
+                &mut hazard_time_scale,
+            );
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn attract_collectibles(
+    player_query: Query<(&Transform, &Magnet), With<Player>>,
+    mut collectible_query: Query<
+        (&mut Transform, &mut Bobbing),
+        (With<Collectible>, Without<Player>),
+    >,
+    time: Res<Time>,
+) {
+    for (player_transform, magnet) in player_query.iter() {
+        if magnet.stacks == 0 {
+            continue;
+        }
+
+        let radius = MAGNET_RADIUS * magnet.stacks as f32;
+        let target = player_transform.translation.truncate();
+
+        for (mut transform, mut bobbing) in collectible_query.iter_mut() {
+            let position = Vec2::new(transform.translation.x, bobbing.base_y);
+            let offset = target - position;
+
+            if offset.length() > radius {
+                continue;
+            }
+
+            let step = offset.clamp_length_max(MAGNET_PULL_SPEED * time.delta_seconds());
+            transform.translation.x += step.x;
+            bobbing.base_y += step.y;
+        }
+    }
+}
+
+fn spawn_effect_chips(
+    mut commands: Commands,
+    effect_query: Query<(Entity, &TimedEffect), Added<TimedEffect>>,
+    strip_query: Query<Entity, With<EffectStrip>>,
+    font_collection: Res<FontCollection>,
+) {
+    let Ok(strip) = strip_query.get_single() else {
+        return;
+    };
+
+    for (entity, effect) in effect_query.iter() {
+        commands.entity(strip).with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: effect.kind.color().into(),
+                        ..default()
+                    },
+                    EffectChip { effect: entity },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        effect.kind.label(),
+                        TextStyle {
+                            font: font_collection.bold.clone(),
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+    }
+}
+
+fn update_effect_chips(
+    chip_query: Query<(&EffectChip, &Children)>,
+    mut text_query: Query<&mut Text>,
+    effect_query: Query<&TimedEffect>,
+) {
+    for (chip, children) in chip_query.iter() {
+        let Ok(effect) = effect_query.get(chip.effect) else {
+            continue;
+        };
+
+        for child in children.iter() {
+            let Ok(mut text) = text_query.get_mut(*child) else {
+                continue;
+            };
+
+            text.sections[0].value = format!(
+                "{} {:.0}s",
+                effect.kind.label(),
+                effect.timer.remaining_secs().ceil()
+            );
+        }
+    }
+}
+
+fn despawn_effect_chips(
+    mut commands: Commands,
+    chip_query: Query<(Entity, &EffectChip)>,
+    effect_query: Query<&TimedEffect>,
+) {
+    for (entity, chip) in chip_query.iter() {
+        if effect_query.get(chip.effect).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn effect_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<HazardTimeScale>()
+            .add_system(update_effects);
+
+        let player = app
+            .world
+            .spawn((
+                Player::default(),
+                HitGuard::default(),
+                BodyScale::new(1.0, Hitbox::new(0.5)),
+                Magnet::default(),
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    /// Runs a frame that ends `seconds` after `start`
+    fn run_at(app: &mut App, start: Instant, seconds: f32) {
+        let instant = start + Duration::from_secs_f32(seconds);
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(instant));
+        app.update();
+    }
+
+    fn add_effect(app: &mut App, kind: PowerUpKind, player: Entity) {
+        app.world.spawn(TimedEffect::new(kind, player));
+    }
+
+    fn speed_multiplier(app: &App, player: Entity) -> f32 {
+        app.world.get::<Player>(player).unwrap().speed_multiplier
+    }
+
+    fn body_multiplier(app: &App, player: Entity) -> f32 {
+        app.world.get::<BodyScale>(player).unwrap().multiplier
+    }
+
+    fn hazard_time_scale(app: &App) -> f32 {
+        app.world.resource::<HazardTimeScale>().scale
+    }
+
+    fn effect_count(app: &mut App) -> usize {
+        app.world.query::<&TimedEffect>().iter(&app.world).count()
+    }
+
+    #[test]
+    fn stacked_effects_multiply_and_wear_off_one_at_a_time() {
+        let (mut app, player) = effect_app();
+        let start = Instant::now();
+
+        add_effect(&mut app, PowerUpKind::SpeedBoost, player);
+        add_effect(&mut app, PowerUpKind::SpeedBoost, player);
+        add_effect(&mut app, PowerUpKind::Shrink, player);
+        add_effect(&mut app, PowerUpKind::SlowMotion, player);
+        add_effect(&mut app, PowerUpKind::SlowMotion, player);
+        run_at(&mut app, start, 0.0);
+
+        assert!(
+            (speed_multiplier(&app, player) - SPEED_BOOST_MULTIPLIER.powi(2)).abs() < TOLERANCE
+        );
+        assert!((body_multiplier(&app, player) - SHRINK_MULTIPLIER).abs() < TOLERANCE);
+        assert!((hazard_time_scale(&app) - SLOW_MOTION_SCALE.powi(2)).abs() < TOLERANCE);
+
+        // A third speed boost picked up part way through outlasts the first two
+        run_at(&mut app, start, 4.0);
+        add_effect(&mut app, PowerUpKind::SpeedBoost, player);
+        run_at(&mut app, start, 4.0);
+        assert!(
+            (speed_multiplier(&app, player) - SPEED_BOOST_MULTIPLIER.powi(3)).abs() < TOLERANCE
+        );
+
+        // Slow motion has run out, but neither the boosts nor the shrink have
+        run_at(&mut app, start, 6.0);
+        assert!((hazard_time_scale(&app) - 1.0).abs() < TOLERANCE);
+        assert!((body_multiplier(&app, player) - SHRINK_MULTIPLIER).abs() < TOLERANCE);
+
+        run_at(&mut app, start, 9.0);
+        assert!((speed_multiplier(&app, player) - SPEED_BOOST_MULTIPLIER).abs() < TOLERANCE);
+        assert!((body_multiplier(&app, player) - 1.0).abs() < TOLERANCE);
+
+        run_at(&mut app, start, 13.0);
+        assert!((speed_multiplier(&app, player) - 1.0).abs() < TOLERANCE);
+        assert_eq!(effect_count(&mut app), 0);
+    }
+
+    #[test]
+    fn everything_is_back_to_normal_once_all_effects_expire() {
+        let (mut app, player) = effect_app();
+        let start = Instant::now();
+        let speed_before = speed_multiplier(&app, player);
+        let body_before = *app.world.get::<BodyScale>(player).unwrap();
+
+        for kind in POWER_UP_KINDS {
+            add_effect(&mut app, kind, player);
+            add_effect(&mut app, kind, player);
+        }
+        run_at(&mut app, start, 0.0);
+        assert_eq!(app.world.get::<HitGuard>(player).unwrap().shields, 2);
+        assert_eq!(app.world.get::<Magnet>(player).unwrap().stacks, 2);
+
+        let longest = POWER_UP_KINDS
+            .iter()
+            .map(|kind| kind.duration())
+            .fold(0.0, f32::max);
+        run_at(&mut app, start, longest + 1.0);
+
+        assert_eq!(effect_count(&mut app), 0);
+        assert!((speed_multiplier(&app, player) - speed_before).abs() < TOLERANCE);
+        assert!((body_multiplier(&app, player) - body_before.multiplier).abs() < TOLERANCE);
+        assert_eq!(
+            app.world.get::<BodyScale>(player).unwrap().base,
+            body_before.base
+        );
+        assert!((hazard_time_scale(&app) - HazardTimeScale::default().scale).abs() < TOLERANCE);
+        assert_eq!(app.world.get::<HitGuard>(player).unwrap().shields, 0);
+        assert_eq!(app.world.get::<Magnet>(player).unwrap().stacks, 0);
+    }
+
+    #[test]
+    fn effects_on_a_missing_player_are_dropped() {
+        let (mut app, player) = effect_app();
+        app.world.despawn(player);
+
+        add_effect(&mut app, PowerUpKind::SlowMotion, player);
+        run_at(&mut app, Instant::now(), 0.0);
+
+        assert_eq!(effect_count(&mut app), 0);
+        assert_eq!(hazard_time_scale(&app), 1.0);
+    }
+}
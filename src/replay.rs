@@ -0,0 +1,629 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+    window::PrimaryWindow,
+};
+use leafwing_input_manager::{axislike::DualAxisData, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fishy_assets::FishType,
+    fixed_step::{Interpolated, StepSet},
+    input::{read_step_input, MovementAction, Player},
+    pointer::{steer_with_pointer, PointerSteering},
+    rng::GameRng,
+    stats::Score,
+    GameMode, GameState, PlayerSpecies, SimulationSet,
+};
+
+// Recording a run to a file and playing it back, so a playtester's bug report can be reproduced
+// exactly. `fishy --record run.ron` records how long every frame took, how big the window was
+// and what the player was pressing. `fishy --replay run.ron` plays those back, feeding the input
+// through the player's `ActionState` in place of the real keyboard, gamepad and mouse. It then
+// checks that the run ends up where the recording did and exits, with a failure code if it
+// didn't. Add `--headless` to play back without a window, as the tests do.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(start_replay)
+            .add_system(
+                start_replayed_run
+                    .run_if(replay_is_active)
+                    .in_schedule(OnEnter(GameState::Playing)),
+            )
+            .add_systems(
+                (
+                    play_back_viewport.run_if(resource_exists::<Playback>()),
+                    record_frame.run_if(resource_exists::<Recorder>()),
+                )
+                    .chain()
+                    .after(TimeSystem)
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_base_set(CoreSet::First),
+            )
+            .add_systems(
+                (
+                    play_back_input
+                        .run_if(resource_exists::<Playback>())
+                        .after(steer_with_pointer)
+                        .before(read_step_input),
+                    record_input
+                        .run_if(resource_exists::<Recorder>())
+                        .after(read_step_input),
+                )
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Input),
+            )
+            .add_system(
+                count_step
+                    .run_if(replay_is_active)
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(StepSet::Input)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    finish_playback.run_if(resource_exists::<Playback>()),
+                    play_back_frame_time
+                        .run_if(resource_exists::<Playback>())
+                        .run_if(in_state(GameState::Playing)),
+                    save_recording.run_if(resource_exists::<Recorder>()),
+                )
+                    .chain()
+                    .in_base_set(CoreSet::Last),
+            );
+    }
+}
+
+/// How close the replayed run has to end up to the recorded one, in world units
+const POSITION_TOLERANCE: f32 = 1e-3;
+
+/// Which recordings to play back and record. Both can be given at once, to record a replay
+/// again with the current build.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReplayMode {
+    /// The recording to play back instead of the real input and time
+    pub replay: Option<PathBuf>,
+
+    /// Where to save a recording of the run
+    pub record: Option<PathBuf>,
+}
+
+impl ReplayMode {
+    /// Picks the recordings from the command line, e.g. `fishy --replay run.ron`
+    pub fn from_args() -> ReplayMode {
+        let args = std::env::args().collect::<Vec<_>>();
+        let path_after = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+                .map(PathBuf::from)
+        };
+
+        ReplayMode {
+            replay: path_after("--replay"),
+            record: path_after("--record"),
+        }
+    }
+}
+
+/// For anything that would make a replay depend on the machine it's played back on, like the
+/// player's saved settings
+pub fn is_playing_back(replay_mode: Option<Res<ReplayMode>>) -> bool {
+    replay_mode.map_or(false, |replay_mode| replay_mode.replay.is_some())
+}
+
+/// What the player was pressing in one frame, as the `ActionState` saw it, and where the mouse
+/// or touch was steering
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pressed: Vec<MovementAction>,
+
+    /// How far the stick was pushed, before the deadzone
+    stick: Option<[f32; 2]>,
+
+    pointer: Option<[f32; 2]>,
+}
+
+impl ReplayInput {
+    fn new(
+        action_state: &ActionState<MovementAction>,
+        pointer_steering: &PointerSteering,
+    ) -> ReplayInput {
+        ReplayInput {
+            pressed: MovementAction::variants()
+                .filter(|action| action_state.pressed(*action))
+                .collect(),
+            stick: action_state
+                .axis_pair(MovementAction::Move)
+                .map(|axis_pair| axis_pair.xy().to_array()),
+            pointer: pointer_steering
+                .direction
+                .map(|direction| direction.to_array()),
+        }
+    }
+
+    /// Presses and releases everything as it was in the recording. Leafwing has already moved
+    /// on from last frame, so presses and releases show up as just pressed or just released.
+    fn apply(
+        &self,
+        action_state: &mut ActionState<MovementAction>,
+        pointer_steering: &mut PointerSteering,
+    ) {
+        for action in MovementAction::variants() {
+            if self.pressed.contains(&action) {
+                action_state.press(action);
+            } else {
+                action_state.release(action);
+            }
+        }
+
+        action_state.action_data_mut(MovementAction::Move).axis_pair = self
+            .stick
+            .map(|stick| DualAxisData::from_xy(Vec2::from(stick)));
+        pointer_steering.direction = self.pointer.map(Vec2::from);
+    }
+}
+
+/// Runs of identical values, as how many in a row and the value itself. Most frames are the
+/// same as the one before, so this keeps the file small.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Runs<T>(Vec<(u32, T)>);
+
+impl<T> Default for Runs<T> {
+    fn default() -> Runs<T> {
+        Runs(Vec::new())
+    }
+}
+
+impl<T: Clone + PartialEq> Runs<T> {
+    fn push(&mut self, value: T) {
+        match self.0.last_mut() {
+            Some((count, last)) if *last == value => *count += 1,
+            _ => self.0.push((1, value)),
+        }
+    }
+
+    fn len(&self) -> u32 {
+        self.0.iter().map(|(count, _)| count).sum()
+    }
+}
+
+/// How far through some `Runs` playback has got
+#[derive(Debug, Default)]
+struct RunsCursor {
+    run: usize,
+
+    in_run: u32,
+
+    played: u32,
+}
+
+impl RunsCursor {
+    fn next<T: Clone>(&mut self, runs: &Runs<T>) -> Option<T> {
+        let (count, value) = runs.0.get(self.run)?.clone();
+
+        self.in_run += 1;
+        self.played += 1;
+        if self.in_run >= count {
+            self.run += 1;
+            self.in_run = 0;
+        }
+
+        Some(value)
+    }
+
+    fn is_finished<T>(&self, runs: &Runs<T>) -> bool {
+        self.run >= runs.0.len()
+    }
+}
+
+/// Where the run ended up, to check a replay against
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayOutcome {
+    frames: u32,
+
+    steps: u32,
+
+    score: u32,
+
+    player_position: [f32; 2],
+}
+
+impl ReplayOutcome {
+    fn new(
+        frames: u32,
+        steps: u32,
+        score: &Score,
+        player_query: &Query<&Interpolated, With<Player>>,
+    ) -> ReplayOutcome {
+        let player_position = player_query
+            .get_single()
+            .map_or(Vec3::ZERO, Interpolated::simulated_translation);
+
+        ReplayOutcome {
+            frames,
+            steps,
+            score: score.points,
+            player_position: player_position.truncate().to_array(),
+        }
+    }
+
+    fn matches(&self, other: &ReplayOutcome) -> bool {
+        let position = Vec2::from(self.player_position);
+
+        self.frames == other.frames
+            && self.steps == other.steps
+            && self.score == other.score
+            && position.distance(Vec2::from(other.player_position)) < POSITION_TOLERANCE
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    seed: u64,
+
+    mode: GameMode,
+
+    species: FishType,
+
+    /// Nanoseconds per simulation step
+    timestep: u64,
+
+    /// How long each frame took, in nanoseconds
+    frame_times: Runs<u64>,
+
+    /// How big the window was each frame, in logical pixels. That decides how much of the play
+    /// area can be seen, which is where things spawn and where the player is kept.
+    viewports: Runs<[f32; 2]>,
+
+    inputs: Runs<ReplayInput>,
+
+    outcome: Option<ReplayOutcome>,
+}
+
+#[derive(Resource, Debug)]
+struct Recorder {
+    path: PathBuf,
+
+    replay: Replay,
+
+    steps: u32,
+
+    saved: bool,
+}
+
+#[derive(Resource, Debug)]
+struct Playback {
+    replay: Replay,
+
+    frame_times: RunsCursor,
+
+    viewports: RunsCursor,
+
+    inputs: RunsCursor,
+
+    steps_played: u32,
+
+    /// When the frame being played back started, as far as `Time` is concerned
+    clock: Instant,
+}
+
+fn replay_is_active(recorder: Option<Res<Recorder>>, playback: Option<Res<Playback>>) -> bool {
+    recorder.is_some() || playback.is_some()
+}
+
+fn window_size(window: &Window) -> [f32; 2] {
+    [window.resolution.width(), window.resolution.height()]
+}
+
+fn start_replay(
+    mut commands: Commands,
+    replay_mode: Res<ReplayMode>,
+    mut game_rng: ResMut<GameRng>,
+    mut game_mode: ResMut<GameMode>,
+    mut player_species: ResMut<PlayerSpecies>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if let Some(path) = &replay_mode.replay {
+        let replay = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                ron::from_str::<Replay>(&contents).map_err(|error| error.to_string())
+            });
+        let replay = match replay {
+            Ok(replay) => replay,
+            Err(error) => {
+                error!("Couldn't load replay {}: {error}", path.display());
+                std::process::exit(1);
+            }
+        };
+
+        *game_rng = GameRng::from_seed(replay.seed);
+        *game_mode = replay.mode;
+        player_species.fish_type = replay.species;
+        *fixed_time = FixedTime::new(Duration::from_nanos(replay.timestep));
+
+        // Anything laid out before play starts sees the same play area as the recording did
+        if let (Ok(mut window), Some((_, [width, height]))) =
+            (window_query.get_single_mut(), replay.viewports.0.first())
+        {
+            window.resolution.set(*width, *height);
+        }
+
+        commands.insert_resource(Playback {
+            replay,
+            frame_times: RunsCursor::default(),
+            viewports: RunsCursor::default(),
+            inputs: RunsCursor::default(),
+            steps_played: 0,
+            clock: Instant::now(),
+        });
+    }
+
+    if let Some(path) = &replay_mode.record {
+        commands.insert_resource(Recorder {
+            path: path.clone(),
+            replay: Replay {
+                seed: game_rng.seed,
+                mode: *game_mode,
+                species: player_species.fish_type,
+                timestep: fixed_time.period.as_nanos() as u64,
+                frame_times: Runs::default(),
+                viewports: Runs::default(),
+                inputs: Runs::default(),
+                outcome: None,
+            },
+            steps: 0,
+            saved: false,
+        });
+    }
+}
+
+/// However long loading took, the frame play starts on is made to take no time, so that no
+/// steps run on it and the recorded frame times start from the same point
+fn start_replayed_run(mut time: ResMut<Time>, playback: Option<ResMut<Playback>>) {
+    let Some(last_update) = time.last_update() else {
+        return;
+    };
+
+    time.update_with_instant(last_update);
+
+    if let Some(mut playback) = playback {
+        playback.clock = last_update;
+    }
+}
+
+fn record_frame(
+    mut recorder: ResMut<Recorder>,
+    time: Res<Time>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    if recorder.saved {
+        return;
+    }
+
+    recorder
+        .replay
+        .frame_times
+        .push(time.delta().as_nanos() as u64);
+
+    if let Ok(window) = window_query.get_single() {
+        recorder.replay.viewports.push(window_size(window));
+    }
+}
+
+fn record_input(
+    mut recorder: ResMut<Recorder>,
+    query: Query<&ActionState<MovementAction>, With<Player>>,
+    pointer_steering: Res<PointerSteering>,
+) {
+    let Ok(action_state) = query.get_single() else {
+        return;
+    };
+
+    if !recorder.saved {
+        recorder
+            .replay
+            .inputs
+            .push(ReplayInput::new(action_state, &pointer_steering));
+    }
+}
+
+fn count_step(recorder: Option<ResMut<Recorder>>, playback: Option<ResMut<Playback>>) {
+    if let Some(mut recorder) = recorder {
+        if !recorder.saved {
+            recorder.steps += 1;
+        }
+    }
+
+    if let Some(mut playback) = playback {
+        playback.steps_played += 1;
+    }
+}
+
+/// Keeps the window the size it was in the recording, whatever it's resized to
+fn play_back_viewport(
+    mut playback: ResMut<Playback>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let playback = playback.as_mut();
+    let (Some([width, height]), Ok(mut window)) = (
+        playback.viewports.next(&playback.replay.viewports),
+        window_query.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    if window_size(&window) != [width, height] {
+        window.resolution.set(width, height);
+    }
+}
+
+/// Feeds the recorded input to the player in place of the real one, which is cut off by taking
+/// away the player's `InputMap`. Once the recording runs out the player lets go of everything.
+fn play_back_input(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut query: Query<(Entity, &mut ActionState<MovementAction>), With<Player>>,
+    input_map_query: Query<(), With<InputMap<MovementAction>>>,
+    mut pointer_steering: ResMut<PointerSteering>,
+) {
+    let Ok((entity, mut action_state)) = query.get_single_mut() else {
+        return;
+    };
+
+    if input_map_query.contains(entity) {
+        commands.entity(entity).remove::<InputMap<MovementAction>>();
+    }
+
+    let playback = playback.as_mut();
+    let input = playback
+        .inputs
+        .next(&playback.replay.inputs)
+        .unwrap_or_default();
+
+    input.apply(&mut action_state, &mut pointer_steering);
+}
+
+/// Makes the next frame take as long as it did in the recording
+fn play_back_frame_time(
+    mut playback: ResMut<Playback>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    let playback = playback.as_mut();
+    let Some(frame_time) = playback.frame_times.next(&playback.replay.frame_times) else {
+        return;
+    };
+
+    playback.clock += Duration::from_nanos(frame_time);
+    *time_update_strategy = TimeUpdateStrategy::ManualInstant(playback.clock);
+}
+
+/// Saves the recording once the game is over or the app is closing
+fn save_recording(
+    mut recorder: ResMut<Recorder>,
+    state: Res<State<GameState>>,
+    exit_events: EventReader<AppExit>,
+    score: Res<Score>,
+    player_query: Query<&Interpolated, With<Player>>,
+) {
+    if recorder.saved || (state.0 != GameState::GameOver && exit_events.is_empty()) {
+        return;
+    }
+
+    let frames = recorder.replay.frame_times.len();
+    let steps = recorder.steps;
+    recorder.replay.outcome = Some(ReplayOutcome::new(frames, steps, &score, &player_query));
+    recorder.saved = true;
+
+    let result = ron::to_string(&recorder.replay)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| {
+            fs::write(&recorder.path, contents).map_err(|error| error.to_string())
+        });
+
+    match result {
+        Ok(()) => info!("Saved replay to {}", recorder.path.display()),
+        Err(error) => error!(
+            "Couldn't save replay to {}: {error}",
+            recorder.path.display()
+        ),
+    }
+}
+
+/// Once every frame has played, or the game ends early, checks the run ended up where the
+/// recording did and exits. A mismatch exits with a failure code so tests can catch it.
+fn finish_playback(
+    playback: Res<Playback>,
+    state: Res<State<GameState>>,
+    score: Res<Score>,
+    player_query: Query<&Interpolated, With<Player>>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    let frames_left = !playback
+        .frame_times
+        .is_finished(&playback.replay.frame_times);
+    if state.0 != GameState::GameOver && (state.0 != GameState::Playing || frames_left) {
+        return;
+    }
+
+    let outcome = ReplayOutcome::new(
+        playback.frame_times.played,
+        playback.steps_played,
+        &score,
+        &player_query,
+    );
+
+    match playback.replay.outcome {
+        Some(expected) if !expected.matches(&outcome) => {
+            error!("Replay diverged, expected {expected:?} but got {outcome:?}");
+            std::process::exit(1);
+        }
+        Some(_) => info!("Replay matched after {} frames", outcome.frames),
+        None => info!("Replay finished after {} frames", outcome.frames),
+    }
+
+    exit_events.send(AppExit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_play_back_what_was_pushed() {
+        let values = [3, 3, 3, 5, 3, 3];
+        let mut runs = Runs::default();
+        for value in values {
+            runs.push(value);
+        }
+
+        assert_eq!(runs.0, vec![(3, 3), (1, 5), (2, 3)]);
+        assert_eq!(runs.len(), 6);
+
+        let mut cursor = RunsCursor::default();
+        let played = std::iter::from_fn(|| cursor.next(&runs)).collect::<Vec<_>>();
+        assert_eq!(played, values);
+        assert!(cursor.is_finished(&runs));
+        assert_eq!(cursor.played, 6);
+    }
+
+    #[test]
+    fn inputs_play_back_through_the_action_state() {
+        let input = ReplayInput {
+            pressed: vec![MovementAction::Left, MovementAction::Dash],
+            stick: Some([0.6, 0.8]),
+            pointer: Some([0.0, -1.0]),
+        };
+        let mut action_state = ActionState::<MovementAction>::default();
+        let mut pointer_steering = PointerSteering::default();
+
+        input.apply(&mut action_state, &mut pointer_steering);
+        assert!(action_state.just_pressed(MovementAction::Dash));
+        assert!(action_state.released(MovementAction::Boost));
+        assert_eq!(ReplayInput::new(&action_state, &pointer_steering), input);
+
+        // Still held on the next frame, so it's no longer just pressed
+        let now = bevy::utils::Instant::now();
+        action_state.tick(now, now);
+        input.apply(&mut action_state, &mut pointer_steering);
+        assert!(action_state.pressed(MovementAction::Dash));
+        assert!(!action_state.just_pressed(MovementAction::Dash));
+
+        action_state.tick(now, now);
+        ReplayInput::default().apply(&mut action_state, &mut pointer_steering);
+        assert!(action_state.just_released(MovementAction::Dash));
+        assert_eq!(pointer_steering.direction, None);
+    }
+}
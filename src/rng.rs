@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    fixed_step::{snapshot_transforms, StepSet},
+    GameState,
+};
+
+// Seeded randomness for everything that changes how a run plays out, so that a run can be
+// replayed from its seed. Purely cosmetic randomness, like particles, doesn't need it.
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>()
+            .add_system(reset_game_rng.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(
+                advance_game_rng
+                    .run_if(in_state(GameState::Playing))
+                    .in_base_set(CoreSet::First),
+            )
+            .add_system(
+                advance_game_rng
+                    .after(snapshot_transforms)
+                    .before(StepSet::Input)
+                    .run_if(in_state(GameState::Playing))
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// Each system draws from its own stream, picked by name and reseeded every frame and every
+/// simulation step. That way the numbers a system gets don't depend on which other systems
+/// happened to run before it.
+#[derive(Resource, Debug)]
+pub struct GameRng {
+    pub seed: u64,
+
+    /// Frames and steps played so far
+    tick: u64,
+}
+
+impl Default for GameRng {
+    fn default() -> GameRng {
+        GameRng::from_seed(rand::random())
+    }
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> GameRng {
+        GameRng { seed, tick: 0 }
+    }
+
+    /// The random numbers for this frame or step, for whoever goes by `name`. Both the hashing
+    /// and the generator are fixed algorithms, so a replay recorded with one build plays back
+    /// the same in any other.
+    pub fn stream(&self, name: &str) -> ChaCha8Rng {
+        let seed = splitmix64(splitmix64(splitmix64(self.seed) ^ self.tick) ^ fnv1a(name));
+
+        ChaCha8Rng::seed_from_u64(seed)
+    }
+}
+
+/// 64 bit FNV-1a
+fn fnv1a(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The splitmix64 mixing function, which spreads every bit of its input over the output
+fn splitmix64(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    x ^ (x >> 31)
+}
+
+/// Every run starts from the same streams, however long it took to get to
+fn reset_game_rng(mut game_rng: ResMut<GameRng>) {
+    game_rng.tick = 0;
+}
+
+fn advance_game_rng(mut game_rng: ResMut<GameRng>) {
+    game_rng.tick += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn hashes_match_their_published_values() {
+        assert_eq!(fnv1a(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(splitmix64(0), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[test]
+    fn streams_are_repeatable_and_distinct() {
+        let game_rng = GameRng::from_seed(7);
+        let draw = |name| game_rng.stream(name).gen::<u64>();
+
+        assert_eq!(draw("hazards"), draw("hazards"));
+        assert_ne!(draw("hazards"), draw("bosses"));
+
+        let next_tick = GameRng { seed: 7, tick: 1 };
+        assert_ne!(draw("hazards"), next_tick.stream("hazards").gen::<u64>());
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use strum::IntoEnumIterator;
+
+use crate::{
+    daynight::Glow,
+    fishy_assets::{
+        CoralCollection, CoralType, RockCollection, RockType, ShellType, ShellsCollection,
+    },
+    instancing::{InstanceData, InstancedMesh},
+    material_override::{MaterialOverride, OverriddenMaterials},
+    terrain_height, GameState, Seabed, SimulationSet, RADIUS,
+};
+
+// Corals, rocks and shells scattered over the seabed. By default the props are drawn in one
+// instanced draw call per mesh and material rather than spawning a glTF scene per prop, still
+// with their own materials.
+pub struct SeabedPlugin;
+
+impl Plugin for SeabedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeabedSettings>()
+            .init_resource::<SeabedDrawCalls>()
+            .init_resource::<PropTemplates>()
+            .add_systems(
+                (
+                    change_seabed_settings,
+                    spawn_seabed_props.run_if(resource_changed::<SeabedSettings>()),
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+/// The same seed every time so both ways of drawing the props can be compared like for like
+const SEABED_SEED: u64 = 340;
+
+const MIN_DENSITY: f32 = 0.25;
+const MAX_DENSITY: f32 = 12.0;
+const DENSITY_STEP: f32 = 0.25;
+
+/// F6 switches between instanced and separate props, Page Up and Page Down change the density
+#[derive(Resource, Debug)]
+pub struct SeabedSettings {
+    pub instanced: bool,
+
+    /// How many props there are compared to the original hand tuned amount
+    pub density: f32,
+}
+
+impl Default for SeabedSettings {
+    fn default() -> SeabedSettings {
+        SeabedSettings {
+            instanced: true,
+            density: 1.0,
+        }
+    }
+}
+
+/// How many draw calls the props take each way, which is the point of instancing them
+#[derive(Resource, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SeabedDrawCalls {
+    pub props: usize,
+
+    pub instanced: usize,
+
+    /// Every part of every prop is a draw call of its own without instancing
+    pub separate: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PropCategory {
+    Coral,
+    Rock,
+    Shell,
+}
+
+const PROP_CATEGORIES: [PropCategory; 3] =
+    [PropCategory::Coral, PropCategory::Rock, PropCategory::Shell];
+
+const CORAL_GLOWS: [Color; 3] = [
+    Color::rgb(0.2, 0.9, 1.0),
+    Color::rgb(1.0, 0.3, 0.7),
+    Color::rgb(0.5, 1.0, 0.4),
+];
+
+impl PropCategory {
+    /// How many there are at a density of 1
+    fn count(&self) -> usize {
+        match self {
+            PropCategory::Coral => 100,
+            PropCategory::Rock => 80,
+            PropCategory::Shell => 60,
+        }
+    }
+
+    fn scale(&self) -> (f32, f32) {
+        match self {
+            PropCategory::Coral => (0.5, 3.0),
+            PropCategory::Rock => (0.5, 4.0),
+            PropCategory::Shell => (0.5, 1.0),
+        }
+    }
+
+    /// Some corals glow in the dark, in one of the `CORAL_GLOWS`
+    fn glow(&self, rng: &mut StdRng) -> Option<usize> {
+        match self {
+            PropCategory::Coral if rng.gen_bool(0.4) => Some(rng.gen_range(0..CORAL_GLOWS.len())),
+            _ => None,
+        }
+    }
+
+    /// How far the prop is pushed into the seabed so it doesn't float on slopes
+    fn sink(&self) -> f32 {
+        match self {
+            PropCategory::Coral => 2.0,
+            PropCategory::Rock => 4.0,
+            PropCategory::Shell => 0.0,
+        }
+    }
+
+    fn scenes(
+        &self,
+        coral_collection: &CoralCollection,
+        rock_collection: &RockCollection,
+        shells_collection: &ShellsCollection,
+    ) -> Vec<Handle<Scene>> {
+        match self {
+            PropCategory::Coral => CoralType::iter()
+                .map(|coral_type| coral_type.model_from(coral_collection))
+                .collect(),
+            PropCategory::Rock => RockType::iter()
+                .map(|rock_type| rock_type.model_from(rock_collection))
+                .collect(),
+            PropCategory::Shell => ShellType::iter()
+                .map(|shell_type| shell_type.model_from(shells_collection))
+                .collect(),
+        }
+    }
+}
+
+/// One mesh out of a prop's glTF scene
+#[derive(Debug, Clone)]
+struct PropPart {
+    mesh: Handle<Mesh>,
+
+    /// Relative to the root of the scene
+    transform: Mat4,
+
+    material: Handle<StandardMaterial>,
+}
+
+/// The meshes that make up each prop, pulled out of their scenes the first time they're needed
+#[derive(Resource, Default)]
+struct PropTemplates {
+    parts: HashMap<Handle<Scene>, Vec<PropPart>>,
+}
+
+impl PropTemplates {
+    fn extract(&mut self, scene_handle: &Handle<Scene>, scenes: &Assets<Scene>) {
+        self.parts
+            .entry(scene_handle.clone())
+            .or_insert_with(|| scenes.get(scene_handle).map_or_else(Vec::new, scene_parts));
+    }
+
+    fn parts(&self, scene_handle: &Handle<Scene>) -> &[PropPart] {
+        self.parts.get(scene_handle).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The glTF loader gives every mesh a material, so a mesh without one isn't drawn
+fn scene_parts(scene: &Scene) -> Vec<PropPart> {
+    let world = &scene.world;
+
+    world
+        .iter_entities()
+        .filter_map(|entity| {
+            let mesh = entity.get::<Handle<Mesh>>()?;
+            let material = entity.get::<Handle<StandardMaterial>>()?;
+
+            Some(PropPart {
+                mesh: mesh.clone(),
+                transform: scene_transform(world, entity.id()),
+                material: material.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Walks up the scene's hierarchy since nothing in it has been given a `GlobalTransform` yet
+fn scene_transform(world: &World, entity: Entity) -> Mat4 {
+    let mut transform = Mat4::IDENTITY;
+    let mut current = Some(entity);
+
+    while let Some(entity) = current {
+        if let Some(local) = world.get::<Transform>(entity) {
+            transform = local.compute_matrix() * transform;
+        }
+
+        current = world.get::<Parent>(entity).map(|parent| parent.get());
+    }
+
+    transform
+}
+
+/// All of the props, whichever way they're drawn
+#[derive(Component, Debug)]
+pub struct SeabedProps;
+
+/// A prop on the seabed
+struct Placement {
+    scene: Handle<Scene>,
+
+    /// Relative to the `Seabed`
+    transform: Transform,
+
+    /// Which of the `CORAL_GLOWS` it glows in, if any
+    glow: Option<usize>,
+}
+
+fn scatter_props(
+    density: f32,
+    coral_collection: &CoralCollection,
+    rock_collection: &RockCollection,
+    shells_collection: &ShellsCollection,
+) -> Vec<Placement> {
+    let mut rng = StdRng::seed_from_u64(SEABED_SEED);
+
+    PROP_CATEGORIES
+        .iter()
+        .flat_map(|category| {
+            let scenes = category.scenes(coral_collection, rock_collection, shells_collection);
+            let (min_scale, max_scale) = category.scale();
+            let count = (category.count() as f32 * density).round() as usize;
+
+            (0..count)
+                .map(|_| {
+                    let scene = scenes.choose(&mut rng).unwrap().clone();
+                    let scale = rng.gen_range(min_scale..=max_scale);
+                    let x = rng.gen_range(-RADIUS..=RADIUS);
+                    let z = rng.gen_range(-RADIUS..=RADIUS);
+                    let y = terrain_height(x, z) - category.sink();
+
+                    Placement {
+                        scene,
+                        transform: Transform::from_xyz(x, y, z).with_scale(Vec3::splat(scale)),
+                        glow: category.glow(&mut rng),
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn change_seabed_settings(keys: Res<Input<KeyCode>>, mut settings: ResMut<SeabedSettings>) {
+    if keys.just_pressed(KeyCode::F6) {
+        settings.instanced = !settings.instanced;
+    }
+
+    let step = if keys.just_pressed(KeyCode::PageUp) {
+        DENSITY_STEP
+    } else if keys.just_pressed(KeyCode::PageDown) {
+        -DENSITY_STEP
+    } else {
+        0.0
+    };
+
+    if step != 0.0 {
+        settings.density = (settings.density + step).clamp(MIN_DENSITY, MAX_DENSITY);
+    }
+}
+
+/// Instances of the same mesh with the same material are drawn together. Glowing ones are drawn
+/// with a glowing copy of the material, so they get batches of their own for each glow color.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BatchKey {
+    mesh: Handle<Mesh>,
+
+    material: Handle<StandardMaterial>,
+
+    glow: Option<usize>,
+}
+
+fn batch_props(
+    placements: &[Placement],
+    templates: &PropTemplates,
+) -> HashMap<BatchKey, Vec<InstanceData>> {
+    let mut batches = HashMap::<BatchKey, Vec<InstanceData>>::new();
+
+    for placement in placements.iter() {
+        for part in templates.parts(&placement.scene) {
+            let instance = InstanceData::new(placement.transform.compute_matrix() * part.transform);
+
+            let key = BatchKey {
+                mesh: part.mesh.clone(),
+                material: part.material.clone(),
+                glow: placement.glow,
+            };
+            batches.entry(key).or_default().push(instance);
+        }
+    }
+
+    batches
+}
+
+fn count_draw_calls(
+    placements: &[Placement],
+    templates: &PropTemplates,
+    batches: &HashMap<BatchKey, Vec<InstanceData>>,
+) -> SeabedDrawCalls {
+    SeabedDrawCalls {
+        props: placements.len(),
+        instanced: batches.len(),
+        separate: placements
+            .iter()
+            .map(|placement| templates.parts(&placement.scene).len())
+            .sum(),
+    }
+}
+
+/// Rebuilds the props whenever the settings change, including when the game starts
+#[allow(clippy::too_many_arguments)]
+fn spawn_seabed_props(
+    mut commands: Commands,
+    seabed_query: Query<Entity, With<Seabed>>,
+    props_query: Query<Entity, With<SeabedProps>>,
+    settings: Res<SeabedSettings>,
+    glow: Res<Glow>,
+    mut draw_calls: ResMut<SeabedDrawCalls>,
+    mut templates: ResMut<PropTemplates>,
+    scenes: Res<Assets<Scene>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    coral_collection: Res<CoralCollection>,
+    rock_collection: Res<RockCollection>,
+    shells_collection: Res<ShellsCollection>,
+) {
+    let Ok(seabed) = seabed_query.get_single() else {
+        return;
+    };
+
+    for entity in props_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let placements = scatter_props(
+        settings.density,
+        &coral_collection,
+        &rock_collection,
+        &shells_collection,
+    );
+
+    for placement in placements.iter() {
+        templates.extract(&placement.scene, &scenes);
+    }
+
+    let batches = batch_props(&placements, &templates);
+    *draw_calls = count_draw_calls(&placements, &templates, &batches);
+
+    commands.entity(seabed).with_children(|parent| {
+        parent
+            .spawn((SpatialBundle::default(), SeabedProps))
+            .with_children(|parent| {
+                if settings.instanced {
+                    // Batches with the same material and glow share the glowing copy
+                    let mut copies = HashMap::<
+                        (Handle<StandardMaterial>, usize),
+                        Handle<StandardMaterial>,
+                    >::new();
+
+                    for (key, instances) in batches {
+                        let Some(index) = key.glow else {
+                            parent.spawn((
+                                key.mesh,
+                                SpatialBundle::default(),
+                                InstancedMesh {
+                                    material: key.material,
+                                    instances,
+                                },
+                                // The instances are spread all over the seabed
+                                NoFrustumCulling,
+                            ));
+                            continue;
+                        };
+
+                        // Overridden like a glowing prop's scene would be, so the copy keeps
+                        // following the glow
+                        let material_override = MaterialOverride::glow(CORAL_GLOWS[index]);
+                        let copy = copies
+                            .entry((key.material.clone(), index))
+                            .or_insert_with(|| {
+                                let mut material =
+                                    materials.get(&key.material).cloned().unwrap_or_default();
+                                material_override.apply(&mut material, glow.strength);
+                                materials.add(material)
+                            })
+                            .clone();
+
+                        parent.spawn((
+                            key.mesh,
+                            SpatialBundle::default(),
+                            InstancedMesh {
+                                material: copy.clone(),
+                                instances,
+                            },
+                            NoFrustumCulling,
+                            material_override,
+                            OverriddenMaterials {
+                                materials: vec![(key.material, copy)],
+                            },
+                        ));
+                    }
+                } else {
+                    for placement in placements {
+                        let mut prop = parent.spawn(SceneBundle {
+                            scene: placement.scene,
+                            transform: placement.transform,
+                            ..default()
+                        });
+
+                        if let Some(index) = placement.glow {
+                            prop.insert(MaterialOverride::glow(CORAL_GLOWS[index]));
+                        }
+                    }
+                }
+            });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::HandleId;
+
+    use super::*;
+
+    fn handle<T: bevy::asset::Asset>() -> Handle<T> {
+        Handle::weak(HandleId::random::<T>())
+    }
+
+    /// A few props made of a couple of meshes each, shared between them like the real models
+    fn templates(
+        meshes: &[Handle<Mesh>],
+        materials: &[Handle<StandardMaterial>],
+    ) -> (PropTemplates, Vec<Handle<Scene>>) {
+        let mut templates = PropTemplates::default();
+        let scenes = (0..4).map(|_| handle::<Scene>()).collect::<Vec<_>>();
+
+        for (index, scene) in scenes.iter().enumerate() {
+            let parts = (0..=index % 3)
+                .map(|part| PropPart {
+                    mesh: meshes[(index + part) % meshes.len()].clone(),
+                    transform: Mat4::IDENTITY,
+                    material: materials[(index + part) % materials.len()].clone(),
+                })
+                .collect();
+            templates.parts.insert(scene.clone(), parts);
+        }
+
+        (templates, scenes)
+    }
+
+    /// Lined up along x so an instance can be traced back to its placement
+    fn placements(scenes: &[Handle<Scene>], count: usize) -> Vec<Placement> {
+        (0..count)
+            .map(|index| Placement {
+                scene: scenes[index % scenes.len()].clone(),
+                transform: Transform::from_xyz(index as f32, 0.0, 0.0),
+                glow: (index % 5 == 0).then_some(index / 5 % CORAL_GLOWS.len()),
+            })
+            .collect()
+    }
+
+    fn placement_index(instance: &InstanceData) -> usize {
+        instance.transform[3][0] as usize
+    }
+
+    #[test]
+    fn batching_keeps_draw_calls_flat_as_density_rises() {
+        let meshes = (0..3).map(|_| handle::<Mesh>()).collect::<Vec<_>>();
+        let materials = (0..3)
+            .map(|_| handle::<StandardMaterial>())
+            .collect::<Vec<_>>();
+        let (templates, scenes) = templates(&meshes, &materials);
+        let mut previous: Option<SeabedDrawCalls> = None;
+
+        for count in [10, 50, 240, 1000, 2880] {
+            let placements = placements(&scenes, count);
+            let batches = batch_props(&placements, &templates);
+            let draw_calls = count_draw_calls(&placements, &templates, &batches);
+
+            // Each mesh always has the same material here, so at most one batch per mesh for
+            // each glow color and one for the rest
+            assert!(draw_calls.instanced <= meshes.len() * (CORAL_GLOWS.len() + 1));
+            assert!(draw_calls.instanced < draw_calls.separate);
+
+            let instances = batches.values().map(Vec::len).sum::<usize>();
+            assert_eq!(instances, draw_calls.separate);
+
+            // Once every scene has turned up in every glow color, more props only means more
+            // instances
+            if let Some(previous) = previous.filter(|previous| previous.props >= 240) {
+                assert!(draw_calls.separate > previous.separate);
+                assert_eq!(draw_calls.instanced, previous.instanced);
+            }
+            previous = Some(draw_calls);
+        }
+    }
+
+    #[test]
+    fn meshes_are_batched_apart_for_each_material() {
+        let meshes = vec![handle::<Mesh>()];
+        let materials = (0..2)
+            .map(|_| handle::<StandardMaterial>())
+            .collect::<Vec<_>>();
+        let (templates, scenes) = templates(&meshes, &materials);
+        let placements = placements(&scenes, 20);
+        let batches = batch_props(&placements, &templates);
+
+        for material in materials.iter() {
+            assert!(batches.keys().any(|key| key.material == *material));
+        }
+
+        for (key, instances) in batches.iter() {
+            for instance in instances {
+                let placement = &placements[placement_index(instance)];
+                assert!(templates
+                    .parts(&placement.scene)
+                    .iter()
+                    .any(|part| part.material == key.material));
+            }
+        }
+    }
+
+    #[test]
+    fn glowing_props_are_batched_apart_for_each_color() {
+        let meshes = vec![handle::<Mesh>()];
+        let materials = vec![handle::<StandardMaterial>()];
+        let (templates, scenes) = templates(&meshes, &materials);
+        let placements = placements(&scenes, 20);
+        let batches = batch_props(&placements, &templates);
+
+        // One batch for each color and one for the props that don't glow
+        assert_eq!(batches.len(), CORAL_GLOWS.len() + 1);
+
+        for (key, instances) in batches.iter() {
+            for instance in instances {
+                assert_eq!(placements[placement_index(instance)].glow, key.glow);
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl};
+use leafwing_input_manager::prelude::InputMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::GraphicsSettings,
+    input::{Bindings, MovementAction, BINDINGS_VERSION},
+    replay::is_playing_back,
+};
+
+// Keeps the player's settings between runs. They're written out as RON to the platform's config
+// directory, or to local storage on the web, whenever any of them change.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // A replay plays back with the default settings and leaves the player's alone
+        app.init_resource::<AudioSettings>()
+            .add_startup_system(load_settings.run_if(not(is_playing_back)))
+            .add_systems(
+                (
+                    apply_audio_settings,
+                    save_settings.run_if(not(is_playing_back)),
+                )
+                    .in_base_set(CoreSet::PostUpdate),
+            );
+    }
+}
+
+const VOLUME_STEPS: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioSettings {
+    /// Scales everything that plays
+    pub master: f64,
+
+    /// Scales the sound effects on top of `master`
+    pub effects: f64,
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings {
+            master: 1.0,
+            effects: 1.0,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn cycle_master(&mut self) {
+        self.master = next_volume(self.master);
+    }
+
+    pub fn cycle_effects(&mut self) {
+        self.effects = next_volume(self.effects);
+    }
+}
+
+fn next_volume(volume: f64) -> f64 {
+    VOLUME_STEPS
+        .iter()
+        .copied()
+        .find(|step| *step > volume + f64::EPSILON)
+        .unwrap_or(VOLUME_STEPS[0])
+}
+
+/// Everything that is saved, as it's laid out in the file
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSettings {
+    audio: AudioSettings,
+
+    graphics: GraphicsSettings,
+
+    bindings: InputMap<MovementAction>,
+
+    /// Missing from files saved before the bindings were versioned
+    #[serde(default)]
+    bindings_version: u32,
+}
+
+fn load_settings(
+    mut audio_settings: ResMut<AudioSettings>,
+    mut graphics_settings: ResMut<GraphicsSettings>,
+    mut bindings: ResMut<Bindings>,
+) {
+    let Some(contents) = storage::read() else {
+        return;
+    };
+
+    // Settings from an older or broken file are dropped rather than stopping the game
+    match ron::from_str::<SavedSettings>(&contents) {
+        Ok(saved) => {
+            *audio_settings = saved.audio;
+            *graphics_settings = saved.graphics;
+            bindings.input_map = saved.bindings;
+            bindings.upgrade(saved.bindings_version);
+        }
+        Err(error) => warn!("Ignoring saved settings: {error}"),
+    }
+}
+
+fn save_settings(
+    audio_settings: Res<AudioSettings>,
+    graphics_settings: Res<GraphicsSettings>,
+    bindings: Res<Bindings>,
+) {
+    // Loading counts as a change, so the file gets written once at startup too
+    if !audio_settings.is_changed() && !graphics_settings.is_changed() && !bindings.is_changed() {
+        return;
+    }
+
+    let saved = SavedSettings {
+        audio: *audio_settings,
+        graphics: *graphics_settings,
+        bindings: bindings.input_map.clone(),
+        bindings_version: BINDINGS_VERSION,
+    };
+
+    match ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => storage::write(&contents),
+        Err(error) => warn!("Couldn't save settings: {error}"),
+    }
+}
+
+fn apply_audio_settings(audio_settings: Res<AudioSettings>, audio: Res<Audio>) {
+    if audio_settings.is_changed() {
+        audio.set_volume(audio_settings.master);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::{fs, path::PathBuf};
+
+    use bevy::prelude::*;
+    use directories::ProjectDirs;
+
+    const FILE_NAME: &str = "settings.ron";
+
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "Fishy").map(|dirs| dirs.config_dir().join(FILE_NAME))
+    }
+
+    pub fn read() -> Option<String> {
+        fs::read_to_string(path()?).ok()
+    }
+
+    pub fn write(contents: &str) {
+        let Some(path) = path() else {
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, contents));
+
+        if let Err(error) = result {
+            warn!("Couldn't save settings to {}: {error}", path.display());
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use bevy::prelude::*;
+
+    const KEY: &str = "fishy.settings";
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read() -> Option<String> {
+        local_storage()?.get_item(KEY).ok()?
+    }
+
+    pub fn write(contents: &str) {
+        let Some(storage) = local_storage() else {
+            return;
+        };
+
+        if storage.set_item(KEY, contents).is_err() {
+            warn!("Couldn't save settings to local storage");
+        }
+    }
+}
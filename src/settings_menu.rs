@@ -0,0 +1,416 @@
+use bevy::prelude::*;
+use leafwing_input_manager::{user_input::InputKind, Actionlike};
+
+use crate::{
+    fishy_assets::FontCollection,
+    graphics::{GraphicsPreset, GraphicsSettings},
+    input::{Bindings, MovementAction},
+    settings::AudioSettings,
+    GameState, SimulationSet,
+};
+
+// The in-game settings menu, opened and closed with Escape. Each row is a button that steps
+// through the values for its setting, and changes take effect straight away. Control rows wait
+// for the next key or gamepad button and bind it instead.
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_system(setup_settings_menu.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (
+                    capture_rebinding,
+                    toggle_settings_menu,
+                    click_settings_rows,
+                    sync_settings_values,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const ROW_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.08);
+const ROW_HOVER_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.2);
+
+/// The panel holding every row, hidden while the menu is closed
+#[derive(Component, Debug)]
+pub struct SettingsMenu;
+
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SettingsRow {
+    Preset,
+    Shadows,
+    ShadowResolution,
+    Bloom,
+    Fog,
+    Msaa,
+    PropDensity,
+    ResolutionScale,
+    MasterVolume,
+    EffectsVolume,
+    Binding(MovementAction),
+}
+
+impl SettingsRow {
+    const GRAPHICS: [SettingsRow; 8] = [
+        SettingsRow::Preset,
+        SettingsRow::Shadows,
+        SettingsRow::ShadowResolution,
+        SettingsRow::Bloom,
+        SettingsRow::Fog,
+        SettingsRow::Msaa,
+        SettingsRow::PropDensity,
+        SettingsRow::ResolutionScale,
+    ];
+
+    const AUDIO: [SettingsRow; 2] = [SettingsRow::MasterVolume, SettingsRow::EffectsVolume];
+
+    fn label(&self) -> String {
+        match self {
+            SettingsRow::Preset => "Quality".to_string(),
+            SettingsRow::Shadows => "Shadows".to_string(),
+            SettingsRow::ShadowResolution => "Shadow resolution".to_string(),
+            SettingsRow::Bloom => "Bloom".to_string(),
+            SettingsRow::Fog => "Fog".to_string(),
+            SettingsRow::Msaa => "Anti-aliasing".to_string(),
+            SettingsRow::PropDensity => "Seabed props".to_string(),
+            SettingsRow::ResolutionScale => "Resolution".to_string(),
+            SettingsRow::MasterVolume => "Volume".to_string(),
+            SettingsRow::EffectsVolume => "Effects".to_string(),
+            SettingsRow::Binding(action) => format!("{action:?}"),
+        }
+    }
+
+    fn value(
+        &self,
+        graphics: &GraphicsSettings,
+        audio: &AudioSettings,
+        bindings: &Bindings,
+        rebinding: &Rebinding,
+    ) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" }.to_string();
+        let percent = |fraction: f64| format!("{}%", (fraction * 100.0).round());
+
+        match self {
+            SettingsRow::Preset => graphics.preset.label().to_string(),
+            SettingsRow::Shadows => on_off(graphics.shadows),
+            SettingsRow::ShadowResolution => graphics.shadow_resolution.to_string(),
+            SettingsRow::Bloom => on_off(graphics.bloom),
+            SettingsRow::Fog => on_off(graphics.fog),
+            SettingsRow::Msaa => on_off(graphics.msaa),
+            SettingsRow::PropDensity => format!("{}x", graphics.prop_density),
+            SettingsRow::ResolutionScale => percent(graphics.resolution_scale as f64),
+            SettingsRow::MasterVolume => percent(audio.master),
+            SettingsRow::EffectsVolume => percent(audio.effects),
+            SettingsRow::Binding(action) if rebinding.action == Some(*action) => "...".to_string(),
+            SettingsRow::Binding(action) => bindings.describe(*action),
+        }
+    }
+
+    /// Steps the setting on to its next value. Changing any graphics setting but the preset
+    /// makes the graphics custom.
+    fn cycle(
+        &self,
+        graphics: &mut GraphicsSettings,
+        audio: &mut AudioSettings,
+        rebinding: &mut Rebinding,
+    ) {
+        match self {
+            SettingsRow::Preset => {
+                *graphics = GraphicsSettings::from_preset(graphics.preset.next());
+                return;
+            }
+            SettingsRow::Shadows => graphics.shadows = !graphics.shadows,
+            SettingsRow::ShadowResolution => graphics.cycle_shadow_resolution(),
+            SettingsRow::Bloom => graphics.bloom = !graphics.bloom,
+            SettingsRow::Fog => graphics.fog = !graphics.fog,
+            SettingsRow::Msaa => graphics.msaa = !graphics.msaa,
+            SettingsRow::PropDensity => graphics.cycle_prop_density(),
+            SettingsRow::ResolutionScale => graphics.cycle_resolution_scale(),
+            SettingsRow::MasterVolume => {
+                audio.cycle_master();
+                return;
+            }
+            SettingsRow::EffectsVolume => {
+                audio.cycle_effects();
+                return;
+            }
+            SettingsRow::Binding(action) => {
+                rebinding.start(*action);
+                return;
+            }
+        }
+
+        graphics.preset = GraphicsPreset::Custom;
+    }
+}
+
+/// The action waiting for a new key or button, and what to tell the player about it
+#[derive(Resource, Debug, Default)]
+struct Rebinding {
+    action: Option<MovementAction>,
+
+    message: String,
+}
+
+impl Rebinding {
+    fn start(&mut self, action: MovementAction) {
+        self.action = Some(action);
+        self.message = format!("Press a key or button for {action:?}, or Escape to cancel");
+    }
+
+    fn finish(&mut self) {
+        self.action = None;
+        self.message.clear();
+    }
+}
+
+/// The text showing a row's current value
+#[derive(Component, Debug)]
+struct SettingsValue(SettingsRow);
+
+/// The line under the rows that says what's going on with rebinding
+#[derive(Component, Debug)]
+struct SettingsStatus;
+
+fn setup_settings_menu(
+    mut commands: Commands,
+    font_collection: Res<FontCollection>,
+    graphics: Res<GraphicsSettings>,
+    audio: Res<AudioSettings>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+) {
+    let text_style = TextStyle {
+        font: font_collection.bold.clone(),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+    let heading_style = TextStyle {
+        font_size: 22.0,
+        ..text_style.clone()
+    };
+    let value = |row: SettingsRow| row.value(&graphics, &audio, &bindings, &rebinding);
+
+    let column = NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            gap: Size::height(Val::Px(4.0)),
+            ..default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                // Above the HUD
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            SettingsMenu,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        gap: Size::height(Val::Px(8.0)),
+                        padding: UiRect::all(Val::Px(16.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.05, 0.15, 0.85).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Settings",
+                        TextStyle {
+                            font_size: 32.0,
+                            ..text_style.clone()
+                        },
+                    ));
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                gap: Size::width(Val::Px(16.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(column.clone()).with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    "Graphics",
+                                    heading_style.clone(),
+                                ));
+                                for row in SettingsRow::GRAPHICS {
+                                    spawn_row(parent, &text_style, row, value(row));
+                                }
+                            });
+
+                            parent.spawn(column.clone()).with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    "Audio",
+                                    heading_style.clone(),
+                                ));
+                                for row in SettingsRow::AUDIO {
+                                    spawn_row(parent, &text_style, row, value(row));
+                                }
+
+                                parent.spawn(TextBundle::from_section(
+                                    "Controls",
+                                    heading_style.clone(),
+                                ));
+                                for action in MovementAction::variants()
+                                    .filter(|action| action.is_rebindable())
+                                {
+                                    let row = SettingsRow::Binding(action);
+                                    spawn_row(parent, &text_style, row, value(row));
+                                }
+                            });
+                        });
+
+                    parent.spawn((
+                        TextBundle::from_section(String::new(), text_style.clone()),
+                        SettingsStatus,
+                    ));
+                });
+        });
+}
+
+fn spawn_row(parent: &mut ChildBuilder, text_style: &TextStyle, row: SettingsRow, value: String) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::width(Val::Px(300.0)),
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: ROW_COLOR.into(),
+                ..default()
+            },
+            row,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(row.label(), text_style.clone()));
+            parent.spawn((
+                TextBundle::from_section(value, text_style.clone()),
+                SettingsValue(row),
+            ));
+        });
+}
+
+/// Binds the next key or gamepad button pressed. Anything already bound to another action is
+/// turned down so that two actions never share an input.
+fn capture_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    mut keys: ResMut<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+
+    // Used up here so that it doesn't close the menu as well
+    if keys.clear_just_pressed(KeyCode::Escape) {
+        rebinding.finish();
+        return;
+    }
+
+    let input = if let Some(key) = keys.get_just_pressed().next() {
+        InputKind::Keyboard(*key)
+    } else if let Some(button) = buttons.get_just_pressed().next() {
+        InputKind::GamepadButton(button.button_type)
+    } else {
+        return;
+    };
+
+    match bindings.rebind(action, input) {
+        Ok(()) => rebinding.finish(),
+        Err(other) => {
+            rebinding.message = format!("That's already used by {other:?}, try another");
+        }
+    }
+}
+
+fn toggle_settings_menu(
+    mut query: Query<&mut Visibility, With<SettingsMenu>>,
+    mut rebinding: ResMut<Rebinding>,
+    keys: Res<Input<KeyCode>>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    for mut visibility in query.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+
+    if rebinding.action.is_some() {
+        rebinding.finish();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn click_settings_rows(
+    mut query: Query<
+        (&Interaction, &SettingsRow, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut graphics: ResMut<GraphicsSettings>,
+    mut audio: ResMut<AudioSettings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, row, mut background) in query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => row.cycle(&mut graphics, &mut audio, &mut rebinding),
+            Interaction::Hovered => *background = ROW_HOVER_COLOR.into(),
+            Interaction::None => *background = ROW_COLOR.into(),
+        }
+    }
+}
+
+fn sync_settings_values(
+    mut value_query: Query<(&SettingsValue, &mut Text), Without<SettingsStatus>>,
+    mut status_query: Query<&mut Text, With<SettingsStatus>>,
+    graphics: Res<GraphicsSettings>,
+    audio: Res<AudioSettings>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+) {
+    if !graphics.is_changed()
+        && !audio.is_changed()
+        && !bindings.is_changed()
+        && !rebinding.is_changed()
+    {
+        return;
+    }
+
+    for (SettingsValue(row), mut text) in value_query.iter_mut() {
+        text.sections[0].value = row.value(&graphics, &audio, &bindings, &rebinding);
+    }
+
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = rebinding.message.clone();
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+#[derive(Resource, Default, Debug)]
+pub struct Score {
+    pub points: u32,
+}
+
+/// The player's energy. Eating food fills it up.
+#[derive(Resource, Debug)]
+pub struct Energy {
+    pub current: f32,
+
+    pub max: f32,
+}
+
+impl Default for Energy {
+    fn default() -> Energy {
+        Energy {
+            current: 50.0,
+            max: 100.0,
+        }
+    }
+}
+
+impl Energy {
+    pub fn add(&mut self, amount: f32) {
+        self.current = (self.current + amount).clamp(0.0, self.max);
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+use crate::{
+    current::CurrentField, fishy_assets::SeaweedType, GameState, SimulationSet, SEAWEED_HEIGHT,
+};
+
+// Seaweed that sways in the vertex shader instead of playing its skeletal animation, so hundreds
+// of strands cost next to nothing on the CPU
+pub struct SwayPlugin;
+
+impl Plugin for SwayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MaterialPlugin::<SwayMaterial>::default())
+            .init_resource::<SeaweedSwaySettings>()
+            .init_resource::<SwayMaterials>()
+            .add_systems(
+                (apply_sway_materials, follow_current)
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+/// How quickly the lean catches up with the current, as a fraction of the difference per second
+const LEAN_SPEED: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SwayMode {
+    /// Plays the model's own looping animation
+    Skeletal,
+
+    /// Bends the mesh in the vertex shader
+    Shader,
+}
+
+/// Which way each type of seaweed is animated
+#[derive(Resource, Debug)]
+pub struct SeaweedSwaySettings {
+    pub modes: HashMap<SeaweedType, SwayMode>,
+}
+
+impl Default for SeaweedSwaySettings {
+    fn default() -> SeaweedSwaySettings {
+        SeaweedSwaySettings {
+            modes: HashMap::from([
+                (SeaweedType::Seaweed1, SwayMode::Shader),
+                (SeaweedType::Seaweed2, SwayMode::Shader),
+            ]),
+        }
+    }
+}
+
+impl SeaweedSwaySettings {
+    pub fn mode(&self, seaweed_type: SeaweedType) -> SwayMode {
+        self.modes
+            .get(&seaweed_type)
+            .copied()
+            .unwrap_or(SwayMode::Skeletal)
+    }
+}
+
+/// Seaweed that swaps its materials for `SwayMaterial`s once its scene has spawned
+#[derive(Component, Debug)]
+pub struct ShaderSway;
+
+#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
+#[uuid = "3e7b9f52-0d6c-4a8e-b1f4-6c2a9d0e5b17"]
+pub struct SwayMaterial {
+    #[uniform(0)]
+    pub color: Color,
+
+    /// How far the tip moves, in model units
+    #[uniform(0)]
+    pub strength: f32,
+
+    /// Sways per second
+    #[uniform(0)]
+    pub frequency: f32,
+
+    /// The height of the model, above which it bends the full amount
+    #[uniform(0)]
+    pub height: f32,
+
+    /// The direction and strength of the current, in world space. Everything leans with it.
+    #[uniform(0)]
+    pub current: Vec2,
+}
+
+impl Material for SwayMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/sway.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/sway.wgsl".into()
+    }
+}
+
+/// The sway version of each material, so strands with the same look share one
+#[derive(Resource, Default)]
+struct SwayMaterials {
+    materials: HashMap<Handle<StandardMaterial>, Handle<SwayMaterial>>,
+}
+
+fn apply_sway_materials(
+    mut commands: Commands,
+    query: Query<(Entity, &Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    sway_query: Query<(), With<ShaderSway>>,
+    parent_query: Query<&Parent>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut sway_materials: ResMut<Assets<SwayMaterial>>,
+    mut cache: ResMut<SwayMaterials>,
+) {
+    for (entity, standard_material) in query.iter() {
+        let swaying = parent_query
+            .iter_ancestors(entity)
+            .any(|ancestor| sway_query.contains(ancestor));
+        if !swaying {
+            continue;
+        }
+
+        let sway_material = cache
+            .materials
+            .entry(standard_material.clone())
+            .or_insert_with(|| {
+                let color = standard_materials
+                    .get(standard_material)
+                    .map_or(Color::DARK_GREEN, |material| material.base_color);
+
+                sway_materials.add(SwayMaterial {
+                    color,
+                    strength: 0.25,
+                    frequency: 0.6,
+                    height: SEAWEED_HEIGHT,
+                    current: Vec2::ZERO,
+                })
+            })
+            .clone();
+
+        commands
+            .entity(entity)
+            .remove::<Handle<StandardMaterial>>()
+            .insert(sway_material);
+    }
+}
+
+/// Leans all of the seaweed with the current. The play plane's x is the world's x, and the
+/// current's vertical push is left to the sway itself.
+fn follow_current(
+    current_field: Res<CurrentField>,
+    mut sway_materials: ResMut<Assets<SwayMaterial>>,
+    time: Res<Time>,
+) {
+    let target = Vec2::new(current_field.currents.base.x, 0.0);
+    let t = (LEAN_SPEED * time.delta_seconds()).min(1.0);
+
+    for (_, material) in sway_materials.iter_mut() {
+        material.current = material.current.lerp(target, t);
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+
+use crate::{
+    fishy_assets::{FishIconCollection, FontCollection, TextureCollection},
+    hazard::PendingHazard,
+    Bounds, GameState, SimulationSet,
+};
+
+// Pre-announces scheduled hazards with an indicator on the edge of the screen they'll enter from
+pub struct TelegraphPlugin;
+
+impl Plugin for TelegraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                spawn_hazard_indicators,
+                update_hazard_indicators,
+                despawn_hazard_indicators,
+            )
+                .chain()
+                .distributive_run_if(in_state(GameState::Playing))
+                .in_set(SimulationSet::Logic),
+        );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScreenEdge {
+    Left,
+    Right,
+}
+
+/// Marks a pending hazard that's dangerous enough to be announced. Harmless fish, like the prey
+/// in the feeding frenzy, turn up unannounced.
+#[derive(Component, Debug, Default)]
+pub struct Telegraphed;
+
+#[derive(Component, Debug)]
+pub struct HazardIndicator {
+    pub pending: Entity,
+}
+
+#[derive(Component)]
+pub struct HazardIndicatorEta;
+
+const INDICATOR_SIZE: f32 = 32.0;
+const INDICATOR_MARGIN: f32 = 8.0;
+
+/// Works out which screen edge a hazard will enter from and how far down that edge (as a
+/// percentage of the screen height) the indicator should sit.
+pub fn indicator_anchor(bounds: &Bounds, entry_point: Vec2) -> (ScreenEdge, f32) {
+    let center_x = (bounds.min.x + bounds.max.x) / 2.0;
+    let edge = if entry_point.x <= center_x {
+        ScreenEdge::Left
+    } else {
+        ScreenEdge::Right
+    };
+
+    let height = bounds.max.y - bounds.min.y;
+    let top_percent = if height > 0.0 {
+        (bounds.max.y - entry_point.y) / height * 100.0
+    } else {
+        50.0
+    };
+
+    // Keep the indicator fully on screen
+    (edge, top_percent.clamp(0.0, 90.0))
+}
+
+fn indicator_style(edge: ScreenEdge, top_percent: f32) -> Style {
+    let mut position = UiRect {
+        top: Val::Percent(top_percent),
+        ..default()
+    };
+
+    match edge {
+        ScreenEdge::Left => position.left = Val::Px(INDICATOR_MARGIN),
+        ScreenEdge::Right => position.right = Val::Px(INDICATOR_MARGIN),
+    }
+
+    Style {
+        position_type: PositionType::Absolute,
+        position,
+        flex_direction: match edge {
+            ScreenEdge::Left => FlexDirection::Row,
+            ScreenEdge::Right => FlexDirection::RowReverse,
+        },
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+fn spawn_hazard_indicators(
+    mut commands: Commands,
+    pending_query: Query<(Entity, &PendingHazard), (Added<PendingHazard>, With<Telegraphed>)>,
+    bounds: Res<Bounds>,
+    fish_icon_collection: Res<FishIconCollection>,
+    texture_collection: Res<TextureCollection>,
+    font_collection: Res<FontCollection>,
+) {
+    for (entity, pending) in pending_query.iter() {
+        let (edge, top_percent) = indicator_anchor(&bounds, pending.entry_point(&bounds));
+        let icon_size = Size::new(Val::Px(INDICATOR_SIZE), Val::Px(INDICATOR_SIZE));
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: indicator_style(edge, top_percent),
+                    ..default()
+                },
+                HazardIndicator { pending: entity },
+            ))
+            .with_children(|parent| {
+                // The arrow texture points right so flip it to point off the left edge
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        size: icon_size,
+                        ..default()
+                    },
+                    image: UiImage {
+                        texture: texture_collection.arrow.clone(),
+                        flip_x: edge == ScreenEdge::Left,
+                        ..default()
+                    },
+                    ..default()
+                });
+
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        size: icon_size,
+                        margin: UiRect::horizontal(Val::Px(4.0)),
+                        ..default()
+                    },
+                    image: UiImage {
+                        texture: pending.fish_type.icon_from(&fish_icon_collection),
+                        ..default()
+                    },
+                    ..default()
+                });
+
+                parent.spawn((
+                    TextBundle::from_section(
+                        format!("{:.1}s", pending.eta()),
+                        TextStyle {
+                            font: font_collection.bold.clone(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    HazardIndicatorEta,
+                ));
+            });
+    }
+}
+
+fn update_hazard_indicators(
+    mut indicator_query: Query<(&HazardIndicator, &mut Style, &Children)>,
+    mut eta_query: Query<&mut Text, With<HazardIndicatorEta>>,
+    pending_query: Query<&PendingHazard>,
+    bounds: Res<Bounds>,
+) {
+    for (indicator, mut style, children) in indicator_query.iter_mut() {
+        let Ok(pending) = pending_query.get(indicator.pending) else {
+            continue;
+        };
+
+        // The bounds follow the camera so the anchor has to be recomputed every frame
+        let (edge, top_percent) = indicator_anchor(&bounds, pending.entry_point(&bounds));
+        *style = indicator_style(edge, top_percent);
+
+        for child in children.iter() {
+            let Ok(mut text) = eta_query.get_mut(*child) else {
+                continue;
+            };
+
+            text.sections[0].value = format!("{:.1}s", pending.eta());
+        }
+    }
+}
+
+fn despawn_hazard_indicators(
+    mut commands: Commands,
+    indicator_query: Query<(Entity, &HazardIndicator)>,
+    pending_query: Query<&PendingHazard>,
+) {
+    for (entity, indicator) in indicator_query.iter() {
+        if pending_query.get(indicator.pending).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-3;
+
+    fn bounds() -> Bounds {
+        Bounds {
+            min: Vec2::new(-8.0, -6.0),
+            max: Vec2::new(8.0, 6.0),
+        }
+    }
+
+    fn assert_anchor(entry_point: Vec2, edge: ScreenEdge, top_percent: f32) {
+        let (actual_edge, actual_top_percent) = indicator_anchor(&bounds(), entry_point);
+
+        assert_eq!(actual_edge, edge, "wrong edge for {entry_point}");
+        assert!(
+            (actual_top_percent - top_percent).abs() < TOLERANCE,
+            "expected {top_percent}% down for {entry_point}, got {actual_top_percent}%"
+        );
+    }
+
+    #[test]
+    fn entering_from_the_left_is_announced_on_the_left() {
+        assert_anchor(Vec2::new(-10.0, 0.0), ScreenEdge::Left, 50.0);
+        assert_anchor(Vec2::new(-10.0, 3.0), ScreenEdge::Left, 25.0);
+    }
+
+    #[test]
+    fn entering_from_the_right_is_announced_on_the_right() {
+        assert_anchor(Vec2::new(10.0, 0.0), ScreenEdge::Right, 50.0);
+        assert_anchor(Vec2::new(10.0, -3.0), ScreenEdge::Right, 75.0);
+    }
+
+    #[test]
+    fn entering_from_the_top_or_bottom_is_announced_at_that_end_of_the_nearer_side() {
+        assert_anchor(Vec2::new(-4.0, 8.0), ScreenEdge::Left, 0.0);
+        assert_anchor(Vec2::new(4.0, -8.0), ScreenEdge::Right, 90.0);
+    }
+
+    #[test]
+    fn the_middle_counts_as_the_left() {
+        assert_anchor(Vec2::new(0.0, 0.0), ScreenEdge::Left, 50.0);
+    }
+
+    #[test]
+    fn corners_stay_on_screen() {
+        assert_anchor(Vec2::new(-8.0, 6.0), ScreenEdge::Left, 0.0);
+        assert_anchor(Vec2::new(8.0, 6.0), ScreenEdge::Right, 0.0);
+        assert_anchor(Vec2::new(-8.0, -6.0), ScreenEdge::Left, 90.0);
+        assert_anchor(Vec2::new(8.0, -6.0), ScreenEdge::Right, 90.0);
+        assert_anchor(Vec2::new(-12.0, 10.0), ScreenEdge::Left, 0.0);
+        assert_anchor(Vec2::new(12.0, -10.0), ScreenEdge::Right, 90.0);
+    }
+
+    #[test]
+    fn flat_bounds_put_the_indicator_half_way_down() {
+        let bounds = Bounds {
+            min: Vec2::new(-8.0, 0.0),
+            max: Vec2::new(8.0, 0.0),
+        };
+
+        assert_eq!(
+            indicator_anchor(&bounds, Vec2::new(10.0, 0.0)),
+            (ScreenEdge::Right, 50.0)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+use rand::Rng;
+
+use crate::{GameState, SimulationSet, Terrain};
+
+// Caustics dancing over the seabed and shafts of light coming down from the surface
+pub struct WaterEffectsPlugin;
+
+impl Plugin for WaterEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MaterialPlugin::<CausticsMaterial>::default())
+            .add_plugin(MaterialPlugin::<LightShaftMaterial>::default())
+            .init_resource::<WaterEffectsSettings>()
+            .add_system(spawn_light_shafts.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems(
+                (
+                    add_caustics_to_terrain,
+                    toggle_water_effects,
+                    apply_water_effects_settings,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .in_set(SimulationSet::Logic),
+            );
+    }
+}
+
+const MAX_LIGHT_SHAFTS: usize = 14;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WaterEffectsQuality {
+    Low,
+    High,
+}
+
+impl WaterEffectsQuality {
+    /// Noise octaves layered into the caustics
+    fn caustic_octaves(&self) -> u32 {
+        match self {
+            WaterEffectsQuality::Low => 1,
+            WaterEffectsQuality::High => 3,
+        }
+    }
+
+    fn light_shafts(&self) -> usize {
+        match self {
+            WaterEffectsQuality::Low => 6,
+            WaterEffectsQuality::High => MAX_LIGHT_SHAFTS,
+        }
+    }
+}
+
+/// F4 turns the effects on and off, F5 switches quality
+#[derive(Resource, Debug)]
+pub struct WaterEffectsSettings {
+    pub enabled: bool,
+
+    pub quality: WaterEffectsQuality,
+}
+
+impl Default for WaterEffectsSettings {
+    fn default() -> WaterEffectsSettings {
+        WaterEffectsSettings {
+            enabled: true,
+            // The web build can't afford the extra noise
+            quality: if cfg!(target_arch = "wasm32") {
+                WaterEffectsQuality::Low
+            } else {
+                WaterEffectsQuality::High
+            },
+        }
+    }
+}
+
+/// Added on top of the seabed so the lighting underneath is left alone
+#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
+#[uuid = "5c1d8b7e-52a4-4c1f-9d38-0f6f0a8f2b61"]
+pub struct CausticsMaterial {
+    #[uniform(0)]
+    pub color: Color,
+
+    #[uniform(0)]
+    pub intensity: f32,
+
+    /// Caustic cells per world unit
+    #[uniform(0)]
+    pub scale: f32,
+
+    #[uniform(0)]
+    pub speed: f32,
+
+    #[uniform(0)]
+    pub octaves: u32,
+}
+
+impl Material for CausticsMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/caustics.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Add
+    }
+
+    fn depth_bias(&self) -> f32 {
+        1.0
+    }
+}
+
+#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
+#[uuid = "a3f0c2d4-7b8e-4e59-8a61-2d94c7e1b0f3"]
+pub struct LightShaftMaterial {
+    #[uniform(0)]
+    pub color: Color,
+
+    #[uniform(0)]
+    pub intensity: f32,
+
+    /// Keeps the shafts from flickering in sync
+    #[uniform(0)]
+    pub seed: f32,
+}
+
+impl Material for LightShaftMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/light_shaft.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Add
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct LightShaft {
+    pub index: usize,
+}
+
+/// The caustics layer laid over the seabed. It's hidden rather than faded out when the effects
+/// are off, so the extra pass isn't drawn at all.
+#[derive(Component, Debug)]
+pub struct Caustics;
+
+fn caustics_visibility(settings: &WaterEffectsSettings) -> Visibility {
+    if settings.enabled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+/// The strength the effects are drawn at when they're on
+const CAUSTICS_INTENSITY: f32 = 0.6;
+const LIGHT_SHAFT_INTENSITY: f32 = 0.25;
+
+fn spawn_light_shafts(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<LightShaftMaterial>>,
+) {
+    let mesh = meshes.add(shape::Quad::new(Vec2::new(3.0, 40.0)).into());
+    let mut rng = rand::thread_rng();
+
+    for index in 0..MAX_LIGHT_SHAFTS {
+        let x = -60.0 + index as f32 * (120.0 / MAX_LIGHT_SHAFTS as f32) + rng.gen_range(-2.0..2.0);
+
+        commands.spawn((
+            MaterialMeshBundle {
+                mesh: mesh.clone(),
+                material: materials.add(LightShaftMaterial {
+                    color: Color::hex("e0f7ff").unwrap(),
+                    intensity: LIGHT_SHAFT_INTENSITY,
+                    seed: rng.gen_range(0.0..100.0),
+                }),
+                // Hanging from the surface and leaning a little, as if the sun is off to one side
+                transform: Transform::from_xyz(x, 4.0, rng.gen_range(-10.0..-4.0))
+                    .with_rotation(Quat::from_rotation_z(rng.gen_range(0.15..0.3)))
+                    .with_scale(Vec3::new(rng.gen_range(0.6..1.6), 1.0, 1.0)),
+                ..default()
+            },
+            LightShaft { index },
+        ));
+    }
+}
+
+fn add_caustics_to_terrain(
+    mut commands: Commands,
+    query: Query<(Entity, &Handle<Mesh>), Added<Terrain>>,
+    mut materials: ResMut<Assets<CausticsMaterial>>,
+    settings: Res<WaterEffectsSettings>,
+) {
+    for (entity, mesh) in query.iter() {
+        let material = materials.add(CausticsMaterial {
+            color: Color::hex("bff3ff").unwrap(),
+            intensity: CAUSTICS_INTENSITY,
+            scale: 0.35,
+            speed: 0.4,
+            octaves: settings.quality.caustic_octaves(),
+        });
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                MaterialMeshBundle {
+                    mesh: mesh.clone(),
+                    material,
+                    visibility: caustics_visibility(&settings),
+                    ..default()
+                },
+                Caustics,
+            ));
+        });
+    }
+}
+
+fn toggle_water_effects(keys: Res<Input<KeyCode>>, mut settings: ResMut<WaterEffectsSettings>) {
+    if keys.just_pressed(KeyCode::F4) {
+        settings.enabled = !settings.enabled;
+    }
+
+    if keys.just_pressed(KeyCode::F5) {
+        settings.quality = match settings.quality {
+            WaterEffectsQuality::Low => WaterEffectsQuality::High,
+            WaterEffectsQuality::High => WaterEffectsQuality::Low,
+        };
+    }
+}
+
+fn apply_water_effects_settings(
+    settings: Res<WaterEffectsSettings>,
+    mut caustics_materials: ResMut<Assets<CausticsMaterial>>,
+    mut caustics_query: Query<&mut Visibility, (With<Caustics>, Without<LightShaft>)>,
+    mut shaft_query: Query<(&LightShaft, &mut Visibility)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (_, material) in caustics_materials.iter_mut() {
+        material.octaves = settings.quality.caustic_octaves();
+    }
+
+    for mut visibility in caustics_query.iter_mut() {
+        *visibility = caustics_visibility(&settings);
+    }
+
+    let shafts = if settings.enabled {
+        settings.quality.light_shafts()
+    } else {
+        0
+    };
+
+    for (shaft, mut visibility) in shaft_query.iter_mut() {
+        // Spread whichever shafts are left across the whole row
+        *visibility = if (shaft.index * shafts) % MAX_LIGHT_SHAFTS < shafts {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
@@ -0,0 +1,38 @@
+use std::{env, fs, path::Path, process::Command};
+
+/// Plays `replay` back without a window, recording it again to `record` if given, and returns
+/// whether it matched
+fn play_back(replay: &Path, record: Option<&Path>) -> bool {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_fishy"));
+    command.arg("--headless").arg("--replay").arg(replay);
+    if let Some(record) = record {
+        command.arg("--record").arg(record);
+    }
+
+    command.status().expect("Couldn't start the game").success()
+}
+
+#[test]
+fn recorded_runs_play_back_the_same() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/replays/classic.ron");
+
+    // Without an outcome the fixture can't catch a build that plays it back differently
+    let contents = fs::read_to_string(&fixture).expect("The fixture is missing");
+    let contents = contents.split_whitespace().collect::<String>();
+    assert!(
+        contents.contains("outcome:Some"),
+        "Record the fixture's outcome, see the comment at the top of it"
+    );
+
+    let recording = env::temp_dir().join(format!("fishy-{}.ron", std::process::id()));
+
+    assert!(play_back(&fixture, Some(&recording)));
+
+    let contents = fs::read_to_string(&recording).expect("The replay wasn't recorded");
+    let contents = contents.split_whitespace().collect::<String>();
+    assert!(contents.contains("outcome:Some"));
+
+    let matched = play_back(&recording, None);
+    fs::remove_file(&recording).ok();
+    assert!(matched);
+}